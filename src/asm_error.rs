@@ -0,0 +1,50 @@
+use std::fmt;
+
+// An error in an assembly source, found by get_tokens, or by byte_generator for words that depend
+// on labels. The parser skips the rest of the line that an error is on and carries on, so that
+// every error in a file can be reported at once.
+// Displays in the style of gcc and rustc, with the line and a caret under the column:
+//     main.asm:12:9: error: unrecognized instruction FOO
+//        12 |         FOO r4
+//           |         ^
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file_name: String,
+    pub line: usize,     // counting from 1
+    pub col: usize,      // counting from 1, in characters
+    pub snippet: String, // the text of the line
+    pub message: String,
+}
+
+impl AsmError {
+    pub fn set_source(&mut self, file_name: &str, source: &str) {
+        // fills in an error from byte_generator, which only knows the line. the caret goes under
+        // the start of the text on the line
+        self.file_name = file_name.to_owned();
+        self.snippet = source.lines().nth(self.line - 1).unwrap_or("").to_owned();
+        self.col = self
+            .snippet
+            .chars()
+            .position(|c| !c.is_whitespace())
+            .map_or(1, |i| i + 1);
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}:{}:{}: error: {}",
+            self.file_name, self.line, self.col, self.message
+        )?;
+        writeln!(f, "{:>6} | {}", self.line, self.snippet)?;
+        // tabs are kept, so that the caret lines up with the snippet above it
+        let indent: String = self
+            .snippet
+            .chars()
+            .take(self.col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(f, "{:>6} | {}^", "", indent)
+    }
+}
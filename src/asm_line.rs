@@ -1,11 +1,14 @@
+use std::fmt;
+
 use crate::ccode::CC;
-use crate::operand::Operand;
+use crate::expr::Expr;
+use crate::operand::{Operand, Reg};
 
 #[derive(Debug, Clone)]
 pub enum AsmLine {
     Label(String),
 
-    Jump(CC, String), // conditional and unconditional jumps
+    Jump(CC, Expr), // conditional and unconditional jumps, to a label or an expression
 
     RRC(Operand, bool), // boolean: 1 for byte, 0 for word
     SWPB(Operand, bool),
@@ -35,4 +38,67 @@ impl AsmLine {
             _ => unreachable!(),
         }
     }
+
+    pub fn get_operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            AsmLine::Label(_) | AsmLine::Jump(_, _) | AsmLine::RETI => vec![],
+            AsmLine::RRC(op, _)
+            | AsmLine::SWPB(op, _)
+            | AsmLine::RRA(op, _)
+            | AsmLine::SXT(op, _)
+            | AsmLine::PUSH(op, _)
+            | AsmLine::CALL(op, _) => vec![op],
+            AsmLine::MOV(src, dst, _)
+            | AsmLine::ADD(src, dst, _)
+            | AsmLine::ADDC(src, dst, _)
+            | AsmLine::SUB(src, dst, _)
+            | AsmLine::SUBC(src, dst, _)
+            | AsmLine::CMP(src, dst, _)
+            | AsmLine::DADD(src, dst, _)
+            | AsmLine::BIT(src, dst, _)
+            | AsmLine::BIC(src, dst, _)
+            | AsmLine::BIS(src, dst, _)
+            | AsmLine::XOR(src, dst, _)
+            | AsmLine::AND(src, dst, _) => vec![src, dst],
+        }
+    }
+}
+
+impl fmt::Display for AsmLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // writes the line in the syntax that get_tokens reads. get_tokens can't read PC or R3
+        // as operands, so the emulated instructions that use them are written instead.
+        let suffix = |is_byte_instr: &bool| if *is_byte_instr { ".B" } else { ".W" };
+        match self {
+            AsmLine::MOV(Operand::IndirectAutoInc(Reg::SP), Operand::Reg(Reg::PC), false) => {
+                write!(f, "RET")
+            }
+            AsmLine::MOV(src, Operand::Reg(Reg::PC), false) => write!(f, "BR {}", src),
+            AsmLine::MOV(Operand::Imm(0), Operand::Reg(Reg::CG), false) => write!(f, "NOP"),
+
+            AsmLine::Label(s) => write!(f, "{}:", s),
+            AsmLine::Jump(cc, target) => write!(f, "{} {}", cc.get_mnemonic(), target),
+
+            AsmLine::RRC(op, b) => write!(f, "RRC{} {}", suffix(b), op),
+            AsmLine::SWPB(op, _) => write!(f, "SWPB {}", op),
+            AsmLine::RRA(op, b) => write!(f, "RRA{} {}", suffix(b), op),
+            AsmLine::SXT(op, _) => write!(f, "SXT {}", op),
+            AsmLine::PUSH(op, b) => write!(f, "PUSH{} {}", suffix(b), op),
+            AsmLine::CALL(op, _) => write!(f, "CALL {}", op),
+            AsmLine::RETI => write!(f, "RETI"),
+
+            AsmLine::MOV(src, dst, b) => write!(f, "MOV{} {},{}", suffix(b), src, dst),
+            AsmLine::ADD(src, dst, b) => write!(f, "ADD{} {},{}", suffix(b), src, dst),
+            AsmLine::ADDC(src, dst, b) => write!(f, "ADDC{} {},{}", suffix(b), src, dst),
+            AsmLine::SUB(src, dst, b) => write!(f, "SUB{} {},{}", suffix(b), src, dst),
+            AsmLine::SUBC(src, dst, b) => write!(f, "SUBC{} {},{}", suffix(b), src, dst),
+            AsmLine::CMP(src, dst, b) => write!(f, "CMP{} {},{}", suffix(b), src, dst),
+            AsmLine::DADD(src, dst, b) => write!(f, "DADD{} {},{}", suffix(b), src, dst),
+            AsmLine::BIT(src, dst, b) => write!(f, "BIT{} {},{}", suffix(b), src, dst),
+            AsmLine::BIC(src, dst, b) => write!(f, "BIC{} {},{}", suffix(b), src, dst),
+            AsmLine::BIS(src, dst, b) => write!(f, "BIS{} {},{}", suffix(b), src, dst),
+            AsmLine::XOR(src, dst, b) => write!(f, "XOR{} {},{}", suffix(b), src, dst),
+            AsmLine::AND(src, dst, b) => write!(f, "AND{} {},{}", suffix(b), src, dst),
+        }
+    }
 }
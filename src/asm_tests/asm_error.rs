@@ -0,0 +1,144 @@
+use crate::{
+    asm_error::AsmError, byte_generator::generate_bytes_symbols_and_addrs,
+    get_verbs::try_get_tokens,
+};
+
+#[test]
+fn test_asm_errors() {
+    let source = "main:
+    MOV #3,r15
+    FOO r15
+    MOV #3 r15
+    ADD r4,@r5
+    JNE
+counter:
+    MOV &counter+,r14
+    CALL #func ; a comment
+flags:
+    .bits 0x1F,12
+    .word 70000
+    MOV r4,r5 r6
+    RET
+";
+    let errors = try_get_tokens("main.asm", source.to_owned()).unwrap_err();
+    let errors: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.col, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (3, 5, "unrecognized instruction FOO"),
+            (4, 11, "expected ,"),
+            (5, 12, "@r5 can't be a destination"),
+            (6, 8, "expected a label to jump to"),
+            (8, 18, "expected a number or a label"),
+            (
+                11,
+                11,
+                "expected an initial value and a number of bits, such as 0x1F,16"
+            ),
+            (12, 5, "70000 doesn't fit in 16 bits"),
+            (13, 15, "unexpected r after instruction"),
+        ]
+    );
+
+    let (_, lines, _) = try_get_tokens("main.asm", "main:\n    RET\n".to_owned()).unwrap();
+    assert_eq!(lines.len(), 2);
+}
+
+#[test]
+fn test_asm_error_display() {
+    let errors = try_get_tokens("main.asm", "main:\n\tMOV.W\t#1,#2\n".to_owned()).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![AsmError {
+            file_name: "main.asm".to_owned(),
+            line: 2,
+            col: 11,
+            snippet: "\tMOV.W\t#1,#2".to_owned(),
+            message: "#2 can't be a destination".to_owned(),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "main.asm:2:11: error: #2 can't be a destination
+     2 | \tMOV.W\t#1,#2
+       | \t     \t   ^"
+    );
+}
+
+#[test]
+fn test_label_before_instr() {
+    let source = "main:\tMOV.W #7, r5\n\tRET\n";
+    let (_, lines, line_nums) = try_get_tokens("main.asm", source.to_owned()).unwrap();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(line_nums, vec![1, 1, 2]);
+
+    let source = "\t.data\nbuffer:\tMOV.W #7, r5\n";
+    let errors = try_get_tokens("main.asm", source.to_owned()).unwrap_err();
+    assert_eq!(
+        (errors[0].line, errors[0].col, errors[0].message.as_str()),
+        (2, 9, "MOV.W is not in a .text section")
+    );
+}
+
+#[test]
+fn test_undefined_labels() {
+    let source = "main:
+\tMOV.W #undefined_thing, r4
+\tJMP main
+\tMOV.W 2(r4),missing+2
+table:
+\t.word main,nowhere
+";
+    let (globals, lines, line_nums) = try_get_tokens("main.asm", source.to_owned()).unwrap();
+    let mut errors = generate_bytes_symbols_and_addrs(globals, lines, &line_nums).unwrap_err();
+    for error in &mut errors {
+        error.set_source("main.asm", source);
+    }
+    let errors: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.col, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (2, 2, "unresolved label undefined_thing"),
+            (4, 2, "unresolved label missing"),
+            (6, 2, "unresolved label nowhere"),
+        ]
+    );
+
+    let errors = generate_bytes_symbols_and_addrs(Vec::new(), Vec::new(), &[]).unwrap_err();
+    assert_eq!(
+        (errors[0].line, errors[0].message.as_str()),
+        (1, "unresolved label main")
+    );
+}
+
+#[test]
+fn test_unaligned_jump_targets() {
+    let source = "main:
+\tJMP $+3
+\tJNE odd
+\tJMP main
+flags:
+\t.byte 1
+odd:
+\t.byte 2
+";
+    let (globals, lines, line_nums) = try_get_tokens("main.asm", source.to_owned()).unwrap();
+    let errors = generate_bytes_symbols_and_addrs(globals, lines, &line_nums).unwrap_err();
+    let errors: Vec<(usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (2, "jump target $+0x0003 (0x000B) is not word aligned"),
+            (3, "jump target odd (0x0007) is not word aligned"),
+        ]
+    );
+}
@@ -0,0 +1,88 @@
+use crate::{
+    byte_generator::generate_bytes_and_symbols,
+    emulator::Emulator,
+    get_verbs::{get_tokens, try_get_tokens},
+};
+
+// in the style of cl430 output, with string literals and const tables in their own sections
+const SOURCE: &str = "SIZE\t.set\t3
+\t.sect\t\".const\"
+\t.align\t2
+names:
+\t.word\t$C$SL1,msg
+$C$SL1:\t.string\t\"hi\",0
+msg\t.cstring\t\"ok\"
+\t.data
+\t.align\t2
+counts:
+\t.field\t1,16
+\t.field\t0x10,8
+\t.word\tSIZE
+\t.bss\tbuffer,4,2
+\t.text
+\t.global\tmain
+main:
+\tMOV #SIZE,r15
+\tMOV &names+0,r14
+\tMOV.B &msg+0,r13
+end:
+\tJMP end
+";
+
+#[test]
+fn test_data_layout() {
+    let (globals, lines) = get_tokens(SOURCE.to_owned());
+    let (bytes, symbols) = generate_bytes_and_symbols(globals, lines);
+
+    // the data follows the startup code, with words aligned on even addresses
+    assert_eq!(
+        bytes[0x0006..0x001A],
+        [
+            0x0A, 0x00, 0x0D, 0x00, // names
+            b'h', b'i', 0x00, // $C$SL1
+            b'o', b'k', 0x00, // msg
+            0x01, 0x00, 0x10, 0x00, 0x03, 0x00, // counts
+            0x00, 0x00, 0x00, 0x00, // buffer
+        ]
+    );
+    assert_eq!(symbols["names"], 0x0006);
+    assert_eq!(symbols["counts"], 0x0010);
+    assert_eq!(symbols["buffer"], 0x0016);
+    assert_eq!(symbols["main"], 0x001A);
+    assert!(!symbols.contains_key("SIZE"));
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..5 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_regs()[15], 3);
+    assert_eq!(cpu_emu.get_regs()[14], 0x000A);
+    assert_eq!(cpu_emu.get_regs()[13], b'o' as u16);
+}
+
+#[test]
+fn test_data_directive_errors() {
+    let source = "\t.data
+table:
+\t.word 1,70000
+\t.byte x
+\tMOV r4,r5
+\t.align 3
+\t.text
+\tMOV r4,r5
+";
+    let errors = try_get_tokens("main.asm", source.to_owned()).unwrap_err();
+    let errors: Vec<(usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (3, "70000 doesn't fit in 16 bits"),
+            (4, "expected a number from -128 to 255, not x"),
+            (5, "MOV is not in a .text section"),
+            (6, "can't align to 3 bytes"),
+        ]
+    );
+}
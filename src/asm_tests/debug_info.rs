@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::{
+    byte_generator::generate_bytes_symbols_and_addrs,
+    debug_info::{DebugInfo, Function, VarLocation, Variable},
+    get_verbs::{debug_directives::get_debug_info, get_tokens_with_line_nums},
+    source_map::SourceMap,
+};
+
+// in the style of cl430 --symdebug:dwarf output
+pub(crate) const SOURCE: &str = "cursor_location:
+\t.bits\t0,16
+next_player:
+\t.bits\t1,8
+\t.sect\t\".text:main\"
+\t.global\tmain
+$C$DW$1\t.dwtag  DW_TAG_subprogram
+\t.dwattr $C$DW$1, DW_AT_name(\"main\")
+\t.dwattr $C$DW$1, DW_AT_low_pc(main)
+\t.dwattr $C$DW$1, DW_AT_TI_symbol_name(\"main\")
+\t.dwpsn\tfile \"main.c\",line 5,column 1,is_stmt,isa 0
+main:
+\tSUB #2,SP
+\t.dwpsn\tfile \"main.c\",line 7,column 5,is_stmt,isa 0
+\tMOV #3,0(SP)
+\t.dwpsn\tfile \"main.c\",line 8,column 5,is_stmt,isa 0
+\tADD #1,&cursor_location+0
+\t.dwpsn\tfile \"main.c\",line 9,column 5,is_stmt,isa 0
+\tMOV.B #2,&next_player+0
+$C$L1:
+\tJMP $C$L1
+$C$DW$2\t.dwtag  DW_TAG_variable, DW_AT_name(\"count\")
+\t.dwattr $C$DW$2, DW_AT_location[DW_OP_breg1 0]
+\t.dwattr $C$DW$2, DW_AT_type(*$C$DW$T$10)
+\t.dwendtag $C$DW$1
+$C$DW$3\t.dwtag  DW_TAG_variable, DW_AT_name(\"cursor_location\")
+\t.dwattr $C$DW$3, DW_AT_location[DW_OP_addr cursor_location]
+\t.dwattr $C$DW$3, DW_AT_type(*$C$DW$T$10)
+$C$DW$4\t.dwtag  DW_TAG_variable, DW_AT_name(\"next_player\")
+\t.dwattr $C$DW$4, DW_AT_location[DW_OP_addr next_player]
+\t.dwattr $C$DW$4, DW_AT_type(*$C$DW$T$20)
+$C$DW$5\t.dwtag  DW_TAG_variable, DW_AT_name(\"board\")
+\t.dwattr $C$DW$5, DW_AT_declaration
+$C$DW$T$6\t.dwtag  DW_TAG_base_type
+\t.dwattr $C$DW$T$6, DW_AT_encoding(DW_ATE_unsigned_char)
+\t.dwattr $C$DW$T$6, DW_AT_byte_size(0x01)
+$C$DW$T$10\t.dwtag  DW_TAG_base_type
+\t.dwattr $C$DW$T$10, DW_AT_byte_size(0x02)
+$C$DW$T$20\t.dwtag  DW_TAG_typedef, DW_AT_name(\"player_t\")
+\t.dwattr $C$DW$T$20, DW_AT_type(*$C$DW$T$6)
+";
+
+pub(crate) fn assemble() -> (Vec<u8>, HashMap<String, u16>, SourceMap) {
+    let (globals, lines, line_nums) = get_tokens_with_line_nums(SOURCE.to_owned());
+    let (bytes, symbols, addrs) =
+        generate_bytes_symbols_and_addrs(globals, lines.clone(), &line_nums).unwrap();
+    let source_map = SourceMap::new(SOURCE, &lines, &line_nums, &addrs);
+    (bytes, symbols, source_map)
+}
+
+#[test]
+fn test_debug_directives() {
+    let variable = |name: &str, location, is_byte| Variable {
+        name: name.to_owned(),
+        location,
+        is_byte,
+    };
+    assert_eq!(
+        get_debug_info(SOURCE),
+        DebugInfo {
+            globals: vec![
+                variable(
+                    "cursor_location",
+                    VarLocation::Label("cursor_location".to_owned()),
+                    false
+                ),
+                variable(
+                    "next_player",
+                    VarLocation::Label("next_player".to_owned()),
+                    true
+                ),
+            ],
+            functions: vec![Function {
+                name: "main".to_owned(),
+                locals: vec![variable("count", VarLocation::RegOffset(1, 0), false)],
+            }],
+        }
+    );
+
+    let (_, _, source_map) = assemble();
+    assert_eq!(source_map.get_c_file_name(), Some("main.c"));
+    assert_eq!(source_map.get_c_line_at(0x000C), Some(7));
+    assert_eq!(source_map.find_c_line_addr(8), Some(0x0012));
+    assert_eq!(source_map.get_c_line_at(0x0000), None);
+}
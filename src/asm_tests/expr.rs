@@ -0,0 +1,129 @@
+use crate::{
+    byte_generator::generate_bytes,
+    emulator::Emulator,
+    get_verbs::{get_tokens, try_get_tokens},
+};
+
+const SOURCE: &str = "SIZE .equ 2*3+1
+MASK .set (1<<4) | 0b0011
+\t.data
+table:
+\t.word 0x10,'A',~0&0xFF,table+4,SIZE*2
+\t.text
+main:
+\tMOV #SIZE*2-1,r4
+\tMOV #MASK&~1,r5
+\tMOV table+2,r6
+\tMOV #table,r7
+\tMOV 2*2(r7),r8
+\tMOV #2,r10
+\tMOV table+4(r10),r9
+\tMOV &table+8,r11
+\tMOV #$,r12
+\tJMP $+4
+\tMOV #1,r13
+\tMOV #LATER,r14
+end:
+\tJMP end
+LATER .equ 'z'-'a'
+";
+
+#[test]
+fn test_expressions() {
+    let (globals, lines) = get_tokens(SOURCE.to_owned());
+    let bytes = generate_bytes(globals, lines);
+
+    let words: Vec<u16> = bytes[0x0006..0x0010]
+        .chunks(2)
+        .map(|w| u16::from_le_bytes([w[0], w[1]]))
+        .collect();
+    assert_eq!(words, vec![0x0010, 0x0041, 0x00FF, 0x000A, 0x000E]);
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..13 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_regs()[4], 13);
+    assert_eq!(cpu_emu.get_regs()[5], 0x12);
+    assert_eq!(cpu_emu.get_regs()[6], 0x41);
+    assert_eq!(cpu_emu.get_regs()[7], 0x0006);
+    assert_eq!(cpu_emu.get_regs()[8], 0xFF);
+    assert_eq!(cpu_emu.get_regs()[9], 0x0A);
+    assert_eq!(cpu_emu.get_regs()[11], 14);
+    assert_eq!(cpu_emu.get_regs()[12], 0x002E); // the address of the MOV
+    assert_eq!(cpu_emu.get_regs()[13], 0); // skipped by JMP $+4
+    assert_eq!(cpu_emu.get_regs()[14], 25);
+    assert_eq!(cpu_emu.get_regs()[0], 0x003A);
+}
+
+#[test]
+fn test_expression_errors() {
+    let source = "main:
+\tMOV #1/0,r4
+\tMOV #(1+2,r4
+\tMOV #'ab',r4
+\tMOV #70000,r4
+\tMOV #0x1G,r4
+\tMOV #2*,r4
+";
+    let errors = try_get_tokens("main.asm", source.to_owned()).unwrap_err();
+    let errors: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.col, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (2, 8, "division by zero"),
+            (3, 11, "expected )"),
+            (4, 7, "expected a character such as 'a'"),
+            (5, 6, "70000 doesn't fit in 16 bits"),
+            (6, 7, "invalid number 0x1G"),
+            (7, 9, "expected a number or a label"),
+        ]
+    );
+}
+
+#[test]
+fn test_constants_defined_later() {
+    let source = "A .set B+1
+main:
+\tMOV #A,r4
+\tMOV #C,r5
+B .set 2*C
+C .equ table+2
+table:
+\t.word A
+";
+    let (globals, lines) = get_tokens(source.to_owned());
+    let bytes = generate_bytes(globals, lines);
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..4 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    let table = 0x0006;
+    assert_eq!(cpu_emu.get_regs()[5], table + 2);
+    assert_eq!(cpu_emu.get_regs()[4], 2 * (table + 2) + 1);
+    assert_eq!(cpu_emu.bus.peek_word(table), 2 * (table + 2) + 1);
+
+    let source = "main:
+\tMOV #A,r4
+A .set B+1
+B .set A*2
+C .set B
+D .set D
+";
+    let errors = try_get_tokens("main.asm", source.to_owned()).unwrap_err();
+    let errors: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.col, e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (4, 3, "B is defined in terms of itself"),
+            (6, 3, "D is defined in terms of itself"),
+        ]
+    );
+}
@@ -0,0 +1,77 @@
+use crate::{
+    byte_generator::{generate_bytes, generate_bytes_and_symbols},
+    emulator::Emulator,
+    get_verbs::get_tokens,
+};
+
+#[test]
+fn test_long_jumps() {
+    // the INCs put far more than 512 words between the jumps and far
+    let source = format!(
+        "main:
+\tJEQ far
+\tMOV #1,r6
+\tCMP #1,r4
+\tJN far
+\tMOV #1,r7
+back:
+\tMOV #1,r9
+end:
+\tJMP end
+{}far:
+\tMOV #1,r8
+\tJMP back
+",
+        "\tINC r5\n".repeat(600)
+    );
+    let (globals, lines) = get_tokens(source);
+    let (bytes, symbols) = generate_bytes_and_symbols(globals, lines);
+
+    let words: Vec<u16> = bytes[0x0006..0x0018]
+        .chunks(2)
+        .map(|w| u16::from_le_bytes([w[0], w[1]]))
+        .collect();
+    let far = symbols["far"] - 4;
+    assert_eq!(
+        words,
+        vec![
+            0x2002, 0x4030, far,    // JNE $+6, BR #far
+            0x4316, // MOV #1,r6
+            0x9314, // CMP #1,r4
+            0x3001, 0x3C02, 0x4030, far, // JN $+4, JMP $+6, BR #far
+        ]
+    );
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..12 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_regs()[0], symbols["end"]);
+    assert_eq!(
+        [5, 6, 7, 8, 9].map(|r| cpu_emu.get_regs()[r]),
+        [0, 1, 0, 1, 1]
+    );
+}
+
+#[test]
+fn test_long_jump_to_main() {
+    // the globals come between the startup code and main
+    let source = "\t.data
+buffer:
+\t.space 1100
+\t.text
+main:
+\tMOV #5,r4
+end:
+\tJMP end
+";
+    let (globals, lines) = get_tokens(source.to_owned());
+    let bytes = generate_bytes(globals, lines);
+    assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 0x4030);
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..3 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_regs()[4], 5);
+}
@@ -0,0 +1,62 @@
+use crate::{
+    byte_generator::generate_bytes_symbols_and_addrs,
+    get_verbs::get_tokens_with_line_nums,
+    listing::{get_symbol_table, write_listing, write_map},
+    source_map::SourceMap,
+};
+
+const SOURCE: &str = "score:
+    .bits 0x0102,16
+main:
+    MOV &score+0,r15
+    CALL #add_one
+end:
+    JMP end
+add_one:
+$C$L1:
+    INC r15
+    RET
+";
+
+#[test]
+fn test_map_file() {
+    let (globals, lines, line_nums) = get_tokens_with_line_nums(SOURCE.to_owned());
+    let (bytes, symbols, _) =
+        generate_bytes_symbols_and_addrs(globals.clone(), lines, &line_nums).unwrap();
+    let mut out = Vec::new();
+    write_map(&mut out, &get_symbol_table(&globals, &symbols, bytes.len())).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "address    size  kind      symbol
+0006          2  global    score
+0008          8  function  main
+0010          2  function  end
+0012          4  function  add_one
+"
+    );
+}
+
+#[test]
+fn test_listing_file() {
+    let (globals, lines, line_nums) = get_tokens_with_line_nums(SOURCE.to_owned());
+    let (bytes, _, addrs) =
+        generate_bytes_symbols_and_addrs(globals, lines.clone(), &line_nums).unwrap();
+    let source_map = SourceMap::new(SOURCE, &lines, &line_nums, &addrs);
+    let mut out = Vec::new();
+    write_listing(&mut out, &source_map, &bytes).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "    1                        score:
+    2                            .bits 0x0102,16
+    3                        main:
+    4  0008  421F 0006           MOV &score+0,r15
+    5  000C  12B0 0010           CALL #add_one
+    6                        end:
+    7  0010  3FFF                JMP end
+    8                        add_one:
+    9                        $C$L1:
+   10  0012  531F                INC r15
+   11  0014  4130                RET
+"
+    );
+}
@@ -0,0 +1,9 @@
+// Tests of the assembler, some of which run the assembled program to check it. The tests of the
+// emulator itself are in emulator::tests.
+pub mod asm_error;
+pub mod data_directives;
+pub mod debug_info;
+pub mod expr;
+pub mod jump_relaxation;
+pub mod listing;
+pub mod symbolic;
@@ -0,0 +1,91 @@
+use crate::{
+    byte_generator::generate_bytes_and_symbols,
+    disassembler::disassemble_to_string,
+    emulator::Emulator,
+    get_verbs::{get_tokens, try_get_tokens},
+};
+
+const SOURCE: &str = "counter:
+\t.bits 0x0007,16
+flag:
+\t.bits 0,8
+main:
+\tMOV counter,r15
+\tADD #5,counter
+\tMOV.B #1,flag
+\tPUSH counter
+end:
+\tJMP end
+";
+
+#[test]
+fn test_symbolic_addressing() {
+    let (globals, lines) = get_tokens(SOURCE.to_owned());
+    let (bytes, symbols) = generate_bytes_and_symbols(globals, lines);
+
+    // the index is the distance from the index word to the label
+    let words: Vec<u16> = bytes[0x000A..0x0014]
+        .chunks(2)
+        .map(|w| u16::from_le_bytes([w[0], w[1]]))
+        .collect();
+    assert_eq!(
+        words,
+        vec![
+            0x401F, 0xFFFA, // MOV.W counter,r15
+            0x50B0, 0x0005, 0xFFF4, // ADD.W #5,counter
+        ]
+    );
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..6 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_regs()[15], 7);
+    assert_eq!(cpu_emu.bus.peek_word(0x0006), 12);
+    assert_eq!(cpu_emu.bus.peek_byte(0x0008), 1);
+    assert_eq!(cpu_emu.bus.peek_word(0x7FFE), 12);
+
+    let disassembly: Vec<String> = [0x000A, 0x000E, 0x0014, 0x0018]
+        .iter()
+        .map(|addr| disassemble_to_string(&cpu_emu.bus, *addr, &symbols).0)
+        .collect();
+    assert_eq!(
+        disassembly,
+        vec![
+            "MOV.W counter,r15",
+            "ADD.W #5,counter",
+            "MOV.B #1,flag",
+            "PUSH.W counter"
+        ]
+    );
+}
+
+#[test]
+fn test_symbolic_register_names() {
+    let errors = try_get_tokens("main.asm", "main:\n\tMOV R4,r5\n".to_owned()).unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "can't use R4, the registers are r4 to r15, SP and SR"
+    );
+}
+
+#[test]
+fn test_labels_starting_with_register_names() {
+    let source = "SPEED:
+\t.bits 0x0003,16
+r4count:
+\t.bits 0x0005,16
+main:
+\tMOV.W SPEED, r4
+\tMOV.W r4count,r5
+\tMOV.W SP,r6
+";
+    let (globals, lines) = get_tokens(source.to_owned());
+    let (bytes, _) = generate_bytes_and_symbols(globals, lines);
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    for _ in 0..5 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!([4, 5, 6].map(|r| cpu_emu.get_regs()[r]), [3, 5, 0x8000]);
+}
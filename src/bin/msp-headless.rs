@@ -0,0 +1,567 @@
+// Runs a program without opening a window, then dumps the machine state.
+// Intended for CI and scripting, so the exit status is nonzero if the cpu faults.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::process::exit;
+
+use msp_emu::byte_generator::generate_bytes_symbols_and_addrs;
+use msp_emu::debug_info::DebugInfo;
+use msp_emu::emulator::coverage::Coverage;
+use msp_emu::emulator::debugger::{format_regs, Debugger};
+use msp_emu::emulator::gdb_server::{accept_tcp, accept_unix, GdbServer};
+use msp_emu::emulator::profiler::Profiler;
+use msp_emu::emulator::tracer::{TraceFormat, Tracer};
+use msp_emu::emulator::watchpoints::{WatchAction, WatchKind};
+use msp_emu::emulator::Emulator;
+use msp_emu::get_verbs;
+use msp_emu::get_verbs::debug_directives::get_debug_info;
+use msp_emu::listing::{get_symbol_table, write_listing, write_map, Symbol};
+use msp_emu::source_map::SourceMap;
+
+const USAGE: &str = "usage: msp-headless <program.asm | program.code | program.bin> [options]
+  --max_instrs=<n>       stop after n instructions if the program has not halted (default 10000000)
+  --switches=<value>     set the switches before running
+  --dump=<addr>:<len>    print len bytes of memory starting at addr (can be repeated)
+  --pbm=<file>           write the framebuffer to file as a PBM image
+  --gdb=<host:port | socket path>
+                         wait for a gdb client and let it control the program instead of running it
+  --debug                start an interactive debugger instead of running the program
+  --trace=<file>         log every instruction to file, or to stdout if file is -
+  --trace_format=<text | json>
+                         write the trace as text (the default) or as JSON Lines
+  --trace_verbose        also log the latches written by every stage
+  --watch=<addr>[:<len>] log writes to len bytes (default 2) starting at addr (can be repeated)
+  --rwatch=<addr>[:<len>], --awatch=<addr>[:<len>]
+                         log reads, or reads and writes
+  --watch_stop           stop running at the first watchpoint hit instead of logging it
+  --record=<n>           keep a history of the last n instructions, so that the debugger
+                         and gdb can run backwards
+  --profile=<file>       write a table of the instructions and cycles spent in each function
+                         to file, or to stdout if file is -
+  --folded=<file>        write the cycles spent in each call stack to file, in the folded
+                         format read by flamegraph tools
+  --coverage=<file>      write the assembly source to file, or to stdout if file is -, with the
+                         number of times each line ran and each conditional jump was taken
+  --lcov=<file>          write the same counts in the lcov format, for the assembly source
+                         and for the C source if the compiler interlisted it
+  --map=<file>           write the address, size and kind of every function and global variable
+                         to file, or to stdout if file is -
+  --listing=<file>       write the assembly source to file, or to stdout if file is -, with the
+                         address and encoded words of every instruction";
+
+const DEFAULT_MAX_INSTRS: u64 = 10_000_000;
+
+const EXIT_USAGE: i32 = 1;
+const EXIT_FAULT: i32 = 2;
+
+struct Options {
+    program_file_name: String,
+    max_instrs: u64,
+    switches: u16,
+    dumps: Vec<(u16, u32)>, // address, length
+    pbm_file_name: Option<String>,
+    gdb_addr: Option<String>,
+    is_debugging: bool,
+    trace_file_name: Option<String>,
+    trace_format: TraceFormat,
+    is_trace_verbose: bool,
+    watches: Vec<(WatchKind, u16, u16)>, // kind, first address, last address
+    is_watch_stopping: bool,
+    history_len: usize,
+    profile_file_name: Option<String>,
+    folded_file_name: Option<String>,
+    coverage_file_name: Option<String>,
+    lcov_file_name: Option<String>,
+    map_file_name: Option<String>,
+    listing_file_name: Option<String>,
+}
+
+struct Program {
+    bytes: Vec<u8>,
+    symbols: HashMap<String, u16>,
+    // only known when the program is assembled here
+    source_map: Option<SourceMap>,
+    symbol_table: Vec<Symbol>,
+    debug_info: DebugInfo, // for C programs compiled with --symdebug:dwarf
+}
+
+fn main() {
+    let options = parse_args();
+    let program = load_program(&options.program_file_name);
+    write_program_info(&program, &options);
+    let symbols = program.symbols;
+    let source_map = program.source_map;
+
+    let mut emulator = Emulator::new(&program.bytes);
+    emulator.set_switch_states(options.switches);
+    if let Some(file_name) = &options.trace_file_name {
+        let mut tracer = open_tracer(file_name, &options, &symbols);
+        if let Some(source_map) = &source_map {
+            tracer.set_source_map(source_map.clone());
+        }
+        emulator.set_tracer(Some(tracer));
+    }
+
+    emulator.set_journal_capacity(options.history_len);
+    if options.profile_file_name.is_some() || options.folded_file_name.is_some() {
+        emulator.set_profiler(Some(Profiler::new(&symbols)));
+    }
+    if options.coverage_file_name.is_some() || options.lcov_file_name.is_some() {
+        if source_map.is_none() {
+            usage_error("coverage can only be reported for .asm programs");
+        }
+        emulator.set_coverage(Some(Coverage::new()));
+    }
+    let watch_action = match options.is_watch_stopping {
+        true => WatchAction::Break,
+        false => WatchAction::Log,
+    };
+    for (kind, first_addr, last_addr) in &options.watches {
+        emulator.add_watchpoint(*first_addr..=*last_addr, *kind, watch_action);
+    }
+
+    if let Some(addr) = &options.gdb_addr {
+        if let Err(e) = run_gdb_server(&mut emulator, addr) {
+            eprintln!("gdb connection failed: {}", e);
+            exit(EXIT_USAGE);
+        }
+        println!("{}", format_regs(&emulator));
+        finish_trace(&mut emulator);
+        finish_profile(&mut emulator, &options);
+        finish_coverage(&mut emulator, &options, &source_map);
+        return;
+    }
+    if options.is_debugging {
+        let mut debugger = Debugger::new(symbols);
+        if let Some(source_map) = &source_map {
+            debugger.set_source(source_map.clone(), program.debug_info);
+        }
+        run_debugger(&mut emulator, debugger);
+        finish_trace(&mut emulator);
+        finish_profile(&mut emulator, &options);
+        finish_coverage(&mut emulator, &options, &source_map);
+        return;
+    }
+
+    let mut num_instrs = 0;
+    let mut fault = None;
+    let mut is_watch_stopped = false;
+    while num_instrs < options.max_instrs && !emulator.is_halted() && !is_watch_stopped {
+        let result = emulator.run_one_instr();
+        for hit in emulator.take_watch_hits() {
+            is_watch_stopped |= hit.action == WatchAction::Break;
+            println!("{}", hit);
+        }
+        if let Err(f) = result {
+            fault = Some(f);
+            break;
+        }
+        num_instrs += 1;
+    }
+
+    finish_trace(&mut emulator);
+    finish_profile(&mut emulator, &options);
+    finish_coverage(&mut emulator, &options, &source_map);
+
+    match fault {
+        Some(f) => println!("CPU fault after {} instructions: {}", num_instrs, f),
+        None if is_watch_stopped => {
+            println!("stopped by watchpoint after {} instructions", num_instrs)
+        }
+        None if emulator.is_halted() => println!("halted after {} instructions", num_instrs),
+        None => println!("stopped after {} instructions", num_instrs),
+    }
+    println!("{}", format_regs(&emulator));
+    for (addr, len) in &options.dumps {
+        print_mem(&emulator, *addr, *len);
+    }
+    if let Some(file_name) = &options.pbm_file_name {
+        write_pbm(&emulator, file_name);
+    }
+
+    if fault.is_some() {
+        exit(EXIT_FAULT);
+    }
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        program_file_name: String::new(),
+        max_instrs: DEFAULT_MAX_INSTRS,
+        switches: 0,
+        dumps: Vec::new(),
+        pbm_file_name: None,
+        gdb_addr: None,
+        is_debugging: false,
+        trace_file_name: None,
+        trace_format: TraceFormat::Text,
+        is_trace_verbose: false,
+        watches: Vec::new(),
+        is_watch_stopping: false,
+        history_len: 0,
+        profile_file_name: None,
+        folded_file_name: None,
+        coverage_file_name: None,
+        lcov_file_name: None,
+        map_file_name: None,
+        listing_file_name: None,
+    };
+
+    for arg in std::env::args().skip(1) {
+        if let Some(n) = arg.strip_prefix("--max_instrs=") {
+            options.max_instrs = parse_num(n) as u64;
+        } else if let Some(val) = arg.strip_prefix("--switches=") {
+            options.switches = match u16::try_from(parse_num(val)) {
+                Ok(switches) => switches,
+                Err(_) => usage_error(&format!("{} doesn't fit in the 16 switches", val)),
+            };
+        } else if let Some(range) = arg.strip_prefix("--dump=") {
+            options.dumps.push(parse_mem_range(range, None));
+        } else if let Some(file_name) = arg.strip_prefix("--pbm=") {
+            options.pbm_file_name = Some(file_name.to_owned());
+        } else if let Some(addr) = arg.strip_prefix("--gdb=") {
+            options.gdb_addr = Some(addr.to_owned());
+        } else if arg == "--debug" {
+            options.is_debugging = true;
+        } else if let Some(file_name) = arg.strip_prefix("--trace=") {
+            options.trace_file_name = Some(file_name.to_owned());
+        } else if let Some(format) = arg.strip_prefix("--trace_format=") {
+            options.trace_format = match format {
+                "text" => TraceFormat::Text,
+                "json" => TraceFormat::JsonLines,
+                _ => usage_error(&format!("unknown trace format: {}", format)),
+            };
+        } else if arg == "--trace_verbose" {
+            options.is_trace_verbose = true;
+        } else if let Some((kind, range)) = parse_watch_arg(&arg) {
+            let (addr, len) = parse_mem_range(range, Some(2));
+            let last_addr = (addr as u32 + len - 1) as u16;
+            options.watches.push((kind, addr, last_addr));
+        } else if arg == "--watch_stop" {
+            options.is_watch_stopping = true;
+        } else if let Some(n) = arg.strip_prefix("--record=") {
+            options.history_len = parse_num(n) as usize;
+        } else if let Some(file_name) = arg.strip_prefix("--profile=") {
+            options.profile_file_name = Some(file_name.to_owned());
+        } else if let Some(file_name) = arg.strip_prefix("--folded=") {
+            options.folded_file_name = Some(file_name.to_owned());
+        } else if let Some(file_name) = arg.strip_prefix("--coverage=") {
+            options.coverage_file_name = Some(file_name.to_owned());
+        } else if let Some(file_name) = arg.strip_prefix("--lcov=") {
+            options.lcov_file_name = Some(file_name.to_owned());
+        } else if let Some(file_name) = arg.strip_prefix("--map=") {
+            options.map_file_name = Some(file_name.to_owned());
+        } else if let Some(file_name) = arg.strip_prefix("--listing=") {
+            options.listing_file_name = Some(file_name.to_owned());
+        } else if arg.starts_with("--") || !options.program_file_name.is_empty() {
+            usage_error(&format!("unexpected argument: {}", arg));
+        } else {
+            options.program_file_name = arg;
+        }
+    }
+
+    if options.program_file_name.is_empty() {
+        usage_error("no program given");
+    }
+    options
+}
+
+fn parse_watch_arg(arg: &str) -> Option<(WatchKind, &str)> {
+    if let Some(range) = arg.strip_prefix("--watch=") {
+        return Some((WatchKind::Write, range));
+    }
+    if let Some(range) = arg.strip_prefix("--rwatch=") {
+        return Some((WatchKind::Read, range));
+    }
+    if let Some(range) = arg.strip_prefix("--awatch=") {
+        return Some((WatchKind::Access, range));
+    }
+    None
+}
+
+fn open_tracer(file_name: &str, options: &Options, symbols: &HashMap<String, u16>) -> Tracer {
+    let out: Box<dyn Write> = match file_name {
+        "-" => Box::new(io::stdout()),
+        _ => match File::create(file_name) {
+            Ok(f) => Box::new(io::BufWriter::new(f)),
+            Err(e) => {
+                eprintln!("could not create {}: {}", file_name, e);
+                exit(EXIT_USAGE);
+            }
+        },
+    };
+    Tracer::new(
+        out,
+        options.trace_format,
+        options.is_trace_verbose,
+        symbols.clone(),
+    )
+}
+
+fn finish_profile(emulator: &mut Emulator, options: &Options) {
+    let profiler = match emulator.set_profiler(None) {
+        Some(p) => p,
+        None => return,
+    };
+    if let Some(file_name) = &options.profile_file_name {
+        write_output(file_name, |out| profiler.write_flat_profile(out));
+    }
+    if let Some(file_name) = &options.folded_file_name {
+        write_output(file_name, |out| profiler.write_folded_stacks(out));
+    }
+}
+
+fn write_program_info(program: &Program, options: &Options) {
+    // the map and listing files, written before the program runs
+    let source_map = match &program.source_map {
+        Some(s) => s,
+        None if options.map_file_name.is_some() || options.listing_file_name.is_some() => {
+            usage_error("map and listing files can only be written for .asm programs")
+        }
+        None => return,
+    };
+    if let Some(file_name) = &options.map_file_name {
+        write_output(file_name, |out| write_map(out, &program.symbol_table));
+    }
+    if let Some(file_name) = &options.listing_file_name {
+        write_output(file_name, |out| {
+            write_listing(out, source_map, &program.bytes)
+        });
+    }
+}
+
+fn finish_coverage(emulator: &mut Emulator, options: &Options, source_map: &Option<SourceMap>) {
+    let (coverage, source_map) = match (emulator.set_coverage(None), source_map) {
+        (Some(c), Some(s)) => (c, s),
+        _ => return,
+    };
+    if let Some(file_name) = &options.coverage_file_name {
+        write_output(file_name, |out| {
+            coverage.write_annotated_listing(out, source_map)
+        });
+    }
+    if let Some(file_name) = &options.lcov_file_name {
+        // the gui compiles main.c to main.asm, so without line directives naming the C source,
+        // it is assumed to be next to the program
+        let asm_file_name = &options.program_file_name;
+        let c_file_name = match (
+            source_map.get_c_file_name(),
+            asm_file_name.strip_suffix(".asm"),
+        ) {
+            (Some(file_name), _) => file_name.to_owned(),
+            (None, Some(base)) => format!("{}.c", base),
+            (None, None) => asm_file_name.clone(),
+        };
+        write_output(file_name, |out| {
+            coverage.write_lcov(out, source_map, asm_file_name, &c_file_name)
+        });
+    }
+}
+
+fn write_output(file_name: &str, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+    // writes to file_name, or to stdout if it is -
+    let result = match file_name {
+        "-" => write(&mut io::stdout()),
+        _ => File::create(file_name).and_then(|f| {
+            let mut out = io::BufWriter::new(f);
+            write(&mut out)?;
+            out.flush()
+        }),
+    };
+    if let Err(e) = result {
+        eprintln!("could not write {}: {}", file_name, e);
+    }
+}
+
+fn finish_trace(emulator: &mut Emulator) {
+    if let Some(tracer) = emulator.set_tracer(None) {
+        if let Err(e) = tracer.finish() {
+            eprintln!("could not write trace: {}", e);
+        }
+    }
+}
+
+fn run_gdb_server(emulator: &mut Emulator, addr: &str) -> std::io::Result<()> {
+    // anything that doesn't look like host:port is a unix socket path
+    println!("waiting for gdb on {}", addr);
+    if addr.contains(':') {
+        GdbServer::new(accept_tcp(addr)?).run(emulator)
+    } else {
+        GdbServer::new(accept_unix(addr)?).run(emulator)
+    }
+}
+
+fn run_debugger(emulator: &mut Emulator, mut debugger: Debugger) {
+    // an empty line repeats the previous command, which makes stepping easier
+    let mut prev_line = String::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("(msp) ");
+        io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let line = match line.trim() {
+            "" => prev_line.clone(),
+            l => l.to_owned(),
+        };
+        if line == "quit" || line == "q" {
+            break;
+        }
+        let output = debugger.execute(emulator, &line);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        prev_line = line;
+    }
+}
+
+fn parse_num(s: &str) -> u32 {
+    // accepts decimal, or hexadecimal with a 0x prefix
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    match parsed {
+        Ok(n) => n,
+        Err(_) => usage_error(&format!("invalid number: {}", s)),
+    }
+}
+
+fn parse_mem_range(range: &str, default_len: Option<u32>) -> (u16, u32) {
+    // addr:len, or just addr if there is a default length. the range has to fit in memory
+    let (addr, len) = match (range.split_once(':'), default_len) {
+        (Some((addr, len)), _) => (parse_num(addr), parse_num(len)),
+        (None, Some(len)) => (parse_num(range), len),
+        (None, None) => usage_error(&format!("invalid memory range: {}", range)),
+    };
+    if len == 0 || addr > 0xFFFF || len > 0x10000 - addr {
+        usage_error(&format!("invalid memory range: {}", range));
+    }
+    (addr as u16, len)
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    exit(EXIT_USAGE);
+}
+
+fn load_program(file_name: &str) -> Program {
+    let mut contents = Vec::new();
+    if let Err(e) = File::open(file_name).and_then(|mut f| f.read_to_end(&mut contents)) {
+        eprintln!("could not read {}: {}", file_name, e);
+        exit(EXIT_USAGE);
+    }
+
+    let program = if file_name.ends_with(".asm") {
+        let source: String = String::from_utf8_lossy(&contents).into();
+        let (globals, lines, line_nums) = match get_verbs::try_get_tokens(file_name, source.clone())
+        {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}\n", error);
+                }
+                eprintln!("could not assemble {}", file_name);
+                exit(EXIT_USAGE);
+            }
+        };
+        let (bytes, symbols, addrs) =
+            match generate_bytes_symbols_and_addrs(globals.clone(), lines.clone(), &line_nums) {
+                Ok(program) => program,
+                Err(mut errors) => {
+                    for error in &mut errors {
+                        error.set_source(file_name, &source);
+                        eprintln!("{}\n", error);
+                    }
+                    eprintln!("could not assemble {}", file_name);
+                    exit(EXIT_USAGE);
+                }
+            };
+        Program {
+            symbol_table: get_symbol_table(&globals, &symbols, bytes.len()),
+            source_map: Some(SourceMap::new(&source, &lines, &line_nums, &addrs)),
+            debug_info: get_debug_info(&source),
+            bytes,
+            symbols,
+        }
+    } else if file_name.ends_with(".code") {
+        // the assembler output written by the gui, one hex byte per line
+        let bytes = String::from_utf8_lossy(&contents)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match u8::from_str_radix(line.trim(), 16) {
+                Ok(byte) => byte,
+                Err(_) => {
+                    eprintln!("invalid byte in {}: {}", file_name, line);
+                    exit(EXIT_USAGE);
+                }
+            })
+            .collect();
+        Program {
+            bytes,
+            symbols: HashMap::new(),
+            source_map: None,
+            symbol_table: Vec::new(),
+            debug_info: DebugInfo::default(),
+        }
+    } else {
+        Program {
+            bytes: contents,
+            symbols: HashMap::new(),
+            source_map: None,
+            symbol_table: Vec::new(),
+            debug_info: DebugInfo::default(),
+        }
+    };
+    // the program is loaded into ram from address 0, so it can't be bigger than memory
+    if program.bytes.len() > 0x10000 {
+        usage_error(&format!(
+            "{} is {} bytes, which doesn't fit in the 64 KiB of memory",
+            file_name,
+            program.bytes.len()
+        ));
+    }
+    program
+}
+
+fn print_mem(emulator: &Emulator, addr: u16, len: u32) {
+    // the range is checked by parse_mem_range, so it doesn't run past the end of memory
+    for line_start in (0..len).step_by(16) {
+        print!("{:04X}:", addr as u32 + line_start);
+        for offset in line_start..len.min(line_start + 16) {
+            print!(
+                " {:02X}",
+                emulator.bus.peek_byte((addr as u32 + offset) as u16)
+            );
+        }
+        println!();
+    }
+}
+
+fn write_pbm(emulator: &Emulator, file_name: &str) {
+    // 160x120, each byte holds 8 pixels with the leftmost one in the lowest bit.
+    // a set bit is a white pixel, whereas PBM uses 1 for black.
+    let buf = emulator.get_gfx_buffer();
+    let mut pbm = String::from("P1\n160 120\n");
+    for row in 0..120 {
+        let mut line = Vec::new();
+        for col in 0..20 {
+            let byte = buf[row * 20 + col];
+            for pixel in 0..8 {
+                let is_pixel_white = (byte >> pixel) & 0x01 == 1;
+                line.push(if is_pixel_white { "0" } else { "1" });
+            }
+        }
+        pbm.push_str(&line.join(" "));
+        pbm.push('\n');
+    }
+
+    if let Err(e) = File::create(file_name).and_then(|mut f| f.write_all(pbm.as_bytes())) {
+        eprintln!("could not write {}: {}", file_name, e);
+        exit(EXIT_USAGE);
+    }
+}
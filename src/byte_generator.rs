@@ -1,77 +1,244 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
+    asm_error::AsmError,
     asm_line::AsmLine,
     ccode::CC,
+    expr::Expr,
     get_verbs::Global,
     operand::{Operand, Reg},
 };
 
 const STACK_INIT_POSITION: u16 = 0x8000;
 
+// the bytes, the address of every label and global variable, and the address of every line
+pub type Assembled = (Vec<u8>, HashMap<String, u16>, Vec<u16>);
+
+// a word that depends on the address of a label. here is the address of the instruction or the
+// data that the word is in, which is the value of $ in expr, and line is its source line
 #[derive(Debug)]
 pub enum UnresolvedLabel {
-    FullWord { offset: usize, label: String },
-    Low10Bits { offset: usize, label: String },
+    FullWord {
+        offset: usize,
+        expr: Expr,
+        here: usize,
+        line: usize,
+    },
+    Low10Bits {
+        offset: usize,
+        expr: Expr,
+        here: usize,
+        line: usize,
+    },
+    PcRelative {
+        offset: usize,
+        expr: Expr,
+        here: usize,
+        line: usize,
+    }, // for symbolic mode
+    BranchTarget {
+        offset: usize,
+        expr: Expr,
+        here: usize,
+        line: usize,
+    }, // the immediate of the BR that a long jump becomes
 }
 
 pub fn generate_bytes(globals: Vec<Global>, instrs: Vec<AsmLine>) -> Vec<u8> {
-    let initial_instrs = vec![
+    generate_bytes_and_symbols(globals, instrs).0
+}
+
+pub fn generate_bytes_and_symbols(
+    globals: Vec<Global>,
+    instrs: Vec<AsmLine>,
+) -> (Vec<u8>, HashMap<String, u16>) {
+    // also returns the address of every label and global variable, for debuggers. for programs
+    // that are known to assemble, such as in tests. panics if there are errors, which only have
+    // their messages shown, since the source lines of instrs aren't known
+    let line_nums = vec![0; instrs.len()];
+    match generate_bytes_symbols_and_addrs(globals, instrs, &line_nums) {
+        Ok((bytes, symbols, _)) => (bytes, symbols),
+        Err(errors) => panic!(
+            "{}",
+            errors
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+pub fn generate_bytes_symbols_and_addrs(
+    globals: Vec<Global>,
+    instrs: Vec<AsmLine>,
+    line_nums: &[usize], // the source line of each of instrs, from try_get_tokens
+) -> Result<Assembled, Vec<AsmError>> {
+    // also returns the address that each of instrs was placed at, for mapping back to the source.
+    // the errors are the words that depend on labels that can't be worked out, such as labels
+    // that aren't defined. they have no file name or snippet, see AsmError::set_source
+    let mut lines = vec![
         AsmLine::MOV(
             Operand::Imm(STACK_INIT_POSITION),
             Operand::Reg(Reg::SP),
             false,
         ),
-        AsmLine::Jump(CC::Unconditional, "main".to_owned()),
+        AsmLine::Jump(CC::Unconditional, Expr::Label("main".to_owned())),
     ];
+    let num_initial_lines = lines.len();
+    // the startup code has no line of its own, so a missing main is reported on the first line
+    let line_nums: Vec<usize> = [1, 1].iter().chain(line_nums).copied().collect();
+    lines.extend(instrs.into_iter().map(|mut instr| {
+        optimize_zero_index_instr(&mut instr);
+        instr
+    }));
+
+    // jumps that can't reach their target with a 10 bit offset are made long, which moves the
+    // labels after them, so the program is laid out again until every short jump reaches. jumps
+    // are never made short again, so this always comes to an end
+    let mut long_jumps: HashSet<usize> = HashSet::new();
+    let mut layout = lay_out(&globals, &lines, &line_nums, num_initial_lines, &long_jumps);
+    loop {
+        let far_jumps: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, line)| match line {
+                AsmLine::Jump(_, target) if !long_jumps.contains(i) => {
+                    get_jump_offset(target, layout.line_addrs[*i], &layout.symbols)
+                        .is_ok_and(|offset| !(-512..=511).contains(&offset))
+                }
+                _ => false,
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if far_jumps.is_empty() {
+            break;
+        }
+        long_jumps.extend(far_jumps);
+        layout = lay_out(&globals, &lines, &line_nums, num_initial_lines, &long_jumps);
+    }
+
+    let mut result_bytes = layout.bytes;
+    resolve_labels(
+        &mut result_bytes,
+        &layout.unresolved_labels,
+        &layout.label_map,
+        &layout.symbols,
+    )?;
+
+    let instr_addrs = layout.line_addrs[num_initial_lines..]
+        .iter()
+        .map(|addr| *addr as u16)
+        .collect();
+    Ok((result_bytes, layout.symbols, instr_addrs))
+}
+
+struct Layout {
+    bytes: Vec<u8>, // with the words in unresolved_labels left as zeros
+    unresolved_labels: Vec<UnresolvedLabel>,
+    label_map: HashMap<String, usize>,
+    symbols: HashMap<String, u16>,
+    line_addrs: Vec<usize>,
+}
 
+fn lay_out(
+    globals: &[Global],
+    lines: &[AsmLine],
+    line_nums: &[usize],
+    num_initial_lines: usize, // the startup code, which comes before the globals
+    long_jumps: &HashSet<usize>,
+) -> Layout {
     let mut result_bytes = Vec::new();
     let mut unresolved_labels: Vec<UnresolvedLabel> = Vec::new();
-    unresolved_labels.push(UnresolvedLabel::Low10Bits {
-        offset: 4,
-        label: "main".to_owned(),
-    });
     let mut label_map: HashMap<String, usize> = HashMap::new();
+    let mut line_addrs = Vec::new();
 
-    for instr in initial_instrs {
+    for (i, instr) in lines.iter().enumerate() {
+        if i == num_initial_lines {
+            place_globals(
+                globals,
+                &mut result_bytes,
+                &mut unresolved_labels,
+                &mut label_map,
+            );
+        }
+        line_addrs.push(result_bytes.len());
         convert_instr_to_bytes(
-            instr,
+            instr.clone(),
+            line_nums[i],
+            long_jumps.contains(&i),
             &mut result_bytes,
             &mut unresolved_labels,
             &mut label_map,
         );
     }
-
-    for global in globals {
-        label_map.insert(global.label, result_bytes.len());
-        result_bytes.extend(global.initial_bytes);
-    }
-    if result_bytes.len() % 2 != 0 {
-        result_bytes.push(0x00); // instructions must be aligned on an even byte boundary
-    }
-
-    for instr in instrs {
-        let mut instr = instr.clone();
-        optimize_zero_index_instr(&mut instr);
-        convert_instr_to_bytes(
-            instr,
+    if lines.len() == num_initial_lines {
+        place_globals(
+            globals,
             &mut result_bytes,
             &mut unresolved_labels,
             &mut label_map,
-        )
+        );
     }
 
-    resolve_labels(&mut result_bytes, &unresolved_labels, &label_map);
+    let symbols = label_map
+        .iter()
+        .map(|(label, location)| {
+            // undo the offset that instruction labels are stored with
+            let addr = match globals.iter().any(|g| g.label == *label) {
+                true => *location,
+                false => location + 2,
+            };
+            (label.clone(), addr as u16)
+        })
+        .collect();
 
-    result_bytes
+    Layout {
+        bytes: result_bytes,
+        unresolved_labels,
+        label_map,
+        symbols,
+        line_addrs,
+    }
+}
+
+fn place_globals(
+    globals: &[Global],
+    result_bytes: &mut Vec<u8>,
+    unresolved_labels: &mut Vec<UnresolvedLabel>,
+    label_map: &mut HashMap<String, usize>,
+) {
+    for global in globals {
+        while !result_bytes.len().is_multiple_of(global.align) {
+            result_bytes.push(0x00);
+        }
+        for (offset, expr, line) in &global.label_words {
+            unresolved_labels.push(UnresolvedLabel::FullWord {
+                offset: result_bytes.len() + offset,
+                expr: expr.clone(),
+                here: result_bytes.len() + offset,
+                line: *line,
+            });
+        }
+        if !global.label.is_empty() {
+            label_map.insert(global.label.clone(), result_bytes.len());
+        }
+        result_bytes.extend(&global.initial_bytes);
+    }
+    if !result_bytes.len().is_multiple_of(2) {
+        result_bytes.push(0x00); // instructions must be aligned on an even byte boundary
+    }
 }
 
 fn convert_instr_to_bytes(
     instr: AsmLine,
+    line: usize,
+    is_long_jump: bool,
     result: &mut Vec<u8>,
     unresolved_labels: &mut Vec<UnresolvedLabel>,
     label_map: &mut HashMap<String, usize>,
 ) {
+    let here = result.len();
     match &instr {
         AsmLine::Label(s) => {
             let offset = result.len() - 2; // we subtract two because all jumps to
@@ -80,10 +247,48 @@ fn convert_instr_to_bytes(
             label_map.insert(s.clone(), offset);
         }
 
-        AsmLine::Jump(cc, label) => {
+        AsmLine::Jump(cc, target) if is_long_jump => {
+            // a jump that is too far for its offset becomes a branch, which a conditional jump
+            // skips over when its condition is false:
+            //     JNE far    becomes    JEQ $+6
+            //                           BR #far
+            // there is no jump if not negative, so JN jumps to the branch instead:
+            //     JN far     becomes    JN $+4
+            //                           JMP $+6
+            //                           BR #far
+            let skip_words: Vec<u16> = match (cc, cc.invert()) {
+                (CC::Unconditional, _) => vec![],
+                (_, Some(inverted)) => vec![0x2000 | inverted.to_bits_repr() | 2],
+                (_, None) => vec![
+                    0x2000 | cc.to_bits_repr() | 1,
+                    0x2000 | CC::Unconditional.to_bits_repr() | 2,
+                ],
+            };
+            for word in skip_words {
+                let [low_byte, high_byte] = word.to_le_bytes();
+                result.push(low_byte);
+                result.push(high_byte);
+            }
+            let br_instr: u16 = 0x4030; // MOV #imm,PC
+            let [low_byte, high_byte] = br_instr.to_le_bytes();
+            result.push(low_byte);
+            result.push(high_byte);
+            unresolved_labels.push(UnresolvedLabel::BranchTarget {
+                offset: result.len(),
+                expr: target.clone(),
+                here,
+                line,
+            });
+            result.push(0);
+            result.push(0);
+        }
+
+        AsmLine::Jump(cc, target) => {
             unresolved_labels.push(UnresolvedLabel::Low10Bits {
                 offset: result.len(),
-                label: label.clone(),
+                expr: target.clone(),
+                here,
+                line,
             });
             let c_code = cc.to_bits_repr();
             let instr: u16 = 0x2000 | c_code;
@@ -123,11 +328,14 @@ fn convert_instr_to_bytes(
                 (None, None) => {}
                 (None, Some(_)) => unreachable!(),
                 (Some(imm), optional_unres_label) => {
-                    if let Some(label) = optional_unres_label {
-                        unresolved_labels.push(UnresolvedLabel::FullWord {
-                            offset: result.len(),
-                            label: label.clone(),
-                        });
+                    if let Some(expr) = optional_unres_label {
+                        unresolved_labels.push(get_unresolved_label(
+                            op,
+                            result.len(),
+                            expr,
+                            here,
+                            line,
+                        ));
                     }
                     let [low_byte, high_byte] = imm.to_le_bytes();
                     result.push(low_byte);
@@ -142,6 +350,7 @@ fn convert_instr_to_bytes(
         | AsmLine::SUB(src_op, dst_op, is_byte_instr)
         | AsmLine::SUBC(src_op, dst_op, is_byte_instr)
         | AsmLine::CMP(src_op, dst_op, is_byte_instr)
+        | AsmLine::DADD(src_op, dst_op, is_byte_instr)
         | AsmLine::BIT(src_op, dst_op, is_byte_instr)
         | AsmLine::BIC(src_op, dst_op, is_byte_instr)
         | AsmLine::BIS(src_op, dst_op, is_byte_instr)
@@ -155,6 +364,7 @@ fn convert_instr_to_bytes(
                 AsmLine::SUBC(..) => 0x7 << 12,
                 AsmLine::SUB(..) => 0x8 << 12,
                 AsmLine::CMP(..) => 0x9 << 12,
+                AsmLine::DADD(..) => 0xA << 12,
                 AsmLine::BIT(..) => 0xB << 12,
                 AsmLine::BIC(..) => 0xC << 12,
                 AsmLine::BIS(..) => 0xD << 12,
@@ -179,11 +389,14 @@ fn convert_instr_to_bytes(
                 (None, None) => {}
                 (None, Some(_)) => unreachable!(),
                 (Some(imm), optional_unres_label) => {
-                    if let Some(label) = optional_unres_label {
-                        unresolved_labels.push(UnresolvedLabel::FullWord {
-                            offset: result.len(),
-                            label: label.clone(),
-                        });
+                    if let Some(expr) = optional_unres_label {
+                        unresolved_labels.push(get_unresolved_label(
+                            src_op,
+                            result.len(),
+                            expr,
+                            here,
+                            line,
+                        ));
                     }
                     let [low_byte, high_byte] = imm.to_le_bytes();
                     result.push(low_byte);
@@ -195,11 +408,14 @@ fn convert_instr_to_bytes(
                 (None, None) => {}
                 (None, Some(_)) => unreachable!(),
                 (Some(imm), optional_unres_label) => {
-                    if let Some(label) = optional_unres_label {
-                        unresolved_labels.push(UnresolvedLabel::FullWord {
-                            offset: result.len(),
-                            label: label.clone(),
-                        });
+                    if let Some(expr) = optional_unres_label {
+                        unresolved_labels.push(get_unresolved_label(
+                            dst_op,
+                            result.len(),
+                            expr,
+                            here,
+                            line,
+                        ));
                     }
                     let [low_byte, high_byte] = imm.to_le_bytes();
                     result.push(low_byte);
@@ -209,19 +425,41 @@ fn convert_instr_to_bytes(
         }
 
         AsmLine::RETI => {
-            panic!("RETI not implemented")
-        }
-        AsmLine::DADD(_, _, _) => {
-            panic!("DADD not implemented")
+            let instr_word: u16 = 0x1300;
+            let [low_byte, high_byte] = instr_word.to_le_bytes();
+            result.push(low_byte);
+            result.push(high_byte);
         }
     }
 }
 
+fn get_unresolved_label(
+    op: &Operand,
+    offset: usize,
+    expr: Expr,
+    here: usize,
+    line: usize,
+) -> UnresolvedLabel {
+    match op {
+        Operand::Symbolic(_) => UnresolvedLabel::PcRelative {
+            offset,
+            expr,
+            here,
+            line,
+        },
+        _ => UnresolvedLabel::FullWord {
+            offset,
+            expr,
+            here,
+            line,
+        },
+    }
+}
+
 fn optimize_zero_index_instr(instr: &mut AsmLine) {
     match instr {
         AsmLine::Label(_) => {}
         AsmLine::Jump(_, _) => {}
-        AsmLine::DADD(_, _, _) => {}
         AsmLine::RETI => {}
         AsmLine::RRC(src, _)
         | AsmLine::SWPB(src, _)
@@ -235,6 +473,7 @@ fn optimize_zero_index_instr(instr: &mut AsmLine) {
         | AsmLine::SUB(src, _, _)
         | AsmLine::SUBC(src, _, _)
         | AsmLine::CMP(src, _, _)
+        | AsmLine::DADD(src, _, _)
         | AsmLine::BIT(src, _, _)
         | AsmLine::BIC(src, _, _)
         | AsmLine::BIS(src, _, _)
@@ -249,44 +488,134 @@ fn optimize_zero_index_instr(instr: &mut AsmLine) {
 }
 
 fn resolve_labels(
-    result_bytes: &mut Vec<u8>,
+    result_bytes: &mut [u8],
     unresolved_labels: &Vec<UnresolvedLabel>,
     label_map: &HashMap<String, usize>,
-) {
+    symbols: &HashMap<String, u16>,
+) -> Result<(), Vec<AsmError>> {
+    // full words use the addresses in label_map, so that an immediate of an instruction label
+    // can be moved into PC. jumps and symbolic mode use the actual addresses in symbols. a word
+    // that can't be worked out is left as zeros, and the rest are still resolved to find any
+    // more errors
+    let label_map_lookup = |label: &str| label_map.get(label).map(|l| *l as i64);
+    let symbols_lookup = |label: &str| symbols.get(label).map(|a| *a as i64);
+    let eval = |expr: &Expr, lookup: &dyn Fn(&str) -> Option<i64>, here: usize| {
+        expr.eval(lookup, Some(here as u16))
+    };
+    let mut errors = Vec::new();
+    let mut error = |line: usize, message: String| {
+        errors.push(AsmError {
+            file_name: String::new(),
+            line,
+            col: 1,
+            snippet: String::new(),
+            message,
+        })
+    };
+
     for unres_label in unresolved_labels {
         match unres_label {
-            UnresolvedLabel::FullWord { offset, label } => {
-                let label_location = label_map.get(label);
-                let label_location = match label_location {
-                    None => panic!("unresolved label {}", label),
-                    Some(l) => *l,
+            UnresolvedLabel::FullWord {
+                offset,
+                expr,
+                here,
+                line,
+            } => {
+                let word = match eval(expr, &label_map_lookup, *here) {
+                    Ok(value) => value as u16,
+                    Err(message) => {
+                        error(*line, message);
+                        continue;
+                    }
                 };
-                let word = label_location as u16;
 
                 let [low_byte, high_byte] = word.to_le_bytes();
                 result_bytes[*offset] = low_byte;
                 result_bytes[*offset + 1] = high_byte;
             }
-            UnresolvedLabel::Low10Bits { offset, label } => {
-                let label_location = label_map.get(label);
-                let label_location = match label_location {
-                    None => panic!("unresolved label {}", label),
-                    Some(l) => *l,
+            UnresolvedLabel::Low10Bits {
+                offset,
+                expr,
+                here,
+                line,
+            } => {
+                // jumps that are too far were made long by generate_bytes_symbols_and_addrs
+                let signed_offset = match get_jump_offset(expr, *here, symbols) {
+                    Ok(offset) => offset,
+                    Err(message) => {
+                        error(*line, message);
+                        continue;
+                    }
                 };
-                let difference_in_addrs = label_location as i64 - *offset as i64;
-                assert!(difference_in_addrs % 2 == 0);
-                let signed_offset = difference_in_addrs / 2;
-
-                // check that the signed offset will fit in 10 bits
-                if signed_offset > 511 || signed_offset < -512 {
-                    panic!("trying to jump too far with label {}!", label);
-                }
                 let signed_offset_bits = (signed_offset as i16) & 0x03FF;
 
                 let [low_byte, high_byte] = signed_offset_bits.to_le_bytes();
                 result_bytes[*offset] = low_byte;
                 result_bytes[*offset + 1] |= high_byte;
             }
+            UnresolvedLabel::PcRelative {
+                offset,
+                expr,
+                here,
+                line,
+            } => {
+                // the cpu adds the index to the address of the index word itself
+                let addr = match eval(expr, &symbols_lookup, *here) {
+                    Ok(addr) => addr,
+                    Err(message) => {
+                        error(*line, message);
+                        continue;
+                    }
+                };
+                let word = (addr - *offset as i64) as u16;
+
+                let [low_byte, high_byte] = word.to_le_bytes();
+                result_bytes[*offset] = low_byte;
+                result_bytes[*offset + 1] = high_byte;
+            }
+            UnresolvedLabel::BranchTarget {
+                offset,
+                expr,
+                here,
+                line,
+            } => {
+                // the cpu moves PC past the BR after the immediate is moved into it, so that a
+                // branch lands four bytes after the immediate
+                let word = match eval(expr, &symbols_lookup, *here) {
+                    Ok(addr) => (addr - 4) as u16,
+                    Err(message) => {
+                        error(*line, message);
+                        continue;
+                    }
+                };
+
+                let [low_byte, high_byte] = word.to_le_bytes();
+                result_bytes[*offset] = low_byte;
+                result_bytes[*offset + 1] = high_byte;
+            }
         }
     }
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| e.line);
+        return Err(errors);
+    }
+    Ok(())
+}
+
+fn get_jump_offset(
+    target: &Expr,
+    here: usize,
+    symbols: &HashMap<String, u16>,
+) -> Result<i64, String> {
+    // the offset in words from the instruction after the jump at here
+    let lookup = |label: &str| symbols.get(label).map(|a| *a as i64);
+    let target_addr = target.eval(&lookup, Some(here as u16))?;
+    if target_addr % 2 != 0 {
+        return Err(format!(
+            "jump target {} (0x{:04X}) is not word aligned",
+            target, target_addr
+        ));
+    }
+    Ok((target_addr - (here as i64 + 2)) / 2)
 }
@@ -26,4 +26,44 @@ impl CC {
         // shift 10 because in JMP instructions, the condition code occupies bits 12:10
         return res << 10;
     }
+
+    pub fn get_mnemonic(&self) -> &'static str {
+        match self {
+            CC::NotEq => "JNE",
+            CC::Eq => "JEQ",
+            CC::NoCarry => "JNC",
+            CC::Carry => "JC",
+            CC::Neg => "JN",
+            CC::GreaterEq => "JGE",
+            CC::Less => "JL",
+            CC::Unconditional => "JMP",
+        }
+    }
+
+    pub fn invert(&self) -> Option<CC> {
+        // the condition that is true when this one is false. there is no jump if not negative
+        match self {
+            CC::NotEq => Some(CC::Eq),
+            CC::Eq => Some(CC::NotEq),
+            CC::NoCarry => Some(CC::Carry),
+            CC::Carry => Some(CC::NoCarry),
+            CC::GreaterEq => Some(CC::Less),
+            CC::Less => Some(CC::GreaterEq),
+            CC::Neg | CC::Unconditional => None,
+        }
+    }
+
+    pub fn from_bits_repr(bits: u16) -> CC {
+        // the inverse of to_bits_repr
+        match (bits >> 10) & 0x7 {
+            0 => CC::NotEq,
+            1 => CC::Eq,
+            2 => CC::NoCarry,
+            3 => CC::Carry,
+            4 => CC::Neg,
+            5 => CC::GreaterEq,
+            6 => CC::Less,
+            _ => CC::Unconditional,
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+// What the debugger knows about the variables of a C program, from the DWARF directives that
+// cl430 writes with --symdebug:dwarf (see get_verbs::debug_directives). Line numbers are kept by
+// SourceMap instead, since they are tied to the instructions that follow each .dwpsn directive.
+// Addresses are kept as labels, and are looked up in the symbols from the assembler when needed.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarLocation {
+    Label(String),         // a global or static variable
+    Reg(usize),            // a variable kept in a register
+    RegOffset(usize, i16), // a variable on the stack, at an offset from SP
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub location: VarLocation,
+    pub is_byte: bool, // chars and bools are read as bytes, everything else as words
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,          // the label of the function's first instruction
+    pub locals: Vec<Variable>, // including parameters
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub globals: Vec<Variable>,
+    pub functions: Vec<Function>,
+}
+
+impl DebugInfo {
+    pub fn is_empty(&self) -> bool {
+        self.globals.is_empty() && self.functions.is_empty()
+    }
+
+    pub fn find_function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    pub fn find_variable(&self, function_name: Option<&str>, name: &str) -> Option<&Variable> {
+        // locals of the function hide globals with the same name, as in C
+        let locals = function_name
+            .and_then(|f| self.find_function(f))
+            .map(|f| f.locals.as_slice())
+            .unwrap_or_default();
+        locals.iter().chain(&self.globals).find(|v| v.name == name)
+    }
+}
+
+impl Variable {
+    pub fn get_addr(&self, regs: &[u16; 16], symbols: &HashMap<String, u16>) -> Option<u16> {
+        // None for variables kept in registers, or at labels that are not in symbols
+        match &self.location {
+            VarLocation::Label(label) => symbols.get(label).copied(),
+            VarLocation::Reg(_) => None,
+            VarLocation::RegOffset(reg, offset) => {
+                Some(regs[*reg].overflowing_add(*offset as u16).0)
+            }
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use msp_emu::disassembler::disassemble_to_string;
+use msp_emu::emulator::debugger::{format_regs, get_call_stack, Debugger};
+use msp_emu::emulator::fault::CpuFault;
+use msp_emu::emulator::Emulator;
+
+// Debugging panels drawn to the right of and below the board, each toggled with a function key:
+//   F1 registers, F2 disassembly around the pc, F3 memory, F4 call stack
+// F6 pauses and resumes the cpu and F7 steps one instruction, as do the buttons above the panels.
+
+const PANELS_X: f32 = 670.0;
+const MEMORY_PANEL_X: f32 = 10.0;
+const MEMORY_PANEL_Y: f32 = 570.0;
+
+const FONT_SIZE: f32 = 18.0;
+const LINE_HEIGHT: f32 = 18.0;
+const PANEL_WIDTH: f32 = 600.0;
+const PANEL_COLOR: Color = Color::new(0.1, 0.1, 0.15, 0.9);
+const HIGHLIGHT_COLOR: Color = Color::new(0.3, 0.3, 0.6, 1.0);
+const BUTTON_WIDTH: f32 = 80.0;
+const BUTTON_HEIGHT: f32 = 24.0;
+
+const NUM_DISASM_LINES: usize = 13;
+// how far before the pc the disassembly listing may start
+const MAX_DISASM_LOOKBEHIND: u16 = 16;
+const NUM_MEMORY_ROWS: u16 = 8;
+const MEMORY_ROW_LEN: u16 = 16;
+const NUM_STACK_LINES: usize = 6;
+
+pub struct DebugOverlay {
+    symbols: HashMap<String, u16>,
+    debugger: Debugger, // used to name addresses
+    is_paused: bool,
+    is_regs_shown: bool,
+    is_disasm_shown: bool,
+    is_memory_shown: bool,
+    is_stack_shown: bool,
+    memory_view_addr: u16,
+}
+
+enum Button {
+    Pause,
+    Step,
+    Resume,
+}
+
+const BUTTONS: [(Button, &str); 3] = [
+    (Button::Pause, "Pause"),
+    (Button::Step, "Step"),
+    (Button::Resume, "Resume"),
+];
+
+impl DebugOverlay {
+    pub fn new(symbols: HashMap<String, u16>) -> Self {
+        DebugOverlay {
+            debugger: Debugger::new(symbols.clone()),
+            symbols,
+            is_paused: false,
+            is_regs_shown: true,
+            is_disasm_shown: true,
+            is_memory_shown: true,
+            is_stack_shown: true,
+            memory_view_addr: 0,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn handle_input(&mut self, emulator: &mut Emulator) -> Result<(), CpuFault> {
+        // runs one instruction if asked to step
+        if is_key_pressed(KeyCode::F1) {
+            self.is_regs_shown = !self.is_regs_shown;
+        }
+        if is_key_pressed(KeyCode::F2) {
+            self.is_disasm_shown = !self.is_disasm_shown;
+        }
+        if is_key_pressed(KeyCode::F3) {
+            self.is_memory_shown = !self.is_memory_shown;
+        }
+        if is_key_pressed(KeyCode::F4) {
+            self.is_stack_shown = !self.is_stack_shown;
+        }
+        if self.is_memory_shown {
+            self.scroll_memory();
+        }
+
+        let mut is_stepping = is_key_pressed(KeyCode::F7);
+        if is_key_pressed(KeyCode::F6) {
+            self.is_paused = !self.is_paused;
+        }
+        if let Some(button) = get_clicked_button() {
+            match button {
+                Button::Pause => self.is_paused = true,
+                Button::Step => is_stepping = true,
+                Button::Resume => self.is_paused = false,
+            }
+        }
+        if is_stepping {
+            self.is_paused = true;
+            emulator.run_one_instr()?;
+        }
+        Ok(())
+    }
+
+    fn scroll_memory(&mut self) {
+        let (mouse_x, mouse_y) = mouse_position();
+        let is_mouse_over = (MEMORY_PANEL_X..MEMORY_PANEL_X + PANEL_WIDTH).contains(&mouse_x)
+            && mouse_y >= MEMORY_PANEL_Y;
+        let (_, wheel_y) = mouse_wheel();
+        let mut delta: i32 = 0;
+        if is_mouse_over && wheel_y != 0.0 {
+            delta = if wheel_y > 0.0 { -1 } else { 1 };
+        }
+        if is_key_pressed(KeyCode::PageUp) {
+            delta = -(NUM_MEMORY_ROWS as i32);
+        }
+        if is_key_pressed(KeyCode::PageDown) {
+            delta = NUM_MEMORY_ROWS as i32;
+        }
+        let offset = (delta * MEMORY_ROW_LEN as i32) as u16;
+        self.memory_view_addr = self.memory_view_addr.overflowing_add(offset).0;
+    }
+
+    pub fn draw(&self, emulator: &Emulator) {
+        draw_buttons(self.is_paused);
+        let mut y = 10.0 + BUTTON_HEIGHT + 10.0;
+        if self.is_regs_shown {
+            y = self.draw_regs(PANELS_X, y, emulator) + 10.0;
+        }
+        if self.is_disasm_shown {
+            y = self.draw_disasm(PANELS_X, y, emulator) + 10.0;
+        }
+        if self.is_stack_shown {
+            self.draw_stack(PANELS_X, y, emulator);
+        }
+        if self.is_memory_shown {
+            self.draw_memory(MEMORY_PANEL_X, MEMORY_PANEL_Y, emulator);
+        }
+    }
+
+    fn draw_regs(&self, x: f32, y: f32, emulator: &Emulator) -> f32 {
+        // returns the bottom of the panel
+        let lines: Vec<String> = format_regs(emulator).lines().map(String::from).collect();
+        draw_panel(x, y, "registers (F1)", &lines, None)
+    }
+
+    fn draw_disasm(&self, x: f32, y: f32, emulator: &Emulator) -> f32 {
+        let pc = emulator.get_regs()[0];
+        let mut addr = find_listing_start(emulator, &self.symbols, pc);
+        let mut lines = Vec::new();
+        let mut highlighted = None;
+        for i in 0..NUM_DISASM_LINES {
+            let (text, len) = disassemble_to_string(&emulator.bus, addr, &self.symbols);
+            if addr == pc {
+                highlighted = Some(i);
+            }
+            lines.push(format!("{}: {}", self.debugger.format_location(addr), text));
+            addr = addr.overflowing_add(len).0;
+        }
+        draw_panel(x, y, "disassembly (F2)", &lines, highlighted)
+    }
+
+    fn draw_stack(&self, x: f32, y: f32, emulator: &Emulator) -> f32 {
+        let mut lines = vec![format!(
+            "#0 {}",
+            self.debugger.format_location(emulator.get_regs()[0])
+        )];
+        for (i, call_addr) in get_call_stack(emulator).iter().enumerate() {
+            lines.push(format!(
+                "#{} {}",
+                i + 1,
+                self.debugger.format_location(*call_addr)
+            ));
+        }
+        lines.truncate(NUM_STACK_LINES);
+        draw_panel(x, y, "call stack (F4)", &lines, Some(0))
+    }
+
+    fn draw_memory(&self, x: f32, y: f32, emulator: &Emulator) -> f32 {
+        let lines: Vec<String> = (0..NUM_MEMORY_ROWS)
+            .map(|row| {
+                let row_addr = self
+                    .memory_view_addr
+                    .overflowing_add(row * MEMORY_ROW_LEN)
+                    .0;
+                let bytes: Vec<String> = (0..MEMORY_ROW_LEN)
+                    .map(|i| {
+                        format!(
+                            "{:02X}",
+                            emulator.bus.peek_byte(row_addr.overflowing_add(i).0)
+                        )
+                    })
+                    .collect();
+                format!("{:04X}: {}", row_addr, bytes.join(" "))
+            })
+            .collect();
+        draw_panel(x, y, "memory (F3, scroll or PgUp/PgDn)", &lines, None)
+    }
+}
+
+fn find_listing_start(emulator: &Emulator, symbols: &HashMap<String, u16>, pc: u16) -> u16 {
+    // instructions have different lengths, so disassembling backwards is ambiguous.
+    // this picks the earliest start from which disassembling forwards lands on the pc.
+    for lookbehind in (2..=MAX_DISASM_LOOKBEHIND).rev().step_by(2) {
+        let start = match pc.checked_sub(lookbehind) {
+            Some(s) => s,
+            None => continue,
+        };
+        let mut addr = start;
+        while addr < pc {
+            let len = disassemble_to_string(&emulator.bus, addr, symbols).1;
+            addr = match addr.checked_add(len) {
+                Some(a) => a,
+                None => break,
+            };
+        }
+        if addr == pc {
+            return start;
+        }
+    }
+    pc
+}
+
+fn draw_panel(x: f32, y: f32, title: &str, lines: &[String], highlighted: Option<usize>) -> f32 {
+    // returns the bottom of the panel
+    let height = LINE_HEIGHT * (lines.len() as f32 + 1.0) + 8.0;
+    draw_rectangle(x, y, PANEL_WIDTH, height, PANEL_COLOR);
+    draw_text(title, x + 6.0, y + LINE_HEIGHT, FONT_SIZE, YELLOW);
+    for (i, line) in lines.iter().enumerate() {
+        let line_y = y + LINE_HEIGHT * (i as f32 + 1.0) + 4.0;
+        if highlighted == Some(i) {
+            draw_rectangle(x, line_y, PANEL_WIDTH, LINE_HEIGHT, HIGHLIGHT_COLOR);
+        }
+        draw_text(line, x + 6.0, line_y + LINE_HEIGHT - 4.0, FONT_SIZE, WHITE);
+    }
+    y + height
+}
+
+fn get_button_rect(index: usize) -> Rect {
+    Rect::new(
+        PANELS_X + index as f32 * (BUTTON_WIDTH + 10.0),
+        10.0,
+        BUTTON_WIDTH,
+        BUTTON_HEIGHT,
+    )
+}
+
+fn draw_buttons(is_paused: bool) {
+    for (i, (_, label)) in BUTTONS.iter().enumerate() {
+        let rect = get_button_rect(i);
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, DARKGRAY);
+        draw_text(label, rect.x + 8.0, rect.y + 17.0, FONT_SIZE, WHITE);
+    }
+    let status = if is_paused {
+        "paused (F6 resume, F7 step)"
+    } else {
+        "running (F6 pause)"
+    };
+    let x = get_button_rect(BUTTONS.len()).x;
+    draw_text(status, x, 10.0 + 17.0, FONT_SIZE, BLACK);
+}
+
+fn get_clicked_button() -> Option<Button> {
+    if !is_mouse_button_pressed(MouseButton::Left) {
+        return None;
+    }
+    let mouse = Vec2::from(mouse_position());
+    for (i, (button, _)) in BUTTONS.into_iter().enumerate() {
+        if get_button_rect(i).contains(mouse) {
+            return Some(button);
+        }
+    }
+    None
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use crate::{
+    asm_line::AsmLine,
+    ccode::CC,
+    emulator::bus::Bus,
+    expr::Expr,
+    operand::{Operand, Reg},
+};
+
+// Turns machine code back into AsmLines, the inverse of byte_generator.
+// Reads go through Bus::peek_word, so disassembling has no effect on peripherals.
+
+pub fn disassemble(bus: &Bus, addr: u16, symbols: &HashMap<String, u16>) -> Option<(AsmLine, u16)> {
+    // returns the instruction at addr and its length in bytes,
+    // or None if the word at addr is not a valid instruction.
+    // symbols is optional (it can be empty), and is used to name jump targets and addresses.
+    let instr = bus.peek_word(addr);
+    let mut ext_word_addr = addr.overflowing_add(2).0;
+    let mut next_ext_word = || {
+        let word = bus.peek_word(ext_word_addr);
+        ext_word_addr = ext_word_addr.overflowing_add(2).0;
+        word
+    };
+
+    let line = match instr >> 12 {
+        0x1 => {
+            // SINGLE OPERAND FAMILY
+            if instr > 0x137F {
+                return None;
+            }
+            let opcode = (instr >> 7) & 0x7;
+            let is_byte_instr = instr & 0x0040 != 0;
+            if opcode == 6 {
+                if instr & 0x007F != 0 {
+                    return None;
+                }
+                AsmLine::RETI
+            } else {
+                if is_byte_instr && (opcode == 1 || opcode == 3 || opcode == 5) {
+                    return None;
+                }
+                let op = decode_src_operand(instr & 0xF, (instr >> 4) & 0x3, &mut next_ext_word);
+                let op = match opcode {
+                    5 => name_code_addr(op, symbols),
+                    _ => name_data_addr(op, symbols),
+                };
+                let op = name_symbolic_addr(op, addr.overflowing_add(2).0, symbols);
+                match opcode {
+                    0 => AsmLine::RRC(op, is_byte_instr),
+                    1 => AsmLine::SWPB(op, false),
+                    2 => AsmLine::RRA(op, is_byte_instr),
+                    3 => AsmLine::SXT(op, false),
+                    4 => AsmLine::PUSH(op, is_byte_instr),
+                    5 => AsmLine::CALL(op, false),
+                    _ => return None,
+                }
+            }
+        }
+        0x2 | 0x3 => {
+            // JUMP FAMILY
+            // the offset is a signed 10 bit number of words, relative to the next instruction
+            let offset = (((instr & 0x03FF) << 6) as i16) >> 6;
+            let target = addr
+                .overflowing_add(2)
+                .0
+                .overflowing_add((offset * 2) as u16)
+                .0;
+            let target = match find_symbol(symbols, target) {
+                Some(label) => Expr::Label(label.to_owned()),
+                None => Expr::Num(target as i64),
+            };
+            AsmLine::Jump(CC::from_bits_repr(instr), target)
+        }
+        0x4..=0xF => {
+            // DOUBLE OPERAND FAMILY
+            let is_byte_instr = instr & 0x0040 != 0;
+            let src =
+                decode_src_operand((instr >> 8) & 0xF, (instr >> 4) & 0x3, &mut next_ext_word);
+            let dst = decode_dst_operand(instr, &mut next_ext_word);
+            let src = match dst {
+                // MOV #label,PC branches to the label
+                Operand::Reg(Reg::PC) => name_code_addr(src, symbols),
+                _ => name_data_addr(src, symbols),
+            };
+            let dst = name_data_addr(dst, symbols);
+            // the index word of the source comes first, and the one of the destination last
+            let src = name_symbolic_addr(src, addr.overflowing_add(2).0, symbols);
+            let dst = name_symbolic_addr(dst, ext_word_addr.overflowing_sub(2).0, symbols);
+            match instr >> 12 {
+                0x4 => AsmLine::MOV(src, dst, is_byte_instr),
+                0x5 => AsmLine::ADD(src, dst, is_byte_instr),
+                0x6 => AsmLine::ADDC(src, dst, is_byte_instr),
+                0x7 => AsmLine::SUBC(src, dst, is_byte_instr),
+                0x8 => AsmLine::SUB(src, dst, is_byte_instr),
+                0x9 => AsmLine::CMP(src, dst, is_byte_instr),
+                0xA => AsmLine::DADD(src, dst, is_byte_instr),
+                0xB => AsmLine::BIT(src, dst, is_byte_instr),
+                0xC => AsmLine::BIC(src, dst, is_byte_instr),
+                0xD => AsmLine::BIS(src, dst, is_byte_instr),
+                0xE => AsmLine::XOR(src, dst, is_byte_instr),
+                _ => AsmLine::AND(src, dst, is_byte_instr),
+            }
+        }
+        _ => return None,
+    };
+
+    let len = ext_word_addr.overflowing_sub(addr).0;
+    Some((line, len))
+}
+
+pub fn disassemble_to_string(
+    bus: &Bus,
+    addr: u16,
+    symbols: &HashMap<String, u16>,
+) -> (String, u16) {
+    // like disassemble, but words that aren't instructions are shown as data
+    match disassemble(bus, addr, symbols) {
+        Some((line, len)) => (line.to_string(), len),
+        None => (format!(".word 0x{:04X}", bus.peek_word(addr)), 2),
+    }
+}
+
+fn decode_src_operand(
+    reg_id: u16,
+    addr_mode: u16,
+    next_ext_word: &mut impl FnMut() -> u16,
+) -> Operand {
+    // R2 and R3 double as constant generators in some addressing modes
+    let reg = Reg::from_bits(reg_id);
+    match (reg, addr_mode) {
+        (Reg::CG, 0) => Operand::Imm(0),
+        (Reg::CG, 1) => Operand::Imm(1),
+        (Reg::CG, 2) => Operand::Imm(2),
+        (Reg::CG, _) => Operand::Imm(u16::MAX),
+        (Reg::SR, 1) => Operand::Abs(next_ext_word()),
+        (Reg::SR, 2) => Operand::Imm(4),
+        (Reg::SR, 3) => Operand::Imm(8),
+        (Reg::PC, 3) => Operand::Imm(next_ext_word()),
+        (r, 0) => Operand::Reg(r),
+        (r, 1) => Operand::IndexedReg(r, next_ext_word() as i16),
+        (r, 2) => Operand::Indirect(r),
+        (r, _) => Operand::IndirectAutoInc(r),
+    }
+}
+
+fn decode_dst_operand(instr: u16, next_ext_word: &mut impl FnMut() -> u16) -> Operand {
+    let reg = Reg::from_bits(instr);
+    match (reg, instr & 0x0080 != 0) {
+        (r, false) => Operand::Reg(r),
+        (Reg::SR, true) => Operand::Abs(next_ext_word()),
+        (r, true) => Operand::IndexedReg(r, next_ext_word() as i16),
+    }
+}
+
+fn name_data_addr(op: Operand, symbols: &HashMap<String, u16>) -> Operand {
+    // only absolute addresses are named. immediates are left alone, since a
+    // constant that happens to equal a symbol's address is usually just a constant.
+    match op {
+        Operand::Abs(addr) => match find_symbol(symbols, addr) {
+            Some(label) => Operand::AbsLabel(Expr::Label(label.to_owned())),
+            None => op,
+        },
+        _ => op,
+    }
+}
+
+fn name_code_addr(op: Operand, symbols: &HashMap<String, u16>) -> Operand {
+    // code labels are encoded two less than the address they refer to,
+    // since a taken branch lands two bytes after its target value.
+    match op {
+        Operand::Imm(imm) => match find_symbol(symbols, imm.overflowing_add(2).0) {
+            Some(label) => Operand::ImmLabel(Expr::Label(label.to_owned())),
+            None => op,
+        },
+        _ => name_data_addr(op, symbols),
+    }
+}
+
+fn name_symbolic_addr(
+    op: Operand,
+    index_word_addr: u16,
+    symbols: &HashMap<String, u16>,
+) -> Operand {
+    // an index from PC is relative to the address of the index word
+    match op {
+        Operand::IndexedReg(Reg::PC, index) => {
+            let addr = index_word_addr.overflowing_add(index as u16).0;
+            match find_symbol(symbols, addr) {
+                Some(label) => Operand::Symbolic(Expr::Label(label.to_owned())),
+                None => op,
+            }
+        }
+        _ => op,
+    }
+}
+
+fn find_symbol(symbols: &HashMap<String, u16>, addr: u16) -> Option<&str> {
+    // if several labels share an address, prefer ones that weren't generated by the compiler
+    symbols
+        .iter()
+        .filter(|(_, a)| **a == addr)
+        .map(|(label, _)| label.as_str())
+        .min_by_key(|label| (label.starts_with('$'), *label))
+}
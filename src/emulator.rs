@@ -1,20 +1,47 @@
+pub mod bus;
+pub mod coverage;
+pub mod cycles;
+pub mod debugger;
 pub mod double_operand;
+pub mod fault;
+pub mod gdb_server;
+pub mod interrupts;
+pub mod journal;
+pub mod peripherals;
+pub mod profiler;
 pub mod single_operand;
+pub mod snapshot;
 pub mod stages;
 pub mod tests;
+pub mod tracer;
+pub mod watchpoints;
 
+use self::bus::{Bus, Peripheral};
+use self::coverage::Coverage;
+use self::cycles::INTERRUPT_ENTRY_CYCLES;
+use self::fault::CpuFault;
+use self::interrupts::{
+    exec_interrupt_entry, get_highest_pending_interrupt, get_vector_addr, CPUOFF_BIT, GIE_BIT,
+};
+use self::journal::{Journal, JournalEntry};
+use self::peripherals::{buttons::Buttons, leds::Leds, switches::Switches, timer::Timer, vga::Vga};
+use self::profiler::Profiler;
 use self::stages::{
     stage_0::{exec_stage_0, Stage0Result},
     stage_1::{exec_stage_1, Stage1Result},
-    stage_2::{exec_stage_2a, exec_stage_2b, Stage2AResult, Stage2BResult},
+    stage_2::{exec_stage_2a, exec_stage_2b, Stage2AResult, Stage2BInput, Stage2BResult},
     stage_3::{exec_stage_3a, exec_stage_3b, Stage3AResult, Stage3BResult},
     stage_4::{exec_stage_4, Stage4Result},
-    stage_5::{exec_stage_5a, exec_stage_5b, Stage5Result},
+    stage_5::{exec_stage_5a, exec_stage_5b, Stage5AInput, Stage5Result},
 };
+use self::tracer::{LatchValue, Stage, Tracer};
+use self::watchpoints::{WatchAction, WatchHit, WatchKind, Watchpoints};
 use std::fmt;
+use std::ops::RangeInclusive;
 
+#[derive(Default)]
 pub struct Emulator {
-    pub mem: [u8; 65536],
+    pub bus: Bus,
     regs: [u16; 16],
 
     curr_instr: u16,
@@ -29,13 +56,17 @@ pub struct Emulator {
 
     is_byte_instr: bool, // 1 bit quantity
 
+    num_cycles: u8, // the cost of the current instruction
+
     operand_0: u16,
     operand_1: u16,
 
     mem_read_addr_0: u16,
+    mem_read_en_0: bool,
     used_instr_word_for_src: bool,
 
     mem_read_addr_1: u16,
+    mem_read_en_1: bool,
     used_instr_word_for_dst: bool,
 
     inc_src_reg: bool,
@@ -47,9 +78,24 @@ pub struct Emulator {
     new_vf: Option<bool>,
 
     result: u16,
-    new_pc_val: u16, // if this value is nonzero, that means jump is taken. Otherwise, jump is not taken.
+    new_pc_val: u16, // only meaningful if jump_taken is set
+    jump_taken: bool,
 
     mem_write_addr: u16,
+    mem_write_en: bool,
+
+    pending_interrupts: u16, // 1 bit per vector, for interrupts requested with request_interrupt
+
+    cycle_count: u64, // total number of cycles executed since the emulator was created
+    cycle_deadline: u64, // run_cycles executes instructions until cycle_count reaches this
+
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    watchpoints: Watchpoints,
+
+    instr_index: u64, // the number of steps run since the emulator was created, less any undone
+    journal: Journal,
 }
 
 impl fmt::Debug for Emulator {
@@ -63,62 +109,436 @@ impl fmt::Debug for Emulator {
 
 impl Emulator {
     pub fn new(instrs: &Vec<u8>) -> Self {
-        let mut emulator: Self = unsafe { std::mem::zeroed() };
+        let mut emulator = Self::default();
+        emulator.bus.load(instrs);
 
-        let mut mem = [0u8; 65536];
-        for (index, instr) in instrs.iter().enumerate() {
-            mem[index] = *instr;
-        }
-        emulator.mem = mem;
+        emulator.register_peripheral(Box::new(Vga::new()));
+        emulator.register_peripheral(Box::new(Switches::new()));
+        emulator.register_peripheral(Box::new(Buttons::new()));
+        emulator.register_peripheral(Box::new(Leds::new()));
+        emulator.register_peripheral(Box::new(Timer::new()));
 
         emulator
     }
 
+    pub fn register_peripheral(&mut self, peripheral: Box<dyn Peripheral>) {
+        // a peripheral registered later takes over any addresses that it shares with earlier ones,
+        // so the built in devices can be replaced.
+        self.bus.register_peripheral(peripheral);
+    }
+
     pub fn get_gfx_buffer(&self) -> &[u8] {
-        return &self.mem.as_slice()[0x8000..=0x895F];
+        return self.bus.get_peripheral::<Vga>().unwrap().get_buffer();
     }
 
     pub fn get_led_output(&self) -> u16 {
-        return u16::from_le_bytes([self.mem[0x8A04], self.mem[0x8A05]]);
+        return self.bus.get_peripheral::<Leds>().unwrap().get_states();
+    }
+    pub fn get_switch_states(&self) -> u16 {
+        self.bus.get_peripheral::<Switches>().unwrap().get_states()
     }
     pub fn set_switch_states(&mut self, new_states: u16) {
-        [self.mem[0x8A00], self.mem[0x8A01]] = new_states.to_le_bytes();
+        self.bus
+            .get_peripheral_mut::<Switches>()
+            .unwrap()
+            .set_states(new_states);
     }
     pub fn set_button_states(&mut self, new_states: u8) {
-        self.mem[0x8A02] = new_states;
+        self.bus
+            .get_peripheral_mut::<Buttons>()
+            .unwrap()
+            .set_states(new_states);
+    }
+
+    pub fn set_interrupt_vector(&mut self, vector: u16, handler: u16) {
+        self.bus.write_word(get_vector_addr(vector), handler);
+    }
+
+    pub fn request_interrupt(&mut self, vector: u16) {
+        self.pending_interrupts |= 1 << vector;
+    }
+
+    pub fn get_cycle_count(&self) -> u64 {
+        self.cycle_count
     }
 
-    pub fn run_some_instrs(&mut self) {
-        for _ in 0..20 {
-            self.run_one_instr();
+    pub fn get_regs(&self) -> &[u16; 16] {
+        &self.regs
+    }
+
+    pub fn is_halted(&self) -> bool {
+        // with interrupts disabled, nothing can wake the cpu from low power mode
+        // or break out of a jump to itself.
+        if self.regs[2] & GIE_BIT != 0 {
+            return false;
+        }
+        self.regs[2] & CPUOFF_BIT != 0 || self.bus.peek_word(self.regs[0]) == 0x3FFF
+    }
+
+    pub fn run_cycles(&mut self, num_cycles: u64) -> Result<(), CpuFault> {
+        // an instruction that runs past the deadline borrows its extra cycles from the next call,
+        // so that the average speed matches the requested number of cycles.
+        self.cycle_deadline += num_cycles;
+        while self.cycle_count < self.cycle_deadline {
+            self.run_one_instr()?;
+            if self.watchpoints.has_break_hit() {
+                // stop early, without owing the remaining cycles to the next call
+                self.cycle_deadline = self.cycle_count;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run_one_instr(&mut self) -> Result<u8, CpuFault> {
+        // returns the number of cycles taken by the instruction
+        let regs_before = self.regs;
+        let cycle = self.cycle_count;
+        if self.journal.is_enabled() {
+            self.journal.begin_step(JournalEntry {
+                regs: self.regs,
+                cycle_count: self.cycle_count,
+                pending_interrupts: self.pending_interrupts,
+                mem_writes: Vec::new(),
+                mem_reads: Vec::new(),
+                device_states: self.bus.save_tick_states(),
+            });
+        }
+        if let Some(vector) = self.accept_interrupt() {
+            // the hardware push of PC and SR takes the place of an instruction
+            self.retire_cycles(INTERRUPT_ENTRY_CYCLES);
+            if let Some(tracer) = &mut self.tracer {
+                let pushes = [
+                    (regs_before[1].overflowing_sub(2).0, regs_before[0]),
+                    (regs_before[1].overflowing_sub(4).0, regs_before[2]),
+                ];
+                tracer.trace_interrupt(cycle, vector, &regs_before, &self.regs, pushes);
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record_interrupt(regs_before[0], &self.regs, INTERRUPT_ENTRY_CYCLES);
+            }
+            return Ok(INTERRUPT_ENTRY_CYCLES);
+        }
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.begin_instr(&self.bus, self.regs[0]);
+        }
+        if let Err(fault) = self.exec_stages(regs_before[0]) {
+            if let Some(tracer) = &mut self.tracer {
+                tracer.trace_fault(cycle, regs_before[0], &fault);
+            }
+            self.journal.abandon_step();
+            return Err(fault);
+        }
+        self.retire_cycles(self.num_cycles);
+
+        if let Some(tracer) = &mut self.tracer {
+            let mem_write = match (self.mem_write_en, self.is_byte_instr) {
+                (false, _) => None,
+                (true, true) => Some((self.mem_write_addr, LatchValue::Byte(self.result as u8))),
+                (true, false) => Some((self.mem_write_addr, LatchValue::Word(self.result))),
+            };
+            tracer.trace_instr(
+                cycle,
+                regs_before[0],
+                &regs_before,
+                &self.regs,
+                mem_write,
+                self.num_cycles,
+            );
         }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_instr(regs_before[0], self.curr_instr, &self.regs, self.num_cycles);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_instr(regs_before[0], self.curr_instr, self.jump_taken);
+        }
+        Ok(self.num_cycles)
     }
 
-    pub fn run_one_instr(&mut self) {
-        self.stage_0();
-        self.stage_1();
+    fn exec_stages(&mut self, pc: u16) -> Result<(), CpuFault> {
+        self.stage_0()?;
+        self.trace_stage(Stage::Stage0);
+        self.stage_1()?;
+        self.trace_stage(Stage::Stage1);
         self.stage_2a();
+        self.trace_stage(Stage::Stage2a);
         self.stage_2b();
+        self.trace_stage(Stage::Stage2b);
+        if self.mem_read_en_0 {
+            self.record_read(pc, self.mem_read_addr_0, self.operand_0);
+        }
         self.stage_3a();
+        self.trace_stage(Stage::Stage3a);
         self.stage_3b();
+        self.trace_stage(Stage::Stage3b);
+        if self.mem_read_en_1 {
+            self.record_read(pc, self.mem_read_addr_1, self.operand_1);
+        }
         self.stage_4();
-        self.stage_5a();
+        self.trace_stage(Stage::Stage4);
+        self.stage_5a()?;
+        self.trace_stage(Stage::Stage5a);
+        // stage 5b is the only place that an instruction writes to memory
+        let is_write_recorded =
+            self.mem_write_en && (self.journal.is_enabled() || !self.watchpoints.is_empty());
+        let old_value = match is_write_recorded {
+            true => self.peek_value(self.mem_write_addr, self.is_byte_instr),
+            false => 0,
+        };
         self.stage_5b();
+        if is_write_recorded {
+            self.record_write(pc, self.mem_write_addr, self.is_byte_instr, old_value);
+        }
+        Ok(())
+    }
+
+    pub fn set_journal_capacity(&mut self, capacity: usize) {
+        // turns on reverse execution, keeping enough history to undo capacity steps.
+        // a capacity of 0 turns it off and forgets the history.
+        self.journal.set_capacity(capacity);
+        if capacity == 0 {
+            self.journal.clear();
+        }
+    }
+
+    pub fn get_journal_len(&self) -> usize {
+        // the number of steps that can be undone
+        self.journal.len()
+    }
+
+    pub fn get_instr_index(&self) -> u64 {
+        self.instr_index
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        // undoes the last step, returning false if the journal has nothing left to undo.
+        // watchpoints are checked against the accesses made by the step, as if it had run again.
+        let entry = match self.journal.pop() {
+            Some(e) => e,
+            None => return false,
+        };
+        let pc = entry.regs[0];
+        if !self.watchpoints.is_empty() {
+            for (addr, is_byte, value) in &entry.mem_reads {
+                self.watchpoints
+                    .check(pc, *addr, *is_byte, false, *value, *value);
+            }
+            for (addr, is_byte, old_value) in &entry.mem_writes {
+                let new_value = self.peek_value(*addr, *is_byte);
+                self.watchpoints
+                    .check(pc, *addr, *is_byte, true, *old_value, new_value);
+            }
+        }
+
+        for (addr, is_byte, old_value) in entry.mem_writes.iter().rev() {
+            if *is_byte {
+                self.bus.write_byte(*addr, *old_value as u8);
+            } else {
+                self.bus.write_word(*addr, *old_value);
+            }
+        }
+        // restored after the memory, in case writing to a device changed more than its registers
+        for (index, state) in &entry.device_states {
+            self.bus
+                .load_peripheral_state(*index, state)
+                .expect("a device rejected its own saved state");
+        }
+        self.regs = entry.regs;
+        self.pending_interrupts = entry.pending_interrupts;
+        self.cycle_count = entry.cycle_count;
+        // run_cycles shouldn't try to make up for the undone cycles
+        self.cycle_deadline = self.cycle_deadline.min(self.cycle_count);
+        self.instr_index -= 1;
+        true
+    }
+
+    pub fn rewind_to(&mut self, instr_index: u64) -> bool {
+        // undoes steps until get_instr_index returns instr_index. returns false without
+        // changing anything if that is in the future, or older than the journal goes back.
+        let num_steps = match self.instr_index.checked_sub(instr_index) {
+            Some(n) if n <= self.journal.len() as u64 => n,
+            _ => return false,
+        };
+        for _ in 0..num_steps {
+            self.step_back();
+        }
+        true
+    }
+
+    fn record_read(&mut self, pc: u16, addr: u16, value: u16) {
+        self.journal.record_read(addr, self.is_byte_instr, value);
+        if !self.watchpoints.is_empty() {
+            self.watchpoints
+                .check(pc, addr, self.is_byte_instr, false, value, value);
+        }
+    }
+
+    fn record_write(&mut self, pc: u16, addr: u16, is_byte: bool, old_value: u16) {
+        // called after the write, with the value from before it
+        self.journal.record_write(addr, is_byte, old_value);
+        if !self.watchpoints.is_empty() {
+            let new_value = self.peek_value(addr, is_byte);
+            self.watchpoints
+                .check(pc, addr, is_byte, true, old_value, new_value);
+        }
+    }
+
+    pub fn add_watchpoint(
+        &mut self,
+        range: RangeInclusive<u16>,
+        kind: WatchKind,
+        action: WatchAction,
+    ) -> usize {
+        // returns an id for remove_watchpoint
+        self.watchpoints.add(range, kind, action)
+    }
+
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        self.watchpoints.remove(id)
+    }
+
+    pub fn get_watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        // hits collect until they are taken, so callers should take them after each instruction
+        self.watchpoints.take_hits()
+    }
+
+    fn peek_value(&self, addr: u16, is_byte: bool) -> u16 {
+        // the new value of a write is read back, since some registers (like BTNS_IFG) ignore writes
+        if is_byte {
+            self.bus.peek_byte(addr) as u16
+        } else {
+            self.bus.peek_word(addr)
+        }
+    }
+
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        // returns the previous tracer, so that it can be finished
+        std::mem::replace(&mut self.tracer, tracer)
+    }
+
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) -> Option<Profiler> {
+        // returns the previous profiler, so that its results can be written
+        std::mem::replace(&mut self.profiler, profiler)
+    }
+
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) -> Option<Coverage> {
+        // returns the previous coverage, so that it can be reported
+        std::mem::replace(&mut self.coverage, coverage)
+    }
+
+    fn trace_stage(&mut self, stage: Stage) {
+        // records the latches written by a stage, in verbose traces
+        let tracer = match &mut self.tracer {
+            Some(tracer) if tracer.is_verbose() => tracer,
+            _ => return,
+        };
+        use LatchValue::{Bool, Byte, Flag, Word};
+        let latches = match stage {
+            Stage::Stage0 => vec![
+                ("curr_instr", Word(self.curr_instr)),
+                ("next_word", Word(self.next_word)),
+                ("next_next_word", Word(self.next_next_word)),
+            ],
+            Stage::Stage1 => vec![
+                ("opcode", Word(self.opcode)),
+                ("src_reg_id", Word(self.src_reg_id)),
+                ("src_addr_mode", Word(self.src_addr_mode)),
+                ("dst_reg_id", Word(self.dst_reg_id)),
+                ("dst_addr_mode", Word(self.dst_addr_mode)),
+                ("is_byte_instr", Bool(self.is_byte_instr)),
+                ("num_cycles", Byte(self.num_cycles)),
+            ],
+            Stage::Stage2a => vec![
+                ("mem_read_addr_0", Word(self.mem_read_addr_0)),
+                ("mem_read_en_0", Bool(self.mem_read_en_0)),
+                ("inc_src_reg", Bool(self.inc_src_reg)),
+                (
+                    "used_instr_word_for_src",
+                    Bool(self.used_instr_word_for_src),
+                ),
+            ],
+            Stage::Stage2b => vec![("operand_0", Word(self.operand_0))],
+            Stage::Stage3a => vec![
+                ("mem_read_addr_1", Word(self.mem_read_addr_1)),
+                ("mem_read_en_1", Bool(self.mem_read_en_1)),
+                (
+                    "used_instr_word_for_dst",
+                    Bool(self.used_instr_word_for_dst),
+                ),
+            ],
+            Stage::Stage3b => vec![("operand_1", Word(self.operand_1))],
+            Stage::Stage4 => vec![
+                ("result", Word(self.result)),
+                ("new_cf", Flag(self.new_cf)),
+                ("new_zf", Flag(self.new_zf)),
+                ("new_nf", Flag(self.new_nf)),
+                ("new_vf", Flag(self.new_vf)),
+                ("dec_sp", Bool(self.dec_sp)),
+                ("new_pc_val", Word(self.new_pc_val)),
+                ("jump_taken", Bool(self.jump_taken)),
+            ],
+            Stage::Stage5a => vec![
+                ("mem_write_addr", Word(self.mem_write_addr)),
+                ("mem_write_en", Bool(self.mem_write_en)),
+            ],
+        };
+        tracer.record_stage(stage, latches);
+    }
+
+    fn retire_cycles(&mut self, num_cycles: u8) {
+        // every step ends here, whether it ran an instruction or entered an interrupt
+        self.cycle_count += num_cycles as u64;
+        self.bus.tick(num_cycles);
+        self.instr_index += 1;
+        self.journal.end_step();
     }
 
-    fn stage_0(&mut self) {
+    fn get_pending_interrupts(&self) -> u16 {
+        self.pending_interrupts | self.bus.get_pending_interrupts()
+    }
+
+    fn accept_interrupt(&mut self) -> Option<u16> {
+        // returns the vector of the interrupt that was taken, if any
+        if self.regs[2] & GIE_BIT == 0 {
+            return None;
+        }
+        let vector = get_highest_pending_interrupt(self.get_pending_interrupts())?;
+        self.pending_interrupts &= !(1 << vector);
+        if !self.journal.is_enabled() && self.watchpoints.is_empty() {
+            self.regs = exec_interrupt_entry(self.regs, vector, &mut self.bus);
+            return Some(vector);
+        }
+        // the pushes of PC and SR are writes too, made at the pc of the interrupted instruction
+        let pc = self.regs[0];
+        let pc_slot = self.regs[1].overflowing_sub(2).0;
+        let sr_slot = self.regs[1].overflowing_sub(4).0;
+        let old_values = [self.bus.peek_word(pc_slot), self.bus.peek_word(sr_slot)];
+        self.regs = exec_interrupt_entry(self.regs, vector, &mut self.bus);
+        for (addr, old_value) in [pc_slot, sr_slot].into_iter().zip(old_values) {
+            self.record_write(pc, addr, false, old_value);
+        }
+        Some(vector)
+    }
+
+    fn stage_0(&mut self) -> Result<(), CpuFault> {
         // load current instruction and subsequent words
         let Stage0Result {
             curr_instr,
             next_word,
             next_next_word,
-        } = exec_stage_0(&self.mem, self.regs[0]);
+        } = exec_stage_0(&mut self.bus, self.regs[0])?;
         self.curr_instr = curr_instr;
         self.next_word = next_word;
         self.next_next_word = next_next_word;
+        Ok(())
     }
 
-    fn stage_1(&mut self) {
+    fn stage_1(&mut self) -> Result<(), CpuFault> {
         // decode instruction
         let Stage1Result {
             src_addr_mode,
@@ -127,7 +547,8 @@ impl Emulator {
             opcode,
             src_reg_id,
             dst_reg_id,
-        } = exec_stage_1(self.curr_instr);
+            num_cycles,
+        } = exec_stage_1(self.curr_instr, self.regs[0])?;
 
         self.src_addr_mode = src_addr_mode;
         self.dst_addr_mode = dst_addr_mode;
@@ -135,12 +556,15 @@ impl Emulator {
         self.opcode = opcode;
         self.src_reg_id = src_reg_id;
         self.dst_reg_id = dst_reg_id;
+        self.num_cycles = num_cycles;
+        Ok(())
     }
 
     fn stage_2a(&mut self) {
         // calculate memory load address
         let Stage2AResult {
             mem_read_addr_0,
+            mem_read_en_0,
             inc_src_reg,
             used_instr_word_for_src,
         } = exec_stage_2a(
@@ -151,6 +575,7 @@ impl Emulator {
             &self.regs,
         );
         self.mem_read_addr_0 = mem_read_addr_0;
+        self.mem_read_en_0 = mem_read_en_0;
         self.inc_src_reg = inc_src_reg;
         self.used_instr_word_for_src = used_instr_word_for_src;
     }
@@ -158,11 +583,15 @@ impl Emulator {
     fn stage_2b(&mut self) {
         // load operand 0
         let Stage2BResult { operand_0 } = exec_stage_2b(
-            self.curr_instr,
-            self.src_addr_mode,
-            self.src_reg_id,
-            self.mem_read_addr_0,
-            &self.mem,
+            Stage2BInput {
+                curr_instr: self.curr_instr,
+                src_addr_mode: self.src_addr_mode,
+                src_reg_id: self.src_reg_id,
+                is_byte_instr: self.is_byte_instr,
+                mem_read_addr_0: self.mem_read_addr_0,
+                mem_read_en_0: self.mem_read_en_0,
+            },
+            &mut self.bus,
             &self.regs,
         );
         self.operand_0 = operand_0;
@@ -171,6 +600,7 @@ impl Emulator {
     fn stage_3a(&mut self) {
         let Stage3AResult {
             mem_read_addr_1,
+            mem_read_en_1,
             used_instr_word_for_dst,
         } = exec_stage_3a(
             self.curr_instr,
@@ -182,12 +612,19 @@ impl Emulator {
             &self.regs,
         );
         self.mem_read_addr_1 = mem_read_addr_1;
+        self.mem_read_en_1 = mem_read_en_1;
         self.used_instr_word_for_dst = used_instr_word_for_dst;
     }
 
     fn stage_3b(&mut self) {
-        let Stage3BResult { operand_1 } =
-            exec_stage_3b(self.dst_reg_id, self.mem_read_addr_1, &self.mem, &self.regs);
+        let Stage3BResult { operand_1 } = exec_stage_3b(
+            self.dst_reg_id,
+            self.is_byte_instr,
+            self.mem_read_addr_1,
+            self.mem_read_en_1,
+            &mut self.bus,
+            &self.regs,
+        );
         self.operand_1 = operand_1;
     }
 
@@ -201,9 +638,11 @@ impl Emulator {
             new_vf,
             result,
             new_pc_val,
+            jump_taken,
         } = exec_stage_4(
             self.curr_instr,
             self.opcode,
+            self.is_byte_instr,
             self.operand_0,
             self.operand_1,
             &self.regs,
@@ -215,38 +654,53 @@ impl Emulator {
         self.new_vf = new_vf;
         self.result = result;
         self.new_pc_val = new_pc_val;
+        self.jump_taken = jump_taken;
     }
 
-    fn stage_5a(&mut self) {
+    fn stage_5a(&mut self) -> Result<(), CpuFault> {
         let Stage5Result {
             regs,
             mem_write_addr,
+            mem_write_en,
         } = exec_stage_5a(
             self.regs,
-            self.inc_src_reg,
-            self.dec_sp,
-            self.new_cf,
-            self.new_zf,
-            self.new_nf,
-            self.new_vf,
-            self.src_reg_id,
-            self.dst_reg_id,
-            self.curr_instr,
-            self.opcode,
-            self.src_addr_mode,
-            self.dst_addr_mode,
-            self.result,
-            self.mem_read_addr_0,
-            self.mem_read_addr_1,
-            self.new_pc_val,
-            self.used_instr_word_for_src,
-            self.used_instr_word_for_dst,
-        );
+            Stage5AInput {
+                inc_src_reg: self.inc_src_reg,
+                dec_sp: self.dec_sp,
+                new_cf: self.new_cf,
+                new_zf: self.new_zf,
+                new_nf: self.new_nf,
+                new_vf: self.new_vf,
+                src_reg_id: self.src_reg_id,
+                dst_reg_id: self.dst_reg_id,
+                curr_instr: self.curr_instr,
+                opcode: self.opcode,
+                is_byte_instr: self.is_byte_instr,
+                src_addr_mode: self.src_addr_mode,
+                dst_addr_mode: self.dst_addr_mode,
+                result: self.result,
+                mem_read_addr_0: self.mem_read_addr_0,
+                mem_read_en_0: self.mem_read_en_0,
+                mem_read_addr_1: self.mem_read_addr_1,
+                new_pc_val: self.new_pc_val,
+                jump_taken: self.jump_taken,
+                used_instr_word_for_src: self.used_instr_word_for_src,
+                used_instr_word_for_dst: self.used_instr_word_for_dst,
+            },
+        )?;
         self.regs = regs;
         self.mem_write_addr = mem_write_addr;
+        self.mem_write_en = mem_write_en;
+        Ok(())
     }
 
     fn stage_5b(&mut self) {
-        exec_stage_5b(self.mem_write_addr, self.result, &mut self.mem);
+        exec_stage_5b(
+            self.mem_write_addr,
+            self.mem_write_en,
+            self.result,
+            self.is_byte_instr,
+            &mut self.bus,
+        );
     }
 }
@@ -0,0 +1,188 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::snapshot::{invalid_snapshot, SnapshotReader, SnapshotWriter};
+
+// A memory mapped device. The bus forwards every access to an address in one of the
+// device's ranges to the device instead of to RAM. Addresses are passed unchanged,
+// so a device with several registers can match on them directly.
+pub trait Peripheral: Any {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>>;
+
+    // reads without side effects, for debuggers and the GUI
+    fn peek_byte(&self, addr: u16) -> u8;
+
+    // reads performed by the cpu. devices which change state when read override this.
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        self.peek_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8);
+
+    // called after every instruction with the number of cycles it took
+    fn tick(&mut self, _num_cycles: u8) {}
+
+    // whether tick changes the device's state. the state of these devices is saved before
+    // every step when reverse execution is on, since their changes don't go through the bus.
+    fn has_tick_state(&self) -> bool {
+        false
+    }
+
+    // 1 bit per interrupt vector that this device is currently requesting
+    fn get_pending_interrupts(&self) -> u16 {
+        0
+    }
+
+    // state saved in emulator snapshots. devices without any state can keep the defaults.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn load_state(&mut self, _state: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+pub struct Bus {
+    ram: Vec<u8>,
+    peripherals: Vec<Box<dyn Peripheral>>,
+    // for each address, 0 if it is backed by ram, otherwise the index of its peripheral plus one
+    device_map: Vec<u8>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Bus {
+            ram: vec![0; 65536],
+            peripherals: Vec::new(),
+            device_map: vec![0; 65536],
+        }
+    }
+}
+
+impl Bus {
+    pub fn register_peripheral(&mut self, peripheral: Box<dyn Peripheral>) {
+        // a peripheral registered later takes over any addresses that it shares with earlier ones
+        assert!(
+            self.peripherals.len() < u8::MAX as usize,
+            "too many peripherals"
+        );
+        let device_id = self.peripherals.len() as u8 + 1;
+        for range in peripheral.get_addr_ranges() {
+            for addr in range {
+                self.device_map[addr as usize] = device_id;
+            }
+        }
+        self.peripherals.push(peripheral);
+    }
+
+    pub fn get_peripheral<T: Peripheral>(&self) -> Option<&T> {
+        self.peripherals
+            .iter()
+            .find_map(|p| p.as_any().downcast_ref::<T>())
+    }
+
+    pub fn get_peripheral_mut<T: Peripheral>(&mut self) -> Option<&mut T> {
+        self.peripherals
+            .iter_mut()
+            .find_map(|p| p.as_any_mut().downcast_mut::<T>())
+    }
+
+    pub fn load(&mut self, bytes: &[u8]) {
+        // copies a program into ram, starting at address 0. panics if it doesn't fit
+        self.ram[..bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn peek_byte(&self, addr: u16) -> u8 {
+        match self.device_map[addr as usize] {
+            0 => self.ram[addr as usize],
+            id => self.peripherals[id as usize - 1].peek_byte(addr),
+        }
+    }
+
+    pub fn read_byte(&mut self, addr: u16) -> u8 {
+        match self.device_map[addr as usize] {
+            0 => self.ram[addr as usize],
+            id => self.peripherals[id as usize - 1].read_byte(addr),
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        match self.device_map[addr as usize] {
+            0 => self.ram[addr as usize] = value,
+            id => self.peripherals[id as usize - 1].write_byte(addr, value),
+        }
+    }
+
+    pub fn peek_word(&self, addr: u16) -> u16 {
+        let low_byte = self.peek_byte(addr);
+        let high_byte = self.peek_byte(addr.overflowing_add(1).0);
+        u16::from_le_bytes([low_byte, high_byte])
+    }
+
+    pub fn read_word(&mut self, addr: u16) -> u16 {
+        let low_byte = self.read_byte(addr);
+        let high_byte = self.read_byte(addr.overflowing_add(1).0);
+        u16::from_le_bytes([low_byte, high_byte])
+    }
+
+    pub fn write_word(&mut self, addr: u16, value: u16) {
+        let [low_byte, high_byte] = value.to_le_bytes();
+        self.write_byte(addr, low_byte);
+        self.write_byte(addr.overflowing_add(1).0, high_byte);
+    }
+
+    pub fn tick(&mut self, num_cycles: u8) {
+        for peripheral in self.peripherals.iter_mut() {
+            peripheral.tick(num_cycles);
+        }
+    }
+
+    pub fn save_state(&self, w: &mut SnapshotWriter) {
+        w.put_bytes(&self.ram);
+        w.put_u16(self.peripherals.len() as u16);
+        for peripheral in self.peripherals.iter() {
+            let state = peripheral.save_state();
+            w.put_u32(state.len() as u32);
+            w.put_bytes(&state);
+        }
+    }
+
+    pub fn load_state(&mut self, r: &mut SnapshotReader) -> io::Result<()> {
+        let ram = r.get_bytes(self.ram.len())?;
+        self.ram.copy_from_slice(ram);
+        if r.get_u16()? as usize != self.peripherals.len() {
+            return Err(invalid_snapshot(
+                "snapshot was saved with different peripherals",
+            ));
+        }
+        for peripheral in self.peripherals.iter_mut() {
+            let len = r.get_u32()? as usize;
+            peripheral.load_state(r.get_bytes(len)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_tick_states(&self) -> Vec<(usize, Vec<u8>)> {
+        // the states of the devices with has_tick_state, by peripheral index
+        self.peripherals
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.has_tick_state())
+            .map(|(i, p)| (i, p.save_state()))
+            .collect()
+    }
+
+    pub fn load_peripheral_state(&mut self, index: usize, state: &[u8]) -> io::Result<()> {
+        self.peripherals[index].load_state(state)
+    }
+
+    pub fn get_pending_interrupts(&self) -> u16 {
+        self.peripherals
+            .iter()
+            .fold(0, |pending, p| pending | p.get_pending_interrupts())
+    }
+}
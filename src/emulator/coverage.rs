@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::source_map::SourceMap;
+
+// Records how many times each instruction ran, and how many times each conditional jump was
+// taken and fell through. Reports map the counts back to source lines, as an annotated listing
+// in the style of gcov, or as an lcov tracefile for genhtml and editor plugins.
+// Interrupt entries are not instructions, so they are not counted.
+
+const JUMP_FAMILY_MASK: u16 = 0xE000;
+const JUMP_FAMILY: u16 = 0x2000;
+const UNCONDITIONAL_JUMP_CC: u16 = 7;
+
+#[derive(Default, Clone, Copy)]
+struct BranchCounts {
+    taken: u64,
+    not_taken: u64,
+}
+
+#[derive(Default)]
+struct LineCounts {
+    count: u64, // the most times any instruction on the line ran
+    // one entry per conditional jump on the line, or None for jumps that never ran
+    branches: Vec<Option<BranchCounts>>,
+}
+
+#[derive(Default)]
+pub struct Coverage {
+    instr_counts: HashMap<u16, u64>,
+    branch_counts: HashMap<u16, BranchCounts>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_instr(&mut self, pc: u16, instr: u16, is_jump_taken: bool) {
+        *self.instr_counts.entry(pc).or_default() += 1;
+        let is_conditional_jump =
+            instr & JUMP_FAMILY_MASK == JUMP_FAMILY && (instr >> 10) & 0x7 != UNCONDITIONAL_JUMP_CC;
+        if is_conditional_jump {
+            let counts = self.branch_counts.entry(pc).or_default();
+            match is_jump_taken {
+                true => counts.taken += 1,
+                false => counts.not_taken += 1,
+            }
+        }
+    }
+
+    pub fn get_instr_count(&self, addr: u16) -> u64 {
+        self.instr_counts.get(&addr).copied().unwrap_or(0)
+    }
+
+    pub fn write_annotated_listing(
+        &self,
+        out: &mut dyn Write,
+        source_map: &SourceMap,
+    ) -> io::Result<()> {
+        // every source line, prefixed with how many times its instruction ran, or with "-" for
+        // lines without an instruction and "#####" for ones that never ran, as gcov does
+        let line_counts = self.get_line_counts(source_map, Some);
+        let num_lines_hit = line_counts.values().filter(|c| c.count > 0).count();
+        let branches: Vec<&Option<BranchCounts>> =
+            line_counts.values().flat_map(|c| &c.branches).collect();
+        let num_branches_hit: usize = branches.iter().map(|b| count_branches_hit(b)).sum();
+        writeln!(
+            out,
+            "lines executed: {} of {}, branches taken at least once: {} of {}",
+            num_lines_hit,
+            line_counts.len(),
+            num_branches_hit,
+            branches.len() * 2
+        )?;
+
+        for (i, text) in source_map.get_source_lines().iter().enumerate() {
+            let line_num = i + 1;
+            let counts = match line_counts.get(&line_num) {
+                Some(c) => c,
+                None => {
+                    writeln!(out, "{:>9}:{:>5}:{}", "-", line_num, text)?;
+                    continue;
+                }
+            };
+            let count = match counts.count {
+                0 => "#####".to_owned(),
+                n => n.to_string(),
+            };
+            writeln!(out, "{:>9}:{:>5}:{}", count, line_num, text)?;
+            for branch in &counts.branches {
+                match branch {
+                    Some(b) => writeln!(
+                        out,
+                        "{:>16}branch taken {}, not taken {}",
+                        "", b.taken, b.not_taken
+                    )?,
+                    None => writeln!(out, "{:>16}branch never executed", "")?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_lcov(
+        &self,
+        out: &mut dyn Write,
+        source_map: &SourceMap,
+        asm_file_name: &str,
+        c_file_name: &str,
+    ) -> io::Result<()> {
+        // one record for the assembly source, and one for the C source if it was interlisted
+        let line_counts = self.get_line_counts(source_map, Some);
+        write_lcov_record(out, asm_file_name, &line_counts)?;
+        if source_map.has_c_lines() {
+            let line_counts =
+                self.get_line_counts(source_map, |line_num| source_map.get_c_line(line_num));
+            write_lcov_record(out, c_file_name, &line_counts)?;
+        }
+        Ok(())
+    }
+
+    fn get_line_counts(
+        &self,
+        source_map: &SourceMap,
+        map_line: impl Fn(usize) -> Option<usize>,
+    ) -> BTreeMap<usize, LineCounts> {
+        // map_line takes a line of the assembly source to the line that is being reported on
+        let mut line_counts: BTreeMap<usize, LineCounts> = BTreeMap::new();
+        for instr_line in source_map.get_instr_lines() {
+            let line_num = match map_line(instr_line.line_num) {
+                Some(l) => l,
+                None => continue,
+            };
+            let counts = line_counts.entry(line_num).or_default();
+            counts.count = counts.count.max(self.get_instr_count(instr_line.addr));
+            if instr_line.is_branch {
+                counts
+                    .branches
+                    .push(self.branch_counts.get(&instr_line.addr).copied());
+            }
+        }
+        line_counts
+    }
+}
+
+fn count_branches_hit(branch: &Option<BranchCounts>) -> usize {
+    match branch {
+        Some(b) => (b.taken > 0) as usize + (b.not_taken > 0) as usize,
+        None => 0,
+    }
+}
+
+fn write_lcov_record(
+    out: &mut dyn Write,
+    file_name: &str,
+    line_counts: &BTreeMap<usize, LineCounts>,
+) -> io::Result<()> {
+    writeln!(out, "TN:")?;
+    writeln!(out, "SF:{}", file_name)?;
+    // each conditional jump is a block of two branches: taken, then not taken
+    let mut num_branches = 0;
+    let mut num_branches_hit = 0;
+    for (line_num, counts) in line_counts {
+        for (block, branch) in counts.branches.iter().enumerate() {
+            let (taken, not_taken) = match branch {
+                Some(b) => (b.taken.to_string(), b.not_taken.to_string()),
+                None => ("-".to_owned(), "-".to_owned()),
+            };
+            writeln!(out, "BRDA:{},{},0,{}", line_num, block, taken)?;
+            writeln!(out, "BRDA:{},{},1,{}", line_num, block, not_taken)?;
+            num_branches += 2;
+            num_branches_hit += count_branches_hit(branch);
+        }
+    }
+    writeln!(out, "BRF:{}", num_branches)?;
+    writeln!(out, "BRH:{}", num_branches_hit)?;
+    for (line_num, counts) in line_counts {
+        writeln!(out, "DA:{},{}", line_num, counts.count)?;
+    }
+    writeln!(out, "LF:{}", line_counts.len())?;
+    writeln!(
+        out,
+        "LH:{}",
+        line_counts.values().filter(|c| c.count > 0).count()
+    )?;
+    writeln!(out, "end_of_record")
+}
@@ -0,0 +1,82 @@
+// Instruction timings, taken from the MSP430 family user's guide.
+
+pub const INTERRUPT_ENTRY_CYCLES: u8 = 6;
+
+pub fn get_instr_cycles(
+    curr_instr: u16,
+    opcode: u16,
+    src_addr_mode: u16,
+    src_reg_id: u16,
+    dst_addr_mode: u16,
+    dst_reg_id: u16,
+) -> u8 {
+    if (curr_instr & 0xE000) == 0x2000 {
+        // jumps take 2 cycles whether or not they are taken
+        return 2;
+    }
+
+    let src_mode = get_effective_src_mode(src_addr_mode, src_reg_id);
+
+    if (curr_instr & 0xE000) == 0 {
+        // single operand instruction
+        return match (opcode, src_mode) {
+            (6, _) => 5, // RETI
+
+            (4, SrcMode::Reg) => 3, // PUSH
+            (4, SrcMode::Indirect | SrcMode::IndirectAutoInc | SrcMode::Imm) => 4,
+            (4, SrcMode::Indexed) => 5,
+
+            (5, SrcMode::Reg | SrcMode::Indirect) => 4, // CALL
+            (5, SrcMode::IndirectAutoInc | SrcMode::Imm | SrcMode::Indexed) => 5,
+
+            // RRC, SWPB, RRA, SXT
+            (_, SrcMode::Reg) => 1,
+            (_, SrcMode::Indirect | SrcMode::IndirectAutoInc | SrcMode::Imm) => 3,
+            (_, SrcMode::Indexed) => 4,
+        };
+    }
+
+    // double operand instruction
+    let dst_is_pc = dst_addr_mode == 0 && dst_reg_id == 0;
+    match (src_mode, dst_addr_mode, dst_is_pc) {
+        (SrcMode::Reg, 0, false) => 1,
+        (SrcMode::Reg, 0, true) => 2,
+        (SrcMode::Reg, _, _) => 4,
+
+        (SrcMode::Indirect, 0, _) => 2,
+        (SrcMode::IndirectAutoInc | SrcMode::Imm, 0, false) => 2,
+        (SrcMode::IndirectAutoInc | SrcMode::Imm, 0, true) => 3,
+        (SrcMode::Indirect | SrcMode::IndirectAutoInc | SrcMode::Imm, _, _) => 5,
+
+        (SrcMode::Indexed, 0, _) => 3,
+        (SrcMode::Indexed, _, _) => 6,
+    }
+}
+
+enum SrcMode {
+    Reg,     // also covers the constant generator forms, which cost nothing extra
+    Indexed, // also covers absolute addressing
+    Indirect,
+    IndirectAutoInc,
+    Imm,
+}
+
+fn get_effective_src_mode(src_addr_mode: u16, src_reg_id: u16) -> SrcMode {
+    if src_reg_id == 3 || (src_reg_id == 2 && src_addr_mode >= 2) {
+        // constant generator
+        return SrcMode::Reg;
+    }
+    match src_addr_mode {
+        0 => SrcMode::Reg,
+        1 => SrcMode::Indexed,
+        2 => SrcMode::Indirect,
+        3 => {
+            if src_reg_id == 0 {
+                SrcMode::Imm
+            } else {
+                SrcMode::IndirectAutoInc
+            }
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::asm_line::AsmLine;
+use crate::debug_info::{DebugInfo, VarLocation, Variable};
+use crate::disassembler::{disassemble, disassemble_to_string};
+use crate::emulator::watchpoints::{WatchAction, WatchKind};
+use crate::emulator::Emulator;
+use crate::source_map::SourceMap;
+
+// An interactive debugger. Each command is a line of text, and the result is text to show the user.
+// Breakpoints and memory addresses can be given as numbers or as labels from the assembler.
+// Given the source map and debugging information of a C program, stops show the C line, C lines
+// (such as main.c:42) can be used as addresses, and variables can be shown by name.
+
+const HELP: &str = "commands:
+  break <label|addr|file:line>
+                          stop when the pc reaches addr
+  delete <label|addr>     remove a breakpoint
+  watch <label|addr> [len] [log]
+                          stop when len bytes (default 2) at addr are written,
+                          or just show the write if log is given
+  rwatch, awatch          like watch, but for reads, or for reads and writes
+  unwatch <id>            remove a watchpoint
+  step                    run one instruction
+  next                    run one instruction, running called functions to completion
+  finish                  run until the current function returns
+  cstep                   run until a different C line is reached
+  continue                run until a breakpoint, fault, or halt
+  record [steps|off]      keep a history of the last steps (default 1000000),
+                          so that the commands below can run backwards
+  reverse-step            undo one instruction
+  reverse-continue        run backwards until a breakpoint, watchpoint, or the start of the history
+  goto <index>            run forwards or backwards until index instructions have run
+  regs                    show the registers, and the number of instructions run
+  backtrace               show the calls that led to the current instruction
+  locals                  show the local variables of the current C function
+  print <variable>        show the value of a C variable
+  x/<n><w|b|i> <label|addr>
+                          show n words, bytes, or instructions starting at addr
+  set reg <reg> <value>   change a register, e.g. set reg r15 0x10";
+
+// stops a continue that would otherwise never return, such as a loop waiting for an interrupt
+const MAX_RUN_INSTRS: u64 = 100_000_000;
+
+const DEFAULT_HISTORY_LEN: usize = 1_000_000;
+
+// how far above SP to look for return addresses in backtraces
+const MAX_STACK_SCAN_BYTES: u16 = 256;
+
+const CALL_INSTR_MASK: u16 = 0xFF80;
+const CALL_INSTR: u16 = 0x1280;
+const RET_INSTR: u16 = 0x4130; // MOV @SP+,PC
+
+pub struct Debugger {
+    symbols: HashMap<String, u16>,
+    breakpoints: BTreeSet<u16>,
+    source_map: Option<SourceMap>,
+    debug_info: DebugInfo,
+}
+
+impl Debugger {
+    pub fn new(symbols: HashMap<String, u16>) -> Self {
+        Debugger {
+            symbols,
+            breakpoints: BTreeSet::new(),
+            source_map: None,
+            debug_info: DebugInfo::default(),
+        }
+    }
+
+    pub fn set_source(&mut self, source_map: SourceMap, debug_info: DebugInfo) {
+        self.source_map = Some(source_map);
+        self.debug_info = debug_info;
+    }
+
+    pub fn execute(&mut self, emulator: &mut Emulator, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            None => return String::new(),
+            Some(x) => x,
+        };
+
+        match *command {
+            "break" | "b" => match args {
+                [addr] => self.set_breakpoint(addr, true),
+                _ => "usage: break <label|addr>".to_owned(),
+            },
+            "delete" | "d" => match args {
+                [addr] => self.set_breakpoint(addr, false),
+                _ => "usage: delete <label|addr>".to_owned(),
+            },
+            "watch" => self.set_watchpoint(emulator, WatchKind::Write, args),
+            "rwatch" => self.set_watchpoint(emulator, WatchKind::Read, args),
+            "awatch" => self.set_watchpoint(emulator, WatchKind::Access, args),
+            "unwatch" => match args {
+                [id] => match id.parse::<usize>() {
+                    Ok(id) if emulator.remove_watchpoint(id) => {
+                        format!("deleted watchpoint {}", id)
+                    }
+                    _ => format!("no watchpoint {}", id),
+                },
+                _ => "usage: unwatch <id>".to_owned(),
+            },
+            "step" | "s" => self.run_until(emulator, |_, _| true),
+            "next" | "n" => self.next(emulator),
+            "finish" => self.finish(emulator),
+            "cstep" => self.cstep(emulator),
+            "continue" | "c" => self.run_until(emulator, |_, _| false),
+            "record" => self.record(emulator, args),
+            "reverse-step" | "rs" => self.run_backwards(emulator, 1),
+            "reverse-continue" | "rc" => self.run_backwards(emulator, u64::MAX),
+            "goto" => match args {
+                [index] => self.goto(emulator, index),
+                _ => "usage: goto <instruction index>".to_owned(),
+            },
+            "regs" => format_regs(emulator),
+            "backtrace" | "bt" => self.backtrace(emulator),
+            "locals" => self.show_locals(emulator),
+            "print" | "p" => match args {
+                [name] => self.print_variable(emulator, name),
+                _ => "usage: print <variable>".to_owned(),
+            },
+            "set" => match args {
+                ["reg", reg, value] => self.set_reg(emulator, reg, value),
+                _ => "usage: set reg <reg> <value>".to_owned(),
+            },
+            "help" => HELP.to_owned(),
+            _ if command.starts_with("x") => match args {
+                [addr] => self.examine(emulator, &command[1..], addr),
+                _ => "usage: x/<n><w|b|i> <label|addr>".to_owned(),
+            },
+            _ => format!("unknown command {}, try help", command),
+        }
+    }
+
+    fn set_breakpoint(&mut self, addr: &str, is_inserted: bool) -> String {
+        let addr = match self.parse_value(addr) {
+            Some(a) => a,
+            None => return format!("unknown address {}", addr),
+        };
+        if is_inserted {
+            self.breakpoints.insert(addr);
+            format!("breakpoint at {}", self.format_location(addr))
+        } else if self.breakpoints.remove(&addr) {
+            format!("deleted breakpoint at {}", self.format_location(addr))
+        } else {
+            format!("no breakpoint at {}", self.format_location(addr))
+        }
+    }
+
+    fn set_watchpoint(&self, emulator: &mut Emulator, kind: WatchKind, args: &[&str]) -> String {
+        let (args, action) = match args.split_last() {
+            Some((&"log", rest)) => (rest, WatchAction::Log),
+            _ => (args, WatchAction::Break),
+        };
+        let (addr, len) = match args {
+            [addr] => (addr, "2"),
+            [addr, len] => (addr, *len),
+            _ => return "usage: watch <label|addr> [len] [log]".to_owned(),
+        };
+        let start = match self.parse_value(addr) {
+            Some(a) => a,
+            None => return format!("unknown address {}", addr),
+        };
+        let end = match self.parse_value(len) {
+            Some(len) if len > 0 => start.overflowing_add(len - 1).0,
+            _ => return format!("invalid length {}", len),
+        };
+        if end < start {
+            return "watchpoints can't wrap around the end of memory".to_owned();
+        }
+        let id = emulator.add_watchpoint(start..=end, kind, action);
+        format!(
+            "watchpoint {}: {:04X}..{:04X} at {}",
+            id,
+            start,
+            end,
+            self.format_location(start)
+        )
+    }
+
+    fn next(&mut self, emulator: &mut Emulator) -> String {
+        let pc = emulator.regs[0];
+        if emulator.bus.peek_word(pc) & CALL_INSTR_MASK != CALL_INSTR {
+            return self.run_until(emulator, |_, _| true);
+        }
+        // run until the RET that pops the return address pushed by this call
+        let sp = emulator.regs[1];
+        let mut is_first_instr = true;
+        self.run_until(emulator, |emu, instr| {
+            if is_first_instr {
+                is_first_instr = false;
+                // an interrupt may have been taken instead of the call
+                return emu.regs[1] != sp.overflowing_sub(2).0;
+            }
+            instr == RET_INSTR && emu.regs[1] == sp
+        })
+    }
+
+    fn finish(&mut self, emulator: &mut Emulator) -> String {
+        // the function has returned once a RET pops the stack above where it is now
+        let sp = emulator.regs[1];
+        self.run_until(emulator, |emu, instr| {
+            instr == RET_INSTR && emu.regs[1] > sp
+        })
+    }
+
+    fn cstep(&mut self, emulator: &mut Emulator) -> String {
+        let source_map = match &self.source_map {
+            Some(s) if s.has_c_lines() => s,
+            _ => return "no C lines, compile with --symdebug:dwarf".to_owned(),
+        };
+        let c_line = source_map.get_c_line_at(emulator.regs[0]);
+        self.run_until(emulator, |emu, _| {
+            let new_c_line = source_map.get_c_line_at(emu.regs[0]);
+            new_c_line.is_some() && new_c_line != c_line
+        })
+    }
+
+    fn run_until(
+        &self,
+        emulator: &mut Emulator,
+        mut is_done: impl FnMut(&Emulator, u16) -> bool,
+    ) -> String {
+        // is_done is called after each instruction, with the instruction word that was run.
+        // breakpoints are only checked after the first instruction, so that it is possible
+        // to continue from one. watchpoint hits are shown before the reason for stopping.
+        let mut lines = Vec::new();
+        for _ in 0..MAX_RUN_INSTRS {
+            let instr = emulator.bus.peek_word(emulator.regs[0]);
+            let result = emulator.run_one_instr();
+            let is_watch_break = take_watch_hits(emulator, &mut lines);
+            if let Err(fault) = result {
+                return join_lines(lines, format!("CPU fault: {}", fault));
+            }
+            let pc = emulator.regs[0];
+            if is_done(emulator, instr) {
+                return join_lines(lines, self.format_instr(emulator, pc));
+            }
+            if is_watch_break {
+                return join_lines(
+                    lines,
+                    format!("stopped at {}", self.format_instr(emulator, pc)),
+                );
+            }
+            if self.breakpoints.contains(&pc) {
+                return join_lines(
+                    lines,
+                    format!("breakpoint at {}", self.format_instr(emulator, pc)),
+                );
+            }
+            if emulator.is_halted() {
+                return join_lines(
+                    lines,
+                    format!("halted at {}", self.format_instr(emulator, pc)),
+                );
+            }
+        }
+        let reason = format!(
+            "still running after {} instructions, stopped at {}",
+            MAX_RUN_INSTRS,
+            self.format_instr(emulator, emulator.regs[0])
+        );
+        join_lines(lines, reason)
+    }
+
+    fn run_backwards(&self, emulator: &mut Emulator, max_steps: u64) -> String {
+        // undoes up to max_steps steps, stopping early at breakpoints and watchpoints
+        // like run_until does going forwards
+        let mut lines = Vec::new();
+        for num_steps in 1..=max_steps {
+            if !emulator.step_back() {
+                if num_steps == 1 {
+                    return "no history to go back through, try record".to_owned();
+                }
+                let pc = emulator.regs[0];
+                return join_lines(
+                    lines,
+                    format!(
+                        "reached the start of the history at {}",
+                        self.format_instr(emulator, pc)
+                    ),
+                );
+            }
+            let is_watch_break = take_watch_hits(emulator, &mut lines);
+            let pc = emulator.regs[0];
+            if num_steps == max_steps {
+                return join_lines(lines, self.format_instr(emulator, pc));
+            }
+            if is_watch_break {
+                return join_lines(
+                    lines,
+                    format!("stopped at {}", self.format_instr(emulator, pc)),
+                );
+            }
+            if self.breakpoints.contains(&pc) {
+                return join_lines(
+                    lines,
+                    format!("breakpoint at {}", self.format_instr(emulator, pc)),
+                );
+            }
+        }
+        unreachable!()
+    }
+
+    fn record(&self, emulator: &mut Emulator, args: &[&str]) -> String {
+        let capacity = match args {
+            [] => DEFAULT_HISTORY_LEN,
+            ["off"] => 0,
+            [n] => match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return format!("invalid number of steps {}", n),
+            },
+            _ => return "usage: record [steps|off]".to_owned(),
+        };
+        emulator.set_journal_capacity(capacity);
+        match capacity {
+            0 => "stopped recording".to_owned(),
+            n => format!("recording the last {} steps", n),
+        }
+    }
+
+    fn goto(&self, emulator: &mut Emulator, index: &str) -> String {
+        let index = match index.parse::<u64>() {
+            Ok(i) => i,
+            Err(_) => return format!("invalid instruction index {}", index),
+        };
+        let curr_index = emulator.get_instr_index();
+        let result = if index >= curr_index {
+            self.run_until(emulator, |emu, _| emu.get_instr_index() >= index)
+        } else if emulator.rewind_to(index) {
+            emulator.take_watch_hits();
+            self.format_instr(emulator, emulator.regs[0])
+        } else {
+            let oldest = curr_index - emulator.get_journal_len() as u64;
+            return format!("the history only goes back to instruction {}", oldest);
+        };
+        format!("instruction {}: {}", emulator.get_instr_index(), result)
+    }
+
+    fn backtrace(&self, emulator: &Emulator) -> String {
+        let mut lines = vec![format!("#0 {}", self.format_location(emulator.regs[0]))];
+        for (i, call_addr) in get_call_stack(emulator).iter().enumerate() {
+            lines.push(format!("#{} {}", i + 1, self.format_location(*call_addr)));
+        }
+        lines.join("\n")
+    }
+
+    fn show_locals(&self, emulator: &Emulator) -> String {
+        let function = self
+            .find_function_label(emulator.regs[0])
+            .and_then(|(label, _)| self.debug_info.find_function(label));
+        match function {
+            Some(f) if !f.locals.is_empty() => f
+                .locals
+                .iter()
+                .map(|v| self.format_variable(emulator, v))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Some(_) => "no locals".to_owned(),
+            None => "no debugging information for this function".to_owned(),
+        }
+    }
+
+    fn print_variable(&self, emulator: &Emulator, name: &str) -> String {
+        let function_label = self
+            .find_function_label(emulator.regs[0])
+            .map(|(label, _)| label.as_str());
+        match self.debug_info.find_variable(function_label, name) {
+            Some(v) => self.format_variable(emulator, v),
+            None => format!("no variable {}", name),
+        }
+    }
+
+    fn format_variable(&self, emulator: &Emulator, variable: &Variable) -> String {
+        let value = match &variable.location {
+            VarLocation::Reg(reg) => Some(emulator.regs[*reg]),
+            _ => variable
+                .get_addr(&emulator.regs, &self.symbols)
+                .map(|addr| match variable.is_byte {
+                    true => emulator.bus.peek_byte(addr) as u16,
+                    false => emulator.bus.peek_word(addr),
+                }),
+        };
+        match (value, variable.is_byte) {
+            (Some(v), true) => format!("{} = {} (0x{:02X})", variable.name, v & 0xFF, v & 0xFF),
+            (Some(v), false) => format!("{} = {} (0x{:04X})", variable.name, v, v),
+            (None, _) => format!("{} = <unknown address>", variable.name),
+        }
+    }
+
+    fn set_reg(&mut self, emulator: &mut Emulator, reg: &str, value: &str) -> String {
+        let reg_id = match parse_reg_name(reg) {
+            Some(r) => r,
+            None => return format!("unknown register {}", reg),
+        };
+        match self.parse_value(value) {
+            Some(v) => {
+                emulator.regs[reg_id] = v;
+                format!("R{} = {:04X}", reg_id, v)
+            }
+            None => format!("invalid value {}", value),
+        }
+    }
+
+    fn examine(&self, emulator: &Emulator, format: &str, addr: &str) -> String {
+        // format is "/<count><unit>", where both parts are optional
+        let format = format.strip_prefix('/').unwrap_or(format);
+        let (count, unit) = match format.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&format[..i], c),
+            _ => (format, 'w'),
+        };
+        let count = match count {
+            "" => 1,
+            c => match c.parse::<u16>() {
+                Ok(c) => c,
+                Err(_) => return format!("invalid count {}", c),
+            },
+        };
+        let start = match self.parse_value(addr) {
+            Some(a) => a,
+            None => return format!("unknown address {}", addr),
+        };
+        let is_word = match unit {
+            'w' => true,
+            'b' => false,
+            'i' => return self.examine_instrs(emulator, count, start),
+            _ => return format!("unknown unit {}", unit),
+        };
+
+        // counted in usize, since a count of words can reach past the end of memory, where the
+        // addresses wrap around
+        let count = count as usize;
+        let unit_size: usize = if is_word { 2 } else { 1 };
+        let units_per_line: usize = if is_word { 8 } else { 16 };
+        let mut lines = Vec::new();
+        for line_start in (0..count).step_by(units_per_line) {
+            let line_addr = start.overflowing_add((line_start * unit_size) as u16).0;
+            let mut line = format!("{:04X}:", line_addr);
+            for i in 0..count.min(line_start + units_per_line) - line_start {
+                let addr = line_addr.overflowing_add((i * unit_size) as u16).0;
+                if is_word {
+                    line.push_str(&format!(" {:04X}", emulator.bus.peek_word(addr)));
+                } else {
+                    line.push_str(&format!(" {:02X}", emulator.bus.peek_byte(addr)));
+                }
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    fn examine_instrs(&self, emulator: &Emulator, count: u16, start: u16) -> String {
+        let mut addr = start;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            let (text, len) = disassemble_to_string(&emulator.bus, addr, &self.symbols);
+            lines.push(format!("{}: {}", self.format_location(addr), text));
+            addr = addr.overflowing_add(len).0;
+        }
+        lines.join("\n")
+    }
+
+    fn format_instr(&self, emulator: &Emulator, addr: u16) -> String {
+        let (text, _) = disassemble_to_string(&emulator.bus, addr, &self.symbols);
+        let c_location = self
+            .source_map
+            .as_ref()
+            .and_then(|s| s.format_c_location(addr));
+        match c_location {
+            Some(loc) => format!("{}: {}   at {}", self.format_location(addr), text, loc),
+            None => format!("{}: {}", self.format_location(addr), text),
+        }
+    }
+
+    fn parse_value(&self, s: &str) -> Option<u16> {
+        // a label, a C line, or a number in decimal or in hexadecimal with a 0x prefix
+        if let Some(addr) = self.symbols.get(s) {
+            return Some(*addr);
+        }
+        if let (Some(source_map), Some((file_name, c_line))) = (&self.source_map, s.split_once(':'))
+        {
+            let is_file_matched = source_map.get_c_file_name().is_none_or(|f| f == file_name);
+            return match (is_file_matched, c_line.parse::<usize>()) {
+                (true, Ok(c_line)) => source_map.find_c_line_addr(c_line),
+                _ => None,
+            };
+        }
+        if let Some(hex) = s.strip_prefix("0x") {
+            return u16::from_str_radix(hex, 16).ok();
+        }
+        match s.parse::<u16>() {
+            Ok(v) => Some(v),
+            Err(_) => s.parse::<i16>().ok().map(|v| v as u16),
+        }
+    }
+
+    pub fn format_location(&self, addr: u16) -> String {
+        // shows the closest label before addr
+        match self.find_function_label(addr) {
+            None => format!("0x{:04X}", addr),
+            Some((label, a)) if *a == addr => format!("0x{:04X} <{}>", addr, label),
+            Some((label, a)) => format!("0x{:04X} <{}+0x{:X}>", addr, label, addr - a),
+        }
+    }
+
+    fn find_function_label(&self, addr: u16) -> Option<(&String, &u16)> {
+        // the closest label at or before addr. compiler generated local labels
+        // (such as $C$L1) are skipped so that the function name is found instead.
+        self.symbols
+            .iter()
+            .filter(|(label, a)| !label.starts_with('$') && **a <= addr)
+            .max_by_key(|(label, a)| (**a, std::cmp::Reverse(label.as_str())))
+    }
+}
+
+fn parse_reg_name(name: &str) -> Option<usize> {
+    match name.to_ascii_lowercase().as_str() {
+        "pc" => Some(0),
+        "sp" => Some(1),
+        "sr" => Some(2),
+        "cg" => Some(3),
+        n => match n.strip_prefix('r')?.parse::<usize>() {
+            Ok(id) if id < 16 => Some(id),
+            _ => None,
+        },
+    }
+}
+
+pub fn get_call_stack(emulator: &Emulator) -> Vec<u16> {
+    // the addresses of the CALL instructions that led to the current function, innermost first.
+    // there are no frame pointers, so this looks for words on the stack that return to just
+    // after a CALL. data that happens to look like a return address will show up as well.
+    let no_symbols = HashMap::new();
+    let sp = emulator.regs[1] & !1;
+    let mut calls = Vec::new();
+    for offset in (0..MAX_STACK_SCAN_BYTES).step_by(2) {
+        let addr = match sp.checked_add(offset) {
+            Some(a) => a,
+            None => break,
+        };
+        // CALL pushes two less than the address that RET returns to
+        let return_addr = emulator.bus.peek_word(addr).overflowing_add(2).0;
+        for len in [2, 4] {
+            let call_addr = return_addr.overflowing_sub(len).0;
+            if let Some((AsmLine::CALL(..), l)) = disassemble(&emulator.bus, call_addr, &no_symbols)
+            {
+                if l == len {
+                    calls.push(call_addr);
+                    break;
+                }
+            }
+        }
+    }
+    calls
+}
+
+fn take_watch_hits(emulator: &mut Emulator, lines: &mut Vec<String>) -> bool {
+    // adds the watchpoint hits of the last step to lines, and returns whether any should stop it
+    let mut is_watch_break = false;
+    for hit in emulator.take_watch_hits() {
+        is_watch_break |= hit.action == WatchAction::Break;
+        lines.push(hit.to_string());
+    }
+    is_watch_break
+}
+
+fn join_lines(mut lines: Vec<String>, last_line: String) -> String {
+    lines.push(last_line);
+    lines.join("\n")
+}
+
+pub fn format_regs(emulator: &Emulator) -> String {
+    let regs = emulator.regs;
+    let mut lines: Vec<String> = (0..4)
+        .map(|row| {
+            (0..4)
+                .map(|col| format!("R{:<2} {:04X}", row * 4 + col, regs[row * 4 + col]))
+                .collect::<Vec<_>>()
+                .join("   ")
+        })
+        .collect();
+    let sr = regs[2];
+    lines.push(format!(
+        "flags: C={} Z={} N={} V={} GIE={}   cycles: {}   instrs: {}",
+        sr & 0x1,
+        (sr >> 1) & 0x1,
+        (sr >> 2) & 0x1,
+        (sr >> 8) & 0x1,
+        (sr >> 3) & 0x1,
+        emulator.get_cycle_count(),
+        emulator.get_instr_index()
+    ));
+    lines.join("\n")
+}
@@ -61,7 +61,11 @@ pub fn process_double_operand_w(
         }
         10 => {
             // DADD
-            unreachable!()
+            let has_carry;
+            (result, has_carry) = decimal_add(operand_1, operand_2, carry_bit, 4);
+            new_cf = Some(has_carry);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x8000 != 0);
         }
         11 | 15 => {
             // BIT or AND
@@ -93,3 +97,121 @@ pub fn process_double_operand_w(
 
     return (result, new_cf, new_zf, new_nf, new_vf);
 }
+
+pub fn process_double_operand_b(
+    operand_1: u8,
+    operand_2: u8,
+    carry_bit: bool,
+    opcode: u16,
+) -> (u16, Option<bool>, Option<bool>, Option<bool>, Option<bool>) {
+    let mut new_cf = None;
+    let mut new_zf = None;
+    let mut new_nf = None;
+    let mut new_vf = None;
+
+    let result: u8;
+    match opcode {
+        4 => {
+            // MOV.B
+            result = operand_1;
+        }
+        5 => {
+            // ADD.B
+            let has_carry;
+            let has_overflow;
+            (result, has_carry) = operand_1.overflowing_add(operand_2);
+            (_, has_overflow) = (operand_1 as i8).overflowing_add(operand_2 as i8);
+            new_cf = Some(has_carry);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some(has_overflow);
+        }
+        6 => {
+            // ADDC.B
+            let has_carry;
+            let has_overflow;
+            (result, has_carry) = operand_1.carrying_add(operand_2, carry_bit);
+            (_, has_overflow) = (operand_1 as i8).carrying_add(operand_2 as i8, carry_bit);
+            new_cf = Some(has_carry);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some(has_overflow);
+        }
+        7 => {
+            // SUBC.B
+            let has_carry;
+            let has_overflow;
+            (result, has_carry) = operand_2.borrowing_sub(operand_1, !carry_bit);
+            (_, has_overflow) = (operand_2 as i8).borrowing_sub(operand_1 as i8, !carry_bit);
+            new_cf = Some(!has_carry); // set to 1 if no borrow, reset if borrow
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some(has_overflow);
+        }
+        8 | 9 => {
+            // SUB.B or CMP.B
+            let has_carry;
+            let has_overflow;
+            (result, has_carry) = operand_2.overflowing_sub(operand_1);
+            (_, has_overflow) = (operand_2 as i8).overflowing_sub(operand_1 as i8);
+            new_cf = Some(!has_carry); // set to 1 if no borrow, reset if borrow
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some(has_overflow);
+        }
+        10 => {
+            // DADD.B
+            let (sum, has_carry) = decimal_add(operand_1 as u16, operand_2 as u16, carry_bit, 2);
+            result = sum as u8;
+            new_cf = Some(has_carry);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+        }
+        11 | 15 => {
+            // BIT.B or AND.B
+            result = operand_1 & operand_2;
+            new_cf = Some(result != 0);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some(false);
+        }
+        12 => {
+            // BIC.B
+            result = !operand_1 & operand_2;
+        }
+        13 => {
+            // BIS.B
+            result = operand_1 | operand_2;
+        }
+        14 => {
+            // XOR.B
+            result = operand_1 ^ operand_2;
+            new_cf = Some(result != 0);
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+            new_vf = Some((operand_1 & 0x80 != 0) && (operand_2 & 0x80 != 0));
+        }
+
+        _ => unreachable!(),
+    }
+
+    // byte results are zero-extended when written to a register
+    (result as u16, new_cf, new_zf, new_nf, new_vf)
+}
+
+fn decimal_add(operand_1: u16, operand_2: u16, carry_bit: bool, num_digits: u16) -> (u16, bool) {
+    // adds the operands as packed BCD, one 4 bit digit at a time, starting with the carry bit.
+    // the overflow flag is undefined for DADD, so it is left unchanged.
+    let mut result = 0;
+    let mut carry = carry_bit as u16;
+    for digit in 0..num_digits {
+        let shift = digit * 4;
+        let mut digit_sum = ((operand_1 >> shift) & 0xF) + ((operand_2 >> shift) & 0xF) + carry;
+        if digit_sum > 9 {
+            digit_sum += 6;
+        }
+        carry = (digit_sum >> 4) & 0x1;
+        result |= (digit_sum & 0xF) << shift;
+    }
+    (result, carry != 0)
+}
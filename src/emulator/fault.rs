@@ -0,0 +1,32 @@
+use std::fmt;
+
+// Errors that stop the cpu. The instruction that caused the fault is not retired,
+// so the registers and memory are left as they were before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuFault {
+    IllegalOpcode { pc: u16, instr: u16 },
+    InvalidDstAddrMode { pc: u16, instr: u16 }, // the result can't be written back to the operand
+    PcOverflow { pc: u16 },                     // the instruction runs past the end of memory
+    MisalignedFetch { pc: u16 },
+}
+
+impl fmt::Display for CpuFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuFault::IllegalOpcode { pc, instr } => {
+                write!(f, "illegal opcode {:04X} at {:04X}", instr, pc)
+            }
+            CpuFault::InvalidDstAddrMode { pc, instr } => write!(
+                f,
+                "invalid addressing mode for destination in {:04X} at {:04X}",
+                instr, pc
+            ),
+            CpuFault::PcOverflow { pc } => {
+                write!(f, "program counter overflowed past {:04X}", pc)
+            }
+            CpuFault::MisalignedFetch { pc } => {
+                write!(f, "misaligned instruction fetch at {:04X}", pc)
+            }
+        }
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::emulator::fault::CpuFault;
+use crate::emulator::watchpoints::{WatchAction, WatchKind};
+use crate::emulator::Emulator;
+
+// A server for the GDB remote serial protocol, so that programs can be debugged with
+// msp430-elf-gdb ("target remote :3333") or any other RSP client.
+// Only one client is served, and the emulator only runs while the client asks it to.
+
+// msp430-elf-gdb sends and expects 32 bit registers, since the MSP430X extends them to 20 bits.
+// the upper bits are always zero here.
+const REG_SIZE: usize = 4;
+const NUM_REGS: usize = 16;
+
+// how many instructions to run between checks for a break request from the client
+const INTERRUPT_POLL_INTERVAL: u32 = 1024;
+
+// signal numbers reported in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGBUS: u8 = 10;
+const SIGSEGV: u8 = 11;
+
+// sent by the client to stop a running program (Ctrl-C)
+const BREAK_BYTE: u8 = 0x03;
+
+pub trait GdbConnection: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl GdbConnection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+impl GdbConnection for UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+pub fn accept_tcp(addr: impl ToSocketAddrs) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(addr)?;
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+pub fn accept_unix(path: &str) -> io::Result<UnixStream> {
+    let listener = UnixListener::bind(path)?;
+    let (stream, _) = listener.accept()?;
+    Ok(stream)
+}
+
+pub struct GdbServer<C: GdbConnection> {
+    conn: C,
+    // software and hardware breakpoints behave the same way in the emulator
+    breakpoints: HashSet<u16>,
+    // the id of each watchpoint set by the client, by its "type,addr,kind" fields
+    watchpoints: HashMap<(WatchKind, u16, u32), usize>,
+    is_detached: bool,
+}
+
+impl<C: GdbConnection> GdbServer<C> {
+    pub fn new(conn: C) -> Self {
+        GdbServer {
+            conn,
+            breakpoints: HashSet::new(),
+            watchpoints: HashMap::new(),
+            is_detached: false,
+        }
+    }
+
+    pub fn into_connection(self) -> C {
+        self.conn
+    }
+
+    pub fn run(&mut self, emulator: &mut Emulator) -> io::Result<()> {
+        // serves the client until it detaches, kills the program, or disconnects
+        while !self.is_detached {
+            let packet = match self.read_packet()? {
+                None => break,
+                Some(p) => p,
+            };
+            let reply = self.handle_packet(emulator, &packet)?;
+            self.write_packet(&reply)?;
+        }
+        Ok(())
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        // returns None if the client disconnected
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                // acks, and break requests while the program is already stopped
+                Some(_) => continue,
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(b) => data.push(b),
+                }
+            }
+            let mut checksum = [0; 2];
+            self.conn.read_exact(&mut checksum)?;
+
+            let expected = u8::from_str_radix(&String::from_utf8_lossy(&checksum), 16);
+            if expected != Ok(get_checksum(&data)) {
+                self.conn.write_all(b"-")?;
+                continue;
+            }
+            self.conn.write_all(b"+")?;
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut buf = [0];
+        match self.conn.read(&mut buf)? {
+            0 => Ok(None),
+            _ => Ok(Some(buf[0])),
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        // the client's ack is skipped over by the next read_packet
+        let packet = format!("${}#{:02x}", data, get_checksum(data.as_bytes()));
+        self.conn.write_all(packet.as_bytes())?;
+        self.conn.flush()
+    }
+
+    fn has_break_request(&mut self) -> io::Result<bool> {
+        // the only thing the client may send while the program runs is a break request
+        self.conn.set_nonblocking(true)?;
+        let mut buf = [0];
+        let result = self.conn.read(&mut buf);
+        self.conn.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Ok(true), // disconnected, so stop and let read_packet notice
+            Ok(_) => Ok(buf[0] == BREAK_BYTE),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn handle_packet(&mut self, emulator: &mut Emulator, packet: &str) -> io::Result<String> {
+        // returns the reply. unsupported packets get an empty reply, as the protocol requires.
+        // the command is the first character, which need not be a single byte
+        let command_len = packet.chars().next().map_or(0, |c| c.len_utf8());
+        let (command, args) = packet.split_at(command_len);
+        let reply = match command {
+            "?" => stop_reply(SIGTRAP),
+            "g" => read_regs(emulator),
+            "G" => write_regs(emulator, args),
+            "p" => read_reg(emulator, args),
+            "P" => write_reg(emulator, args),
+            "m" => read_mem(emulator, args),
+            "M" => write_mem(emulator, args),
+            "s" => self.step(emulator, args),
+            "c" => self.resume(emulator, args)?,
+            "b" => match args {
+                // reverse step and continue, when the emulator's journal is on
+                "s" => self.run_backwards(emulator, 1)?,
+                "c" => self.run_backwards(emulator, u64::MAX)?,
+                _ => String::new(),
+            },
+            "Z" => self.set_breakpoint(emulator, args, true),
+            "z" => self.set_breakpoint(emulator, args, false),
+            "H" => "OK".to_owned(),
+            "D" => {
+                self.is_detached = true;
+                "OK".to_owned()
+            }
+            "k" => {
+                // there is no reply to a kill request
+                self.is_detached = true;
+                return Ok(String::new());
+            }
+            "q" => handle_query(args),
+            _ => String::new(),
+        };
+        Ok(reply)
+    }
+
+    fn step(&mut self, emulator: &mut Emulator, args: &str) -> String {
+        set_pc_from_args(emulator, args);
+        let result = emulator.run_one_instr();
+        if let Some(reply) = self.take_watch_reply(emulator) {
+            return reply;
+        }
+        match result {
+            Ok(_) => stop_reply(SIGTRAP),
+            Err(fault) => stop_reply(get_fault_signal(&fault)),
+        }
+    }
+
+    fn resume(&mut self, emulator: &mut Emulator, args: &str) -> io::Result<String> {
+        // runs until a breakpoint, a watchpoint, a fault, or a break request. a breakpoint at the
+        // current pc does not stop the program, otherwise it could never move past it.
+        set_pc_from_args(emulator, args);
+        let mut num_instrs = 0;
+        loop {
+            let result = emulator.run_one_instr();
+            if let Some(reply) = self.take_watch_reply(emulator) {
+                return Ok(reply);
+            }
+            if let Err(fault) = result {
+                return Ok(stop_reply(get_fault_signal(&fault)));
+            }
+            if self.breakpoints.contains(&emulator.regs[0]) {
+                return Ok(stop_reply(SIGTRAP));
+            }
+            if emulator.is_halted() {
+                // nothing can change the state anymore, so hand control back to the user
+                return Ok(stop_reply(SIGTRAP));
+            }
+            num_instrs += 1;
+            if num_instrs % INTERRUPT_POLL_INTERVAL == 0 && self.has_break_request()? {
+                return Ok(stop_reply(SIGINT));
+            }
+        }
+    }
+
+    fn run_backwards(&mut self, emulator: &mut Emulator, max_steps: u64) -> io::Result<String> {
+        // like resume, but undoing steps until the journal runs out
+        for num_steps in 1..=max_steps {
+            if !emulator.step_back() {
+                return Ok(format!("T{:02x}replaylog:begin;", SIGTRAP));
+            }
+            if let Some(reply) = self.take_watch_reply(emulator) {
+                return Ok(reply);
+            }
+            if num_steps == max_steps || self.breakpoints.contains(&emulator.regs[0]) {
+                break;
+            }
+            if num_steps % INTERRUPT_POLL_INTERVAL as u64 == 0 && self.has_break_request()? {
+                return Ok(stop_reply(SIGINT));
+            }
+        }
+        Ok(stop_reply(SIGTRAP))
+    }
+
+    fn take_watch_reply(&self, emulator: &mut Emulator) -> Option<String> {
+        // the stop reply for the first watchpoint hit by the last instruction, which tells
+        // the client which watchpoint it was and the address that was accessed
+        let hit = emulator
+            .take_watch_hits()
+            .into_iter()
+            .find(|hit| hit.action == WatchAction::Break)?;
+        let kind = self
+            .watchpoints
+            .iter()
+            .find(|(_, id)| **id == hit.id)
+            .map(|((kind, _, _), _)| *kind)?;
+        let name = match kind {
+            WatchKind::Write => "watch",
+            WatchKind::Read => "rwatch",
+            WatchKind::Access => "awatch",
+        };
+        Some(format!("T{:02x}{}:{:x};", SIGTRAP, name, hit.addr))
+    }
+
+    fn set_breakpoint(&mut self, emulator: &mut Emulator, args: &str, is_inserted: bool) -> String {
+        // "type,addr,kind". types 0 and 1 are software and hardware breakpoints, and
+        // types 2, 3, and 4 are write, read, and access watchpoints of kind bytes.
+        let mut fields = args.split(',');
+        let (bp_type, addr) = match (fields.next(), fields.next().and_then(parse_addr)) {
+            (Some(t), Some(addr)) => (t, addr),
+            _ => return "E01".to_owned(),
+        };
+        let watch_kind = match bp_type {
+            "0" | "1" => None,
+            "2" => Some(WatchKind::Write),
+            "3" => Some(WatchKind::Read),
+            "4" => Some(WatchKind::Access),
+            _ => return String::new(),
+        };
+        let kind = match watch_kind {
+            None => {
+                if is_inserted {
+                    self.breakpoints.insert(addr);
+                } else {
+                    self.breakpoints.remove(&addr);
+                }
+                return "OK".to_owned();
+            }
+            Some(kind) => kind,
+        };
+
+        let len = match fields.next().and_then(parse_hex) {
+            Some(len) if len > 0 && len <= 0x10000 - addr as u32 => len,
+            _ => return "E01".to_owned(),
+        };
+        let key = (kind, addr, len);
+        if is_inserted {
+            self.watchpoints.entry(key).or_insert_with(|| {
+                let range = addr..=(addr as u32 + len - 1) as u16;
+                emulator.add_watchpoint(range, kind, WatchAction::Break)
+            });
+        } else if let Some(id) = self.watchpoints.remove(&key) {
+            emulator.remove_watchpoint(id);
+        }
+        "OK".to_owned()
+    }
+}
+
+fn handle_query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        return "PacketSize=1000;ReverseStep+;ReverseContinue+".to_owned();
+    }
+    match args {
+        "Attached" => "1".to_owned(),
+        "C" => "QC1".to_owned(),
+        "fThreadInfo" => "m1".to_owned(),
+        "sThreadInfo" => "l".to_owned(),
+        _ => String::new(),
+    }
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+fn get_fault_signal(fault: &CpuFault) -> u8 {
+    match fault {
+        CpuFault::IllegalOpcode { .. } | CpuFault::InvalidDstAddrMode { .. } => SIGILL,
+        CpuFault::PcOverflow { .. } => SIGSEGV,
+        CpuFault::MisalignedFetch { .. } => SIGBUS,
+    }
+}
+
+fn set_pc_from_args(emulator: &mut Emulator, args: &str) {
+    // "s" and "c" may give an address to resume from
+    if let Some(addr) = parse_addr(args) {
+        emulator.regs[0] = addr;
+    }
+}
+
+fn read_regs(emulator: &Emulator) -> String {
+    emulator.regs.iter().map(|r| encode_reg(*r)).collect()
+}
+
+fn write_regs(emulator: &mut Emulator, args: &str) -> String {
+    if args.len() != NUM_REGS * REG_SIZE * 2 {
+        return "E01".to_owned();
+    }
+    for i in 0..NUM_REGS {
+        let start = i * REG_SIZE * 2;
+        match decode_reg(&args[start..start + REG_SIZE * 2]) {
+            Some(val) => emulator.regs[i] = val,
+            None => return "E01".to_owned(),
+        }
+    }
+    "OK".to_owned()
+}
+
+fn read_reg(emulator: &Emulator, args: &str) -> String {
+    match parse_hex(args) {
+        Some(id) if (id as usize) < NUM_REGS => encode_reg(emulator.regs[id as usize]),
+        _ => "E01".to_owned(),
+    }
+}
+
+fn write_reg(emulator: &mut Emulator, args: &str) -> String {
+    // "n=value"
+    let (id, val) = match args.split_once('=') {
+        Some((id, val)) => (parse_hex(id), decode_reg(val)),
+        None => return "E01".to_owned(),
+    };
+    match (id, val) {
+        (Some(id), Some(val)) if (id as usize) < NUM_REGS => {
+            emulator.regs[id as usize] = val;
+            "OK".to_owned()
+        }
+        _ => "E01".to_owned(),
+    }
+}
+
+fn read_mem(emulator: &Emulator, args: &str) -> String {
+    // "addr,length". reads go through peek so that they have no side effects on peripherals.
+    let (addr, len) = match parse_addr_len(args) {
+        Some(x) => x,
+        None => return "E01".to_owned(),
+    };
+    (0..len)
+        .map(|i| format!("{:02x}", emulator.bus.peek_byte(addr.overflowing_add(i).0)))
+        .collect()
+}
+
+fn write_mem(emulator: &mut Emulator, args: &str) -> String {
+    // "addr,length:XX..."
+    let (addr_len, data) = match args.split_once(':') {
+        Some(x) => x,
+        None => return "E01".to_owned(),
+    };
+    let (addr, len) = match parse_addr_len(addr_len) {
+        Some(x) => x,
+        None => return "E01".to_owned(),
+    };
+    let bytes = match decode_hex_bytes(data) {
+        Some(b) if b.len() == len as usize => b,
+        _ => return "E01".to_owned(),
+    };
+    for (i, byte) in bytes.into_iter().enumerate() {
+        emulator
+            .bus
+            .write_byte(addr.overflowing_add(i as u16).0, byte);
+    }
+    "OK".to_owned()
+}
+
+fn parse_addr_len(args: &str) -> Option<(u16, u16)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((parse_addr(addr)?, parse_addr(len)?))
+}
+
+fn parse_addr(s: &str) -> Option<u16> {
+    // None if it is too big for the 16 bit address space
+    u16::try_from(parse_hex(s)?).ok()
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn decode_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_reg(val: u16) -> String {
+    // registers are sent in target byte order, which is little endian
+    let mut bytes = val.to_le_bytes().to_vec();
+    bytes.resize(REG_SIZE, 0);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_reg(s: &str) -> Option<u16> {
+    let bytes = decode_hex_bytes(s)?;
+    if bytes.is_empty() || bytes.len() > REG_SIZE {
+        return None;
+    }
+    Some(u16::from_le_bytes([bytes[0], *bytes.get(1).unwrap_or(&0)]))
+}
+
+fn get_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, b| sum.overflowing_add(*b).0)
+}
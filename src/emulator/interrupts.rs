@@ -0,0 +1,46 @@
+use crate::emulator::bus::Bus;
+
+// The interrupt vector table occupies the top 32 bytes of memory. Vector n lives at
+// VECTOR_TABLE_ADDR + 2 * n, and a higher vector number means a higher priority.
+// Vector 15 is the reset vector, which is not maskable and is never requested here.
+//
+// Like the targets of CALL, a vector holds the address of the handler minus two,
+// which is the form that the assembler's label map uses for instruction labels.
+pub const VECTOR_TABLE_ADDR: u16 = 0xFFE0;
+pub const NUM_VECTORS: u16 = 16;
+pub const RESET_VECTOR: u16 = 15;
+
+pub const BTNS_VECTOR: u16 = 2; // 0xFFE4
+pub const TIMER_VECTOR: u16 = 9; // 0xFFF2
+
+pub const GIE_BIT: u16 = 0x0008;
+pub const CPUOFF_BIT: u16 = 0x0010;
+
+pub fn get_vector_addr(vector: u16) -> u16 {
+    VECTOR_TABLE_ADDR + vector * 2
+}
+
+pub fn get_highest_pending_interrupt(pending_interrupts: u16) -> Option<u16> {
+    let maskable = pending_interrupts & !(1 << RESET_VECTOR);
+    if maskable == 0 {
+        return None;
+    }
+    Some(15 - maskable.leading_zeros() as u16)
+}
+
+pub fn exec_interrupt_entry(mut regs: [u16; 16], vector: u16, bus: &mut Bus) -> [u16; 16] {
+    // hardware push of PC and SR. regs[0] already holds the address of the next instruction,
+    // so RETI restores it unchanged.
+    regs[1] = regs[1].overflowing_sub(2).0;
+    bus.write_word(regs[1], regs[0]);
+    regs[1] = regs[1].overflowing_sub(2).0;
+    bus.write_word(regs[1], regs[2]);
+
+    // nested interrupts are disabled until the handler returns or sets GIE itself
+    regs[2] &= !GIE_BIT;
+
+    let handler = bus.read_word(get_vector_addr(vector));
+    regs[0] = handler.overflowing_add(2).0;
+
+    regs
+}
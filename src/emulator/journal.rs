@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+// An undo journal for reverse execution. Each step of the emulator (an instruction or an
+// interrupt entry) gets one entry, holding what is needed to put the machine back the way it was
+// before the step: the registers from before the stage 5a update,
+// the old value of every location written in stage 5b or by an interrupt entry, and the state of
+// devices that change on their own as cycles pass.
+// Changes made from outside of the cpu (by a debugger, or by inputs such as the switches)
+// are not journaled, so stepping back over them does not undo them.
+
+pub struct JournalEntry {
+    pub regs: [u16; 16],
+    pub cycle_count: u64,
+    pub pending_interrupts: u16,
+    // address, whether it was a byte access, and the old value, in the order of the writes
+    pub mem_writes: Vec<(u16, bool, u16)>,
+    // address, whether it was a byte access, and the value read. used to check read watchpoints.
+    pub mem_reads: Vec<(u16, bool, u16)>,
+    // peripheral index and saved state
+    pub device_states: Vec<(usize, Vec<u8>)>,
+}
+
+#[derive(Default)]
+pub struct Journal {
+    capacity: usize, // the number of steps that can be undone, or 0 if journaling is off
+    entries: VecDeque<JournalEntry>,
+    // the entry for the step being run, moved into entries once it is retired
+    curr_entry: Option<JournalEntry>,
+}
+
+impl Journal {
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        // the oldest entries are dropped if the journal is already longer than capacity
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.curr_entry = None;
+    }
+
+    pub fn begin_step(&mut self, entry: JournalEntry) {
+        self.curr_entry = Some(entry);
+    }
+
+    pub fn record_write(&mut self, addr: u16, is_byte: bool, old_value: u16) {
+        if let Some(entry) = &mut self.curr_entry {
+            entry.mem_writes.push((addr, is_byte, old_value));
+        }
+    }
+
+    pub fn record_read(&mut self, addr: u16, is_byte: bool, value: u16) {
+        if let Some(entry) = &mut self.curr_entry {
+            entry.mem_reads.push((addr, is_byte, value));
+        }
+    }
+
+    pub fn end_step(&mut self) {
+        let entry = match self.curr_entry.take() {
+            Some(e) => e,
+            None => return,
+        };
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn abandon_step(&mut self) {
+        // a step that faulted is not retired, so there is nothing to undo later
+        self.curr_entry = None;
+    }
+
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        self.entries.pop_back()
+    }
+}
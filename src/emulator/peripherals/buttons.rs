@@ -0,0 +1,88 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, interrupts::BTNS_VECTOR, snapshot::invalid_snapshot};
+
+pub const BTNS_ADDR: u16 = 0x8A02; // read as a word, with the buttons in the low byte
+pub const BTNS_IE_ADDR: u16 = 0x8A06; // 1 bit per button, enables its interrupt
+pub const BTNS_IFG_ADDR: u16 = 0x8A07; // 1 bit per button, set when it is pressed
+
+pub struct Buttons {
+    states: u8,
+    interrupt_enable: u8,
+    interrupt_flags: u8,
+}
+
+impl Buttons {
+    pub fn new() -> Self {
+        Buttons {
+            states: 0,
+            interrupt_enable: 0,
+            interrupt_flags: 0,
+        }
+    }
+
+    pub fn set_states(&mut self, new_states: u8) {
+        let pressed = new_states & !self.states;
+        self.interrupt_flags |= pressed;
+        self.states = new_states;
+    }
+}
+
+impl Default for Buttons {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Peripheral for Buttons {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![BTNS_ADDR..=BTNS_ADDR + 1, BTNS_IE_ADDR..=BTNS_IFG_ADDR]
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        match addr {
+            BTNS_ADDR => self.states,
+            BTNS_IE_ADDR => self.interrupt_enable,
+            BTNS_IFG_ADDR => self.interrupt_flags,
+            _ => 0,
+        }
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        // the button states themselves are read only
+        match addr {
+            BTNS_IE_ADDR => self.interrupt_enable = value,
+            BTNS_IFG_ADDR => self.interrupt_flags = value,
+            _ => {}
+        }
+    }
+
+    fn get_pending_interrupts(&self) -> u16 {
+        // the interrupt stays pending for as long as the flag is set,
+        // so the handler is responsible for clearing it.
+        if self.interrupt_flags & self.interrupt_enable != 0 {
+            1 << BTNS_VECTOR
+        } else {
+            0
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        vec![self.states, self.interrupt_enable, self.interrupt_flags]
+    }
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        [self.states, self.interrupt_enable, self.interrupt_flags] = state
+            .try_into()
+            .map_err(|_| invalid_snapshot("invalid button state"))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,61 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, snapshot::invalid_snapshot};
+
+pub const LED_ADDR: u16 = 0x8A04;
+
+pub struct Leds {
+    states: u16,
+}
+
+impl Leds {
+    pub fn new() -> Self {
+        Leds { states: 0 }
+    }
+
+    pub fn get_states(&self) -> u16 {
+        self.states
+    }
+}
+
+impl Default for Leds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Peripheral for Leds {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![LED_ADDR..=LED_ADDR + 1]
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        self.states.to_le_bytes()[(addr - LED_ADDR) as usize]
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        let mut bytes = self.states.to_le_bytes();
+        bytes[(addr - LED_ADDR) as usize] = value;
+        self.states = u16::from_le_bytes(bytes);
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.states.to_le_bytes().to_vec()
+    }
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        let state: [u8; 2] = state
+            .try_into()
+            .map_err(|_| invalid_snapshot("invalid led state"))?;
+        self.states = u16::from_le_bytes(state);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,5 @@
+pub mod buttons;
+pub mod leds;
+pub mod switches;
+pub mod timer;
+pub mod vga;
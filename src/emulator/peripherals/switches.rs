@@ -0,0 +1,63 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, snapshot::invalid_snapshot};
+
+pub const SWITCHES_ADDR: u16 = 0x8A00;
+
+pub struct Switches {
+    states: u16,
+}
+
+impl Switches {
+    pub fn new() -> Self {
+        Switches { states: 0 }
+    }
+
+    pub fn get_states(&self) -> u16 {
+        self.states
+    }
+
+    pub fn set_states(&mut self, new_states: u16) {
+        self.states = new_states;
+    }
+}
+
+impl Default for Switches {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Peripheral for Switches {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![SWITCHES_ADDR..=SWITCHES_ADDR + 1]
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        self.states.to_le_bytes()[(addr - SWITCHES_ADDR) as usize]
+    }
+
+    fn write_byte(&mut self, _addr: u16, _value: u8) {
+        // the switches are read only
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.states.to_le_bytes().to_vec()
+    }
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        let state: [u8; 2] = state
+            .try_into()
+            .map_err(|_| invalid_snapshot("invalid switch state"))?;
+        self.states = u16::from_le_bytes(state);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,116 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, interrupts::TIMER_VECTOR, snapshot::invalid_snapshot};
+
+pub const TIMER_CTL_ADDR: u16 = 0x8A08;
+pub const TIMER_PERIOD_ADDR: u16 = 0x8A0A;
+pub const TIMER_COUNT_ADDR: u16 = 0x8A0C;
+
+// the high bytes of the word registers
+const TIMER_PERIOD_HIGH_ADDR: u16 = TIMER_PERIOD_ADDR + 1;
+const TIMER_COUNT_HIGH_ADDR: u16 = TIMER_COUNT_ADDR + 1;
+
+pub const TIMER_CTL_ENABLE: u8 = 0x1;
+pub const TIMER_CTL_IE: u8 = 0x2;
+pub const TIMER_CTL_IFG: u8 = 0x4;
+
+pub struct Timer {
+    ctl: u8,
+    period: u16,
+    count: u16,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            ctl: 0,
+            period: 0,
+            count: 0,
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Peripheral for Timer {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![TIMER_CTL_ADDR..=TIMER_COUNT_HIGH_ADDR]
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        match addr {
+            TIMER_CTL_ADDR => self.ctl,
+            TIMER_PERIOD_ADDR => self.period.to_le_bytes()[0],
+            TIMER_PERIOD_HIGH_ADDR => self.period.to_le_bytes()[1],
+            TIMER_COUNT_ADDR => self.count.to_le_bytes()[0],
+            TIMER_COUNT_HIGH_ADDR => self.count.to_le_bytes()[1],
+            _ => 0,
+        }
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        match addr {
+            TIMER_CTL_ADDR => self.ctl = value,
+            TIMER_PERIOD_ADDR => self.period = (self.period & 0xFF00) | value as u16,
+            TIMER_PERIOD_HIGH_ADDR => self.period = (self.period & 0x00FF) | (value as u16) << 8,
+            TIMER_COUNT_ADDR => self.count = (self.count & 0xFF00) | value as u16,
+            TIMER_COUNT_HIGH_ADDR => self.count = (self.count & 0x00FF) | (value as u16) << 8,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, num_cycles: u8) {
+        // the timer counts cycles, and sets its flag every time the count reaches the period
+        if self.ctl & TIMER_CTL_ENABLE == 0 {
+            return;
+        }
+        for _ in 0..num_cycles {
+            self.count = self.count.overflowing_add(1).0;
+            if self.count >= self.period {
+                self.count = 0;
+                self.ctl |= TIMER_CTL_IFG;
+            }
+        }
+    }
+
+    fn has_tick_state(&self) -> bool {
+        true
+    }
+
+    fn get_pending_interrupts(&self) -> u16 {
+        if self.ctl & TIMER_CTL_IE != 0 && self.ctl & TIMER_CTL_IFG != 0 {
+            1 << TIMER_VECTOR
+        } else {
+            0
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = vec![self.ctl];
+        state.extend(self.period.to_le_bytes());
+        state.extend(self.count.to_le_bytes());
+        state
+    }
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        if state.len() != 5 {
+            return Err(invalid_snapshot("invalid timer state"));
+        }
+        self.ctl = state[0];
+        self.period = u16::from_le_bytes([state[1], state[2]]);
+        self.count = u16::from_le_bytes([state[3], state[4]]);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,63 @@
+use std::any::Any;
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, snapshot::invalid_snapshot};
+
+// 160x120 monochrome framebuffer, 1 bit per pixel with the leftmost pixel in the lowest bit
+pub const VGA_BEGIN_ADDR: u16 = 0x8000;
+pub const VGA_END_ADDR: u16 = 0x895F;
+
+pub struct Vga {
+    buffer: Vec<u8>,
+}
+
+impl Vga {
+    pub fn new() -> Self {
+        Vga {
+            buffer: vec![0; (VGA_END_ADDR - VGA_BEGIN_ADDR) as usize + 1],
+        }
+    }
+
+    pub fn get_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl Default for Vga {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Peripheral for Vga {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![VGA_BEGIN_ADDR..=VGA_END_ADDR]
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        self.buffer[(addr - VGA_BEGIN_ADDR) as usize]
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        self.buffer[(addr - VGA_BEGIN_ADDR) as usize] = value;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.buffer.clone()
+    }
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        if state.len() != self.buffer.len() {
+            return Err(invalid_snapshot("invalid vga state"));
+        }
+        self.buffer.copy_from_slice(state);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+// Attributes retired instructions and cycles to functions, using the assembler's labels.
+// A shadow call stack follows CALLs and interrupt entries, and a frame is popped once the
+// stack pointer moves above its return address, which covers both RET and RETI.
+// The results can be written as a flat profile, or as folded stacks ("main;draw_board 1234")
+// for flamegraph tools such as inferno or flamegraph.pl.
+
+const CALL_INSTR_MASK: u16 = 0xFF80;
+const CALL_INSTR: u16 = 0x1280;
+
+#[derive(Default, Clone, Copy)]
+struct Counts {
+    instrs: u64,
+    cycles: u64,
+}
+
+pub struct Profiler {
+    symbols: Vec<(u16, String)>, // code labels sorted by address, without compiler generated ones
+    function_names: Vec<String>,
+    name_ids: HashMap<String, usize>,
+    function_ids: HashMap<u16, usize>, // by address
+    // the call stack, as function ids and the addresses of their return addresses.
+    // the outermost function was never called, so it has no return address.
+    stack_ids: Vec<usize>,
+    return_slots: Vec<Option<u16>>,
+    stack_counts: HashMap<Vec<usize>, Counts>,
+    num_calls: HashMap<usize, u64>,
+}
+
+impl Profiler {
+    pub fn new(symbols: &HashMap<String, u16>) -> Self {
+        let mut symbols: Vec<(u16, String)> = symbols
+            .iter()
+            .filter(|(label, _)| !label.starts_with('$'))
+            .map(|(label, addr)| (*addr, label.clone()))
+            .collect();
+        // if several labels share an address, the first one alphabetically is found first
+        symbols.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        Profiler {
+            symbols,
+            function_names: Vec::new(),
+            name_ids: HashMap::new(),
+            function_ids: HashMap::new(),
+            stack_ids: Vec::new(),
+            return_slots: Vec::new(),
+            stack_counts: HashMap::new(),
+            num_calls: HashMap::new(),
+        }
+    }
+
+    pub fn record_instr(&mut self, pc: u16, instr: u16, regs_after: &[u16; 16], num_cycles: u8) {
+        // the instruction is charged to the function it ran in, so a CALL counts towards the
+        // caller and a RET towards the callee
+        self.ensure_outer_frame(pc);
+        self.add_counts(1, num_cycles);
+        if instr & CALL_INSTR_MASK == CALL_INSTR {
+            self.push_frame(regs_after);
+        } else {
+            self.pop_returned_frames(regs_after[1]);
+        }
+    }
+
+    pub fn record_interrupt(&mut self, pc: u16, regs_after: &[u16; 16], num_cycles: u8) {
+        // the cost of entering the interrupt is charged to the handler
+        self.ensure_outer_frame(pc);
+        self.push_frame(regs_after);
+        self.add_counts(0, num_cycles);
+    }
+
+    pub fn write_flat_profile(&self, out: &mut dyn Write) -> io::Result<()> {
+        // one line per function, sorted by the cycles spent in the function itself
+        let mut self_counts: HashMap<usize, Counts> = HashMap::new();
+        let mut total_cycles: HashMap<usize, u64> = HashMap::new();
+        let mut all_cycles = 0;
+        for (stack, counts) in &self.stack_counts {
+            let leaf = self_counts.entry(*stack.last().unwrap()).or_default();
+            leaf.instrs += counts.instrs;
+            leaf.cycles += counts.cycles;
+            // a recursive function is only counted once per stack
+            let functions: HashSet<&usize> = stack.iter().collect();
+            for id in functions {
+                *total_cycles.entry(*id).or_default() += counts.cycles;
+            }
+            all_cycles += counts.cycles;
+        }
+
+        let mut ids: Vec<usize> = total_cycles.keys().copied().collect();
+        ids.sort_by_key(|id| {
+            let counts = self_counts.get(id).copied().unwrap_or_default();
+            (
+                std::cmp::Reverse(counts.cycles),
+                self.function_names[*id].clone(),
+            )
+        });
+        let percent = |cycles: u64| match all_cycles {
+            0 => 0.0,
+            _ => cycles as f64 * 100.0 / all_cycles as f64,
+        };
+
+        writeln!(
+            out,
+            "{:<24} {:>8} {:>10} {:>12} {:>7} {:>12} {:>7}",
+            "function", "calls", "instrs", "self cycles", "self", "total cycles", "total"
+        )?;
+        for id in ids {
+            let counts = self_counts.get(&id).copied().unwrap_or_default();
+            writeln!(
+                out,
+                "{:<24} {:>8} {:>10} {:>12} {:>6.2}% {:>12} {:>6.2}%",
+                self.function_names[id],
+                self.num_calls.get(&id).copied().unwrap_or(0),
+                counts.instrs,
+                counts.cycles,
+                percent(counts.cycles),
+                total_cycles[&id],
+                percent(total_cycles[&id])
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_folded_stacks(&self, out: &mut dyn Write) -> io::Result<()> {
+        // one line per distinct call stack, outermost function first, weighted by cycles
+        let mut lines: Vec<String> = self
+            .stack_counts
+            .iter()
+            .filter(|(_, counts)| counts.cycles > 0)
+            .map(|(stack, counts)| {
+                let names: Vec<&str> = stack
+                    .iter()
+                    .map(|id| self.function_names[*id].as_str())
+                    .collect();
+                format!("{} {}", names.join(";"), counts.cycles)
+            })
+            .collect();
+        lines.sort();
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    fn ensure_outer_frame(&mut self, pc: u16) {
+        if self.stack_ids.is_empty() {
+            let function_id = self.get_function_id(pc);
+            self.stack_ids.push(function_id);
+            self.return_slots.push(None);
+        }
+    }
+
+    fn push_frame(&mut self, regs_after: &[u16; 16]) {
+        // called once the return address has been pushed and the pc is at the new function
+        let function_id = self.get_function_id(regs_after[0]);
+        self.stack_ids.push(function_id);
+        self.return_slots.push(Some(regs_after[1]));
+        *self.num_calls.entry(function_id).or_default() += 1;
+    }
+
+    fn pop_returned_frames(&mut self, sp: u16) {
+        while let Some(Some(slot)) = self.return_slots.last() {
+            if *slot >= sp {
+                break;
+            }
+            self.stack_ids.pop();
+            self.return_slots.pop();
+        }
+    }
+
+    fn add_counts(&mut self, instrs: u64, cycles: u8) {
+        let counts = match self.stack_counts.get_mut(self.stack_ids.as_slice()) {
+            Some(c) => c,
+            None => self.stack_counts.entry(self.stack_ids.clone()).or_default(),
+        };
+        counts.instrs += instrs;
+        counts.cycles += cycles as u64;
+    }
+
+    fn get_function_id(&mut self, addr: u16) -> usize {
+        // functions are named by the closest label at or before their address
+        if let Some(id) = self.function_ids.get(&addr) {
+            return *id;
+        }
+        let closest = self.symbols.iter().rev().find(|(a, _)| *a <= addr);
+        let name = match closest {
+            Some((_, label)) => label.clone(),
+            None => format!("0x{:04X}", addr),
+        };
+        let id = self.get_function_id_by_name(&name);
+        self.function_ids.insert(addr, id);
+        id
+    }
+
+    fn get_function_id_by_name(&mut self, name: &str) -> usize {
+        if let Some(id) = self.name_ids.get(name) {
+            return *id;
+        }
+        let id = self.function_names.len();
+        self.function_names.push(name.to_owned());
+        self.name_ids.insert(name.to_owned(), id);
+        id
+    }
+}
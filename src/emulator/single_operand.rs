@@ -1,9 +1,6 @@
-pub fn process_single_operand_w(
-    operand_1: u16,
-    carry_bit: bool,
-    opcode: u16,
-    regs: &[u16],
-) -> (
+// the result, the new carry, zero, negative and overflow flags (None if unchanged), whether SP is
+// decremented, the new PC, and whether the jump to the new PC is taken
+pub type SingleOperandResult = (
     u16,
     Option<bool>,
     Option<bool>,
@@ -11,7 +8,16 @@ pub fn process_single_operand_w(
     Option<bool>,
     bool,
     u16,
-) {
+    bool,
+);
+
+pub fn process_single_operand_w(
+    operand_1: u16,
+    operand_2: u16,
+    carry_bit: bool,
+    opcode: u16,
+    regs: &[u16],
+) -> SingleOperandResult {
     let mut dec_sp = false;
 
     let mut new_cf = None;
@@ -20,13 +26,14 @@ pub fn process_single_operand_w(
     let mut new_vf = None;
 
     let mut new_pc_val = 0;
+    let mut jump_taken = false;
 
     let result;
 
     match opcode {
         0 => {
             // RRC
-            new_cf = Some(operand_1 % 2 != 0);
+            new_cf = Some(!operand_1.is_multiple_of(2));
             new_vf = Some(false);
             new_nf = Some(carry_bit);
             let mut rotated_right = operand_1 >> 1;
@@ -42,7 +49,7 @@ pub fn process_single_operand_w(
         }
         2 => {
             // RRA
-            new_cf = Some(operand_1 % 2 != 0);
+            new_cf = Some(!operand_1.is_multiple_of(2));
             new_vf = Some(false);
 
             let rotated_right: i16 = (operand_1 as i16) >> 1;
@@ -71,12 +78,81 @@ pub fn process_single_operand_w(
         5 => {
             // CALL
             dec_sp = true;
-            result = regs[0] + 2;
+            result = regs[0].overflowing_add(2).0;
 
             new_pc_val = operand_1;
+            jump_taken = true;
+        }
+        6 => {
+            // RETI
+            // operand_1 is the saved SR and operand_2 is the saved PC. A taken jump
+            // lands two bytes after new_pc_val, so we subtract two to return exactly to the saved PC.
+            result = operand_1;
+            new_pc_val = operand_2.overflowing_sub(2).0;
+            jump_taken = true;
+        }
+        _ => unreachable!(),
+    }
+
+    return (
+        result, new_cf, new_zf, new_nf, new_vf, dec_sp, new_pc_val, jump_taken,
+    );
+}
+
+pub fn process_single_operand_b(
+    operand_1: u8,
+    carry_bit: bool,
+    opcode: u16,
+) -> SingleOperandResult {
+    // SWPB, SXT and CALL have no byte form, so only RRC.B, RRA.B and PUSH.B are handled here.
+    let mut dec_sp = false;
+
+    let mut new_cf = None;
+    let mut new_zf = None;
+    let mut new_nf = None;
+    let mut new_vf = None;
+
+    let result: u8;
+
+    match opcode {
+        0 => {
+            // RRC.B
+            new_cf = Some(!operand_1.is_multiple_of(2));
+            new_vf = Some(false);
+            new_nf = Some(carry_bit);
+            let mut rotated_right = operand_1 >> 1;
+            if carry_bit {
+                rotated_right |= 0x80;
+            }
+            result = rotated_right;
+            new_zf = Some(result == 0);
+        }
+        2 => {
+            // RRA.B
+            new_cf = Some(!operand_1.is_multiple_of(2));
+            new_vf = Some(false);
+
+            let rotated_right: i8 = (operand_1 as i8) >> 1;
+            result = rotated_right as u8;
+            new_zf = Some(result == 0);
+            new_nf = Some(result & 0x80 != 0);
+        }
+        4 => {
+            // PUSH.B
+            dec_sp = true;
+            result = operand_1;
         }
         _ => unreachable!(),
     }
 
-    return (result, new_cf, new_zf, new_nf, new_vf, dec_sp, new_pc_val);
+    (
+        result as u16,
+        new_cf,
+        new_zf,
+        new_nf,
+        new_vf,
+        dec_sp,
+        0,
+        false,
+    )
 }
@@ -0,0 +1,248 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use crate::emulator::Emulator;
+
+// Snapshot file layout (all integers little endian):
+//   magic, version
+//   registers, inter-stage latches, pending interrupts, cycle counters, instruction index
+//   ram (65536 bytes)
+//   number of peripherals, then for each one (in registration order) its state, prefixed by its length
+const SNAPSHOT_MAGIC: &[u8; 8] = b"MSPSNAP\0";
+const SNAPSHOT_VERSION: u16 = 2;
+
+pub struct SnapshotWriter {
+    bytes: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn new() -> Self {
+        SnapshotWriter { bytes: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+    pub fn put_u8(&mut self, val: u8) {
+        self.bytes.push(val);
+    }
+    pub fn put_u16(&mut self, val: u16) {
+        self.put_bytes(&val.to_le_bytes());
+    }
+    pub fn put_u32(&mut self, val: u32) {
+        self.put_bytes(&val.to_le_bytes());
+    }
+    pub fn put_u64(&mut self, val: u64) {
+        self.put_bytes(&val.to_le_bytes());
+    }
+    pub fn put_bool(&mut self, val: bool) {
+        self.put_u8(val as u8);
+    }
+    pub fn put_opt_bool(&mut self, val: Option<bool>) {
+        // 2 bit quantity, like the new_*f latches in hardware
+        match val {
+            None => self.put_u8(0),
+            Some(false) => self.put_u8(1),
+            Some(true) => self.put_u8(2),
+        }
+    }
+}
+
+impl Default for SnapshotWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SnapshotReader { bytes, index: 0 }
+    }
+
+    pub fn get_bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.index + len > self.bytes.len() {
+            return Err(invalid_snapshot("snapshot is truncated"));
+        }
+        let bytes = &self.bytes[self.index..self.index + len];
+        self.index += len;
+        Ok(bytes)
+    }
+    pub fn get_u8(&mut self) -> io::Result<u8> {
+        Ok(self.get_bytes(1)?[0])
+    }
+    pub fn get_u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.get_bytes(2)?.try_into().unwrap()))
+    }
+    pub fn get_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.get_bytes(4)?.try_into().unwrap()))
+    }
+    pub fn get_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.get_bytes(8)?.try_into().unwrap()))
+    }
+    pub fn get_bool(&mut self) -> io::Result<bool> {
+        match self.get_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_snapshot("invalid boolean")),
+        }
+    }
+    pub fn get_opt_bool(&mut self) -> io::Result<Option<bool>> {
+        match self.get_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(false)),
+            2 => Ok(Some(true)),
+            _ => Err(invalid_snapshot("invalid flag update")),
+        }
+    }
+}
+
+pub fn invalid_snapshot(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+impl Emulator {
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut w = SnapshotWriter::new();
+        w.put_bytes(SNAPSHOT_MAGIC);
+        w.put_u16(SNAPSHOT_VERSION);
+
+        for r in self.regs {
+            w.put_u16(r);
+        }
+
+        w.put_u16(self.curr_instr);
+        w.put_u16(self.next_word);
+        w.put_u16(self.next_next_word);
+        w.put_u16(self.opcode);
+        w.put_u16(self.src_reg_id);
+        w.put_u16(self.src_addr_mode);
+        w.put_u16(self.dst_reg_id);
+        w.put_u16(self.dst_addr_mode);
+        w.put_bool(self.is_byte_instr);
+        w.put_u8(self.num_cycles);
+        w.put_u16(self.operand_0);
+        w.put_u16(self.operand_1);
+        w.put_u16(self.mem_read_addr_0);
+        w.put_bool(self.mem_read_en_0);
+        w.put_bool(self.used_instr_word_for_src);
+        w.put_u16(self.mem_read_addr_1);
+        w.put_bool(self.mem_read_en_1);
+        w.put_bool(self.used_instr_word_for_dst);
+        w.put_bool(self.inc_src_reg);
+        w.put_bool(self.dec_sp);
+        w.put_opt_bool(self.new_cf);
+        w.put_opt_bool(self.new_zf);
+        w.put_opt_bool(self.new_nf);
+        w.put_opt_bool(self.new_vf);
+        w.put_u16(self.result);
+        w.put_u16(self.new_pc_val);
+        w.put_bool(self.jump_taken);
+        w.put_u16(self.mem_write_addr);
+        w.put_bool(self.mem_write_en);
+
+        w.put_u16(self.pending_interrupts);
+        w.put_u64(self.cycle_count);
+        w.put_u64(self.cycle_deadline);
+        w.put_u64(self.instr_index);
+
+        self.bus.save_state(&mut w);
+
+        w.into_bytes()
+    }
+
+    pub fn load_snapshot(&mut self, bytes: &[u8]) -> io::Result<()> {
+        // the emulator must have the same peripherals registered as the one that saved the snapshot.
+        // if loading fails, the emulator is left as it was. peripherals can only be restored in
+        // place, so the current state is saved first, and put back if the snapshot is bad.
+        let old_state = self.save_snapshot();
+        if let Err(e) = self.read_snapshot(bytes) {
+            self.read_snapshot(&old_state)
+                .expect("the emulator rejected its own snapshot");
+            return Err(e);
+        }
+
+        // the journal describes how the old state was reached, so it can't undo anything now
+        self.journal.clear();
+
+        Ok(())
+    }
+
+    fn read_snapshot(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let mut r = SnapshotReader::new(bytes);
+        if r.get_bytes(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+            return Err(invalid_snapshot("not an emulator snapshot"));
+        }
+        let version = r.get_u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_snapshot(&format!(
+                "unsupported snapshot version {}",
+                version
+            )));
+        }
+
+        for i in 0..16 {
+            self.regs[i] = r.get_u16()?;
+        }
+
+        self.curr_instr = r.get_u16()?;
+        self.next_word = r.get_u16()?;
+        self.next_next_word = r.get_u16()?;
+        self.opcode = r.get_u16()?;
+        self.src_reg_id = r.get_u16()?;
+        self.src_addr_mode = r.get_u16()?;
+        self.dst_reg_id = r.get_u16()?;
+        self.dst_addr_mode = r.get_u16()?;
+        self.is_byte_instr = r.get_bool()?;
+        self.num_cycles = r.get_u8()?;
+        self.operand_0 = r.get_u16()?;
+        self.operand_1 = r.get_u16()?;
+        self.mem_read_addr_0 = r.get_u16()?;
+        self.mem_read_en_0 = r.get_bool()?;
+        self.used_instr_word_for_src = r.get_bool()?;
+        self.mem_read_addr_1 = r.get_u16()?;
+        self.mem_read_en_1 = r.get_bool()?;
+        self.used_instr_word_for_dst = r.get_bool()?;
+        self.inc_src_reg = r.get_bool()?;
+        self.dec_sp = r.get_bool()?;
+        self.new_cf = r.get_opt_bool()?;
+        self.new_zf = r.get_opt_bool()?;
+        self.new_nf = r.get_opt_bool()?;
+        self.new_vf = r.get_opt_bool()?;
+        self.result = r.get_u16()?;
+        self.new_pc_val = r.get_u16()?;
+        self.jump_taken = r.get_bool()?;
+        self.mem_write_addr = r.get_u16()?;
+        self.mem_write_en = r.get_bool()?;
+
+        self.pending_interrupts = r.get_u16()?;
+        self.cycle_count = r.get_u64()?;
+        self.cycle_deadline = r.get_u64()?;
+        self.instr_index = r.get_u64()?;
+
+        self.bus.load_state(&mut r)?;
+        if r.index != bytes.len() {
+            return Err(invalid_snapshot("trailing data in snapshot"));
+        }
+
+        Ok(())
+    }
+
+    pub fn save_snapshot_to_file(&self, file_name: &str) -> io::Result<()> {
+        File::create(file_name)?.write_all(&self.save_snapshot())
+    }
+
+    pub fn load_snapshot_from_file(&mut self, file_name: &str) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        File::open(file_name)?.read_to_end(&mut bytes)?;
+        self.load_snapshot(&bytes)
+    }
+}
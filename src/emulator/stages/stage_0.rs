@@ -1,25 +1,23 @@
+use crate::emulator::{bus::Bus, fault::CpuFault};
+
 pub struct Stage0Result {
     pub curr_instr: u16,
     pub next_word: u16,
     pub next_next_word: u16,
 }
 
-pub fn exec_stage_0(mem: &[u8], pc: u16) -> Stage0Result {
-    let low_byte = mem[pc as usize];
-    let high_byte = mem[(pc + 1) as usize];
-    let curr_instr = u16::from_le_bytes([low_byte, high_byte]);
-
-    let low_byte = mem[(pc + 2) as usize];
-    let high_byte = mem[(pc + 3) as usize];
-    let next_word = u16::from_le_bytes([low_byte, high_byte]);
+pub fn exec_stage_0(bus: &mut Bus, pc: u16) -> Result<Stage0Result, CpuFault> {
+    if !pc.is_multiple_of(2) {
+        return Err(CpuFault::MisalignedFetch { pc });
+    }
 
-    let low_byte = mem[(pc + 4) as usize];
-    let high_byte = mem[(pc + 5) as usize];
-    let next_next_word = u16::from_le_bytes([low_byte, high_byte]);
+    let curr_instr = bus.read_word(pc);
+    let next_word = bus.read_word(pc.overflowing_add(2).0);
+    let next_next_word = bus.read_word(pc.overflowing_add(4).0);
 
-    Stage0Result {
+    Ok(Stage0Result {
         curr_instr,
         next_word,
         next_next_word,
-    }
+    })
 }
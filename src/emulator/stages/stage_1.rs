@@ -1,3 +1,5 @@
+use crate::emulator::{cycles::get_instr_cycles, fault::CpuFault};
+
 pub struct Stage1Result {
     pub src_addr_mode: u16,  // 2 bit quantity
     pub dst_addr_mode: u16,  // 1 bit quantity
@@ -6,9 +8,11 @@ pub struct Stage1Result {
     pub opcode: u16, // this will be a 3 or 4 bit quantity depending on which instruction family is being executed
     pub src_reg_id: u16, // 4 bit quantity
     pub dst_reg_id: u16, // 4 bit quantity
+
+    pub num_cycles: u8,
 }
 
-pub fn exec_stage_1(curr_instr: u16) -> Stage1Result {
+pub fn exec_stage_1(curr_instr: u16, pc: u16) -> Result<Stage1Result, CpuFault> {
     // decode instruction
     let opcode: u16;
     let src_reg_id: u16;
@@ -16,7 +20,8 @@ pub fn exec_stage_1(curr_instr: u16) -> Stage1Result {
 
     let src_addr_mode = (curr_instr >> 4) & 0x3;
     let dst_addr_mode = (curr_instr >> 7) & 0x1;
-    let is_byte_instr = (curr_instr >> 6) & 0x1 == 1;
+    // bit 6 is part of the offset in jump instructions, so it only selects byte mode otherwise
+    let is_byte_instr = (curr_instr & 0xE000) != 0x2000 && (curr_instr >> 6) & 0x1 == 1;
 
     if (curr_instr & 0xE000) == 0 {
         opcode = (curr_instr >> 7) & 0x7;
@@ -33,12 +38,49 @@ pub fn exec_stage_1(curr_instr: u16) -> Stage1Result {
         src_reg_id = (curr_instr >> 8) & 0x0F;
         dst_reg_id = curr_instr & 0x0F;
     }
-    Stage1Result {
+    if (curr_instr & 0xE000) == 0 {
+        // single operand instruction. only 0x1000 to 0x137F are defined.
+        if (curr_instr & 0xFC00) != 0x1000 || opcode == 7 {
+            return Err(CpuFault::IllegalOpcode {
+                pc,
+                instr: curr_instr,
+            });
+        }
+        // SWPB, SXT, CALL and RETI have no byte form
+        if is_byte_instr && (opcode == 1 || opcode == 3 || opcode == 5 || opcode == 6) {
+            return Err(CpuFault::IllegalOpcode {
+                pc,
+                instr: curr_instr,
+            });
+        }
+        // RRC, SWPB, RRA and SXT write their result back to their operand,
+        // which is impossible for constants and immediates.
+        let is_constant = src_reg_id == 3 || (src_reg_id == 2 && src_addr_mode >= 2);
+        let is_immediate = src_reg_id == 0 && src_addr_mode == 3;
+        if opcode <= 3 && (is_constant || is_immediate) {
+            return Err(CpuFault::InvalidDstAddrMode {
+                pc,
+                instr: curr_instr,
+            });
+        }
+    }
+
+    let num_cycles = get_instr_cycles(
+        curr_instr,
+        opcode,
+        src_addr_mode,
+        src_reg_id,
+        dst_addr_mode,
+        dst_reg_id,
+    );
+
+    Ok(Stage1Result {
         opcode,
         src_reg_id,
         src_addr_mode,
         dst_reg_id,
         dst_addr_mode,
         is_byte_instr,
-    }
+        num_cycles,
+    })
 }
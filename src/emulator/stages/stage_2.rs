@@ -1,5 +1,8 @@
+use crate::emulator::bus::Bus;
+
 pub struct Stage2AResult {
     pub mem_read_addr_0: u16,
+    pub mem_read_en_0: bool,
     pub inc_src_reg: bool,
     pub used_instr_word_for_src: bool,
 }
@@ -17,9 +20,14 @@ pub fn exec_stage_2a(
 ) -> Stage2AResult {
     let mut inc_src_reg = false;
     let mut mem_read_addr_0 = 0x0000;
+    let mut mem_read_en_0 = false;
     let mut used_instr_word_for_src = false;
-    // if the current instruction takes a source register (i.e is not a jump instruction)
-    if (curr_instr & 0xE000) != 0x2000 {
+    if (curr_instr & 0xFF80) == 0x1300 {
+        // RETI: the saved SR is on top of the stack
+        mem_read_addr_0 = regs[1];
+        mem_read_en_0 = true;
+    } else if (curr_instr & 0xE000) != 0x2000 {
+        // the current instruction takes a source register (i.e is not a jump instruction)
         match src_addr_mode {
             0 => {}
             1 => {
@@ -28,10 +36,17 @@ pub fn exec_stage_2a(
                     if src_reg_id == 2 {
                         // absolute addressing
                         mem_read_addr_0 = next_word;
+                        mem_read_en_0 = true;
+                        used_instr_word_for_src = true;
+                    } else if src_reg_id == 0 {
+                        // symbolic addressing: the index is relative to the index word itself
+                        mem_read_addr_0 = regs[0].overflowing_add(2).0.overflowing_add(next_word).0;
+                        mem_read_en_0 = true;
                         used_instr_word_for_src = true;
                     } else {
                         // indexed addressing
-                        mem_read_addr_0 = regs[src_reg_id as usize] + next_word;
+                        mem_read_addr_0 = regs[src_reg_id as usize].overflowing_add(next_word).0;
+                        mem_read_en_0 = true;
                         used_instr_word_for_src = true;
                     }
                 }
@@ -40,16 +55,19 @@ pub fn exec_stage_2a(
                 // indirect addressing
                 if src_reg_id != 2 && src_reg_id != 3 {
                     mem_read_addr_0 = regs[src_reg_id as usize];
+                    mem_read_en_0 = true;
                 }
             }
             3 => {
                 if src_reg_id == 0 {
                     // immediate
-                    mem_read_addr_0 = regs[0] + 2;
+                    mem_read_addr_0 = regs[0].overflowing_add(2).0;
+                    mem_read_en_0 = true;
                     used_instr_word_for_src = true;
                 } else if src_reg_id != 2 && src_reg_id != 3 {
                     // indirect auto-inc mode
                     mem_read_addr_0 = regs[src_reg_id as usize];
+                    mem_read_en_0 = true;
                     inc_src_reg = true;
                 }
             }
@@ -59,26 +77,40 @@ pub fn exec_stage_2a(
 
     Stage2AResult {
         mem_read_addr_0,
+        mem_read_en_0,
         inc_src_reg,
         used_instr_word_for_src,
     }
 }
 
-pub fn exec_stage_2b(
-    curr_instr: u16,
-    src_addr_mode: u16,
-    src_reg_id: u16,
-    mem_read_addr_0: u16,
-    mem: &[u8],
-    regs: &[u16],
-) -> Stage2BResult {
+// the latches from the earlier stages that stage 2b reads
+pub struct Stage2BInput {
+    pub curr_instr: u16,
+    pub src_addr_mode: u16,
+    pub src_reg_id: u16,
+    pub is_byte_instr: bool,
+    pub mem_read_addr_0: u16,
+    pub mem_read_en_0: bool,
+}
+
+pub fn exec_stage_2b(input: Stage2BInput, bus: &mut Bus, regs: &[u16]) -> Stage2BResult {
+    let Stage2BInput {
+        curr_instr,
+        src_addr_mode,
+        src_reg_id,
+        is_byte_instr,
+        mem_read_addr_0,
+        mem_read_en_0,
+    } = input;
     // load operand 0
-    let operand_0: u16;
+    let mut operand_0: u16;
 
-    if mem_read_addr_0 != 0x0000 {
-        let low_byte = mem[mem_read_addr_0 as usize];
-        let high_byte = mem[(mem_read_addr_0 + 1) as usize];
-        operand_0 = u16::from_le_bytes([low_byte, high_byte]);
+    if mem_read_en_0 {
+        if is_byte_instr {
+            operand_0 = bus.read_byte(mem_read_addr_0) as u16;
+        } else {
+            operand_0 = bus.read_word(mem_read_addr_0);
+        }
     } else {
         // if the current instruction takes a source register
         if (curr_instr & 0xE000) == 0 || (curr_instr & 0xC000) != 0 {
@@ -90,7 +122,7 @@ pub fn exec_stage_2b(
                     if src_reg_id == 3 {
                         operand_0 = 1; // immediate
                     } else {
-                        // unreachable because mem_read_addr_0 would be non-null.
+                        // unreachable because mem_read_en_0 would be set.
                         unreachable!();
                     }
                 }
@@ -100,7 +132,7 @@ pub fn exec_stage_2b(
                     } else if src_reg_id == 3 {
                         operand_0 = 2; // immediate
                     } else {
-                        // unreachable because mem_read_addr_0 would be non-null.
+                        // unreachable because mem_read_en_0 would be set.
                         unreachable!();
                     }
                 }
@@ -110,7 +142,7 @@ pub fn exec_stage_2b(
                     } else if src_reg_id == 3 {
                         operand_0 = 0xFFFF; // immediate -1.
                     } else {
-                        // unreachable because mem_read_addr_0 would be non-null.
+                        // unreachable because mem_read_en_0 would be set.
                         unreachable!();
                     }
                 }
@@ -126,5 +158,10 @@ pub fn exec_stage_2b(
         }
     }
 
+    if is_byte_instr {
+        // byte instructions only operate on the low byte of registers and immediates
+        operand_0 &= 0x00FF;
+    }
+
     Stage2BResult { operand_0 }
 }
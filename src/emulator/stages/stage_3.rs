@@ -1,5 +1,8 @@
+use crate::emulator::bus::Bus;
+
 pub struct Stage3AResult {
     pub mem_read_addr_1: u16,
+    pub mem_read_en_1: bool,
     pub used_instr_word_for_dst: bool,
 }
 
@@ -17,6 +20,7 @@ pub fn exec_stage_3a(
     regs: &[u16],
 ) -> Stage3AResult {
     let mut mem_read_addr_1: u16 = 0x0000;
+    let mut mem_read_en_1 = false;
     let mut used_instr_word_for_dst = false;
 
     // if the current instruction takes a dst register
@@ -24,6 +28,9 @@ pub fn exec_stage_3a(
         match dst_addr_mode {
             1 => {
                 used_instr_word_for_dst = true;
+                // MOV overwrites its destination without using it, so the destination is not read.
+                // this matters for peripherals that have side effects when read.
+                mem_read_en_1 = (curr_instr & 0xF000) != 0x4000;
                 let next_instr_stream_word = if used_instr_word_for_src {
                     next_next_word
                 } else {
@@ -32,6 +39,14 @@ pub fn exec_stage_3a(
                 if dst_reg_id == 2 {
                     // absolute addressing
                     mem_read_addr_1 = next_instr_stream_word;
+                } else if dst_reg_id == 0 {
+                    // symbolic addressing: the index is relative to the index word itself
+                    let index_word_offset = if used_instr_word_for_src { 4 } else { 2 };
+                    mem_read_addr_1 = regs[0]
+                        .overflowing_add(index_word_offset)
+                        .0
+                        .overflowing_add(next_instr_stream_word)
+                        .0;
                 } else {
                     // indexed addressing
                     mem_read_addr_1 = regs[dst_reg_id as usize]
@@ -44,26 +59,37 @@ pub fn exec_stage_3a(
             }
             _ => unreachable!(),
         }
+    } else if (curr_instr & 0xFF80) == 0x1300 {
+        // RETI: the saved PC is below the saved SR on the stack
+        mem_read_addr_1 = regs[1].overflowing_add(2).0;
+        mem_read_en_1 = true;
     }
     Stage3AResult {
         mem_read_addr_1,
+        mem_read_en_1,
         used_instr_word_for_dst,
     }
 }
 
 pub fn exec_stage_3b(
     dst_reg_id: u16,
+    is_byte_instr: bool,
     mem_read_addr_1: u16,
-    mem: &[u8],
+    mem_read_en_1: bool,
+    bus: &mut Bus,
     regs: &[u16],
 ) -> Stage3BResult {
     // load operand 1
     let operand_1: u16;
 
-    if mem_read_addr_1 != 0x0000 {
-        let low_byte = mem[mem_read_addr_1 as usize];
-        let high_byte = mem[(mem_read_addr_1 + 1) as usize];
-        operand_1 = u16::from_le_bytes([low_byte, high_byte]);
+    if mem_read_en_1 {
+        if is_byte_instr {
+            operand_1 = bus.read_byte(mem_read_addr_1) as u16;
+        } else {
+            operand_1 = bus.read_word(mem_read_addr_1);
+        }
+    } else if is_byte_instr {
+        operand_1 = regs[dst_reg_id as usize] & 0x00FF;
     } else {
         operand_1 = regs[dst_reg_id as usize];
     }
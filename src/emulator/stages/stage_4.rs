@@ -1,5 +1,6 @@
 use crate::emulator::{
-    double_operand::process_double_operand_w, single_operand::process_single_operand_w,
+    double_operand::{process_double_operand_b, process_double_operand_w},
+    single_operand::{process_single_operand_b, process_single_operand_w},
 };
 
 pub struct Stage4Result {
@@ -12,11 +13,13 @@ pub struct Stage4Result {
     pub result: u16,
 
     pub new_pc_val: u16,
+    pub jump_taken: bool,
 }
 
 pub fn exec_stage_4(
     curr_instr: u16,
     opcode: u16,
+    is_byte_instr: bool,
     operand_0: u16,
     operand_1: u16,
     regs: &[u16],
@@ -29,7 +32,8 @@ pub fn exec_stage_4(
     let mut new_nf = None;
     let mut new_vf = None;
     let mut result = 0;
-    let mut new_pc_val = 0x0000; // if this value is nonzero, that means jump is taken. Otherwise, jump is not taken.
+    let mut new_pc_val = 0x0000; // only meaningful if jump_taken is set
+    let mut jump_taken = false;
 
     let sr = regs[2];
     let carry_flag = sr & 0x01 != 0;
@@ -39,10 +43,17 @@ pub fn exec_stage_4(
 
     if (curr_instr & 0xE000) == 0 {
         // single operand instruction
-        (result, new_cf, new_zf, new_nf, new_vf, dec_sp, new_pc_val) =
-            process_single_operand_w(operand_0, carry_flag, opcode, regs);
+        if is_byte_instr {
+            (
+                result, new_cf, new_zf, new_nf, new_vf, dec_sp, new_pc_val, jump_taken,
+            ) = process_single_operand_b(operand_0 as u8, carry_flag, opcode);
+        } else {
+            (
+                result, new_cf, new_zf, new_nf, new_vf, dec_sp, new_pc_val, jump_taken,
+            ) = process_single_operand_w(operand_0, operand_1, carry_flag, opcode, regs);
+        }
     } else if (curr_instr & 0xC000) == 0 {
-        let jump_taken = match opcode {
+        jump_taken = match opcode {
             0 => !zero_flag,                  // JNZ
             1 => zero_flag,                   // JZ
             2 => !carry_flag,                 // JNC
@@ -53,13 +64,16 @@ pub fn exec_stage_4(
             7 => true,                        // JMP
             _ => unreachable!(),
         };
-        if jump_taken {
-            new_pc_val = operand_0;
-        }
+        new_pc_val = operand_0;
     } else {
         // double operand instruction
-        (result, new_cf, new_zf, new_nf, new_vf) =
-            process_double_operand_w(operand_0, operand_1, carry_flag, opcode);
+        if is_byte_instr {
+            (result, new_cf, new_zf, new_nf, new_vf) =
+                process_double_operand_b(operand_0 as u8, operand_1 as u8, carry_flag, opcode);
+        } else {
+            (result, new_cf, new_zf, new_nf, new_vf) =
+                process_double_operand_w(operand_0, operand_1, carry_flag, opcode);
+        }
     }
 
     Stage4Result {
@@ -70,5 +84,6 @@ pub fn exec_stage_4(
         new_vf,
         result,
         new_pc_val,
+        jump_taken,
     }
 }
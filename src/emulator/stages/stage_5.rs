@@ -1,30 +1,62 @@
+use crate::emulator::{bus::Bus, fault::CpuFault};
+
 pub struct Stage5Result {
     pub regs: [u16; 16],
     pub mem_write_addr: u16,
+    pub mem_write_en: bool,
 }
 
-pub fn exec_stage_5a(
-    mut regs: [u16; 16],
-    inc_src_reg: bool,
-    dec_sp: bool,
-    new_cf: Option<bool>,
-    new_zf: Option<bool>,
-    new_nf: Option<bool>,
-    new_vf: Option<bool>,
-    src_reg_id: u16,
-    dst_reg_id: u16,
-    curr_instr: u16,
-    opcode: u16,
-    src_addr_mode: u16,
-    dst_addr_mode: u16,
-    result: u16,
-    mem_read_addr_0: u16,
-    mem_read_addr_1: u16,
-    new_pc_val: u16,
-    used_instr_word_for_src: bool,
-    used_instr_word_for_dst: bool,
-) -> Stage5Result {
+// the latches from the earlier stages that stage 5a reads
+pub struct Stage5AInput {
+    pub inc_src_reg: bool,
+    pub dec_sp: bool,
+    pub new_cf: Option<bool>,
+    pub new_zf: Option<bool>,
+    pub new_nf: Option<bool>,
+    pub new_vf: Option<bool>,
+    pub src_reg_id: u16,
+    pub dst_reg_id: u16,
+    pub curr_instr: u16,
+    pub opcode: u16,
+    pub is_byte_instr: bool,
+    pub src_addr_mode: u16,
+    pub dst_addr_mode: u16,
+    pub result: u16,
+    pub mem_read_addr_0: u16,
+    pub mem_read_en_0: bool,
+    pub mem_read_addr_1: u16,
+    pub new_pc_val: u16,
+    pub jump_taken: bool,
+    pub used_instr_word_for_src: bool,
+    pub used_instr_word_for_dst: bool,
+}
+
+pub fn exec_stage_5a(mut regs: [u16; 16], input: Stage5AInput) -> Result<Stage5Result, CpuFault> {
+    let Stage5AInput {
+        inc_src_reg,
+        dec_sp,
+        new_cf,
+        new_zf,
+        new_nf,
+        new_vf,
+        src_reg_id,
+        dst_reg_id,
+        curr_instr,
+        opcode,
+        is_byte_instr,
+        src_addr_mode,
+        dst_addr_mode,
+        result,
+        mem_read_addr_0,
+        mem_read_en_0,
+        mem_read_addr_1,
+        new_pc_val,
+        jump_taken,
+        used_instr_word_for_src,
+        used_instr_word_for_dst,
+    } = input;
     let mut mem_write_addr = 0;
+    let mut mem_write_en = false;
 
     if let Some(new_cf) = new_cf {
         if new_cf {
@@ -55,19 +87,30 @@ pub fn exec_stage_5a(
         }
     }
     if dec_sp {
-        regs[1] -= 2;
+        regs[1] = regs[1].overflowing_sub(2).0;
     }
     if inc_src_reg {
-        regs[src_reg_id as usize] += 2; // TODO: handle b/w
+        // byte instructions step by 1, except for SP which must stay word aligned
+        if is_byte_instr && src_reg_id != 1 {
+            regs[src_reg_id as usize] = regs[src_reg_id as usize].overflowing_add(1).0;
+        } else {
+            regs[src_reg_id as usize] = regs[src_reg_id as usize].overflowing_add(2).0;
+        }
     }
 
     if (curr_instr & 0xE000) == 0 {
         if opcode == 5 {
             // CALL
             mem_write_addr = regs[1];
+            mem_write_en = true;
         } else if opcode == 4 {
             // PUSH
             mem_write_addr = regs[1];
+            mem_write_en = true;
+        } else if opcode == 6 {
+            // RETI
+            regs[2] = result;
+            regs[1] = regs[1].overflowing_add(4).0;
         } else {
             // single operand instr
             match src_addr_mode {
@@ -75,8 +118,10 @@ pub fn exec_stage_5a(
                     regs[src_reg_id as usize] = result;
                 }
                 1 | 2 | 3 => {
-                    // indexed, indirect, absolute, or indirect auto-inc addressing mode
+                    // indexed, indirect, absolute, or indirect auto-inc addressing mode.
+                    // constant generator operands are not read from memory, so nothing is written back.
                     mem_write_addr = mem_read_addr_0;
+                    mem_write_en = mem_read_en_0;
                 }
                 _ => unreachable!(),
             }
@@ -96,34 +141,49 @@ pub fn exec_stage_5a(
                 1 => {
                     // indexed, or absolute addressing mode
                     mem_write_addr = mem_read_addr_1;
+                    mem_write_en = true;
                 }
                 _ => unreachable!(),
             }
         }
     }
 
-    if new_pc_val != 0 {
-        regs[0] = new_pc_val + 2;
+    if jump_taken {
+        regs[0] = new_pc_val.overflowing_add(2).0;
     } else {
-        if used_instr_word_for_src & used_instr_word_for_dst {
-            regs[0] += 6;
+        let instr_len = if used_instr_word_for_src & used_instr_word_for_dst {
+            6
         } else if used_instr_word_for_src | used_instr_word_for_dst {
-            regs[0] += 4;
+            4
         } else {
-            regs[0] += 2;
-        }
+            2
+        };
+        // a jump may wrap around to address 0, but running off the end of memory is a fault
+        regs[0] = match regs[0].checked_add(instr_len) {
+            None => return Err(CpuFault::PcOverflow { pc: regs[0] }),
+            Some(pc) => pc,
+        };
     }
 
-    Stage5Result {
+    Ok(Stage5Result {
         regs,
         mem_write_addr,
-    }
+        mem_write_en,
+    })
 }
 
-pub fn exec_stage_5b(mem_write_addr: u16, result: u16, mem: &mut [u8]) {
-    if mem_write_addr != 0 {
-        let [low_byte, high_byte] = result.to_le_bytes();
-        mem[mem_write_addr as usize] = low_byte;
-        mem[(mem_write_addr + 1) as usize] = high_byte;
+pub fn exec_stage_5b(
+    mem_write_addr: u16,
+    mem_write_en: bool,
+    result: u16,
+    is_byte_instr: bool,
+    bus: &mut Bus,
+) {
+    if mem_write_en {
+        if is_byte_instr {
+            bus.write_byte(mem_write_addr, result as u8);
+        } else {
+            bus.write_word(mem_write_addr, result);
+        }
     }
 }
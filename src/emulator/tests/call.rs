@@ -10,16 +10,16 @@ fn test_call_and_ret() {
     ];
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
 
-    cpu_emu.run_one_instr();
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x000C);
     assert_eq!(cpu_emu.regs[1], 0x7FFE);
-    assert_eq!(cpu_emu.mem[0x7FFE], 0x06);
-    assert_eq!(cpu_emu.mem[0x7FFF], 0x00);
-    cpu_emu.run_one_instr();
+    assert_eq!(cpu_emu.bus.peek_byte(0x7FFE), 0x06);
+    assert_eq!(cpu_emu.bus.peek_byte(0x7FFF), 0x00);
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x0008);
     assert_eq!(cpu_emu.regs[1], 0x8000);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x000C);
     assert_eq!(cpu_emu.regs[1], 0x8000);
     assert_eq!(cpu_emu.regs[15], 0x1101);
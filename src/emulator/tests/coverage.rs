@@ -0,0 +1,95 @@
+use crate::{
+    byte_generator::generate_bytes_symbols_and_addrs,
+    emulator::{coverage::Coverage, Emulator},
+    get_verbs::get_tokens_with_line_nums,
+    source_map::SourceMap,
+};
+
+const SOURCE: &str = ";   3 | int n = 3;
+main:
+    MOV #3,r15
+loop:
+;   5 | while (--n) {}
+    SUB #1,r15
+    JNE loop
+;   6 | if (n) { n = 1; }
+    CMP #0,r15
+    JNE never
+end:
+    JMP end
+never:
+    MOV #1,r15
+    JMP end
+";
+
+fn run_covered() -> (Coverage, SourceMap) {
+    let (globals, lines, line_nums) = get_tokens_with_line_nums(SOURCE.to_owned());
+    let (bytes, _, addrs) =
+        generate_bytes_symbols_and_addrs(globals, lines.clone(), &line_nums).unwrap();
+    let source_map = SourceMap::new(SOURCE, &lines, &line_nums, &addrs);
+
+    let mut cpu_emu = Emulator::new(&bytes);
+    cpu_emu.set_coverage(Some(Coverage::new()));
+    // the startup code, then the loop runs 3 times and the program stops at end
+    for _ in 0..12 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert!(cpu_emu.is_halted());
+    (cpu_emu.set_coverage(None).unwrap(), source_map)
+}
+
+#[test]
+fn test_coverage_annotated_listing() {
+    let (coverage, source_map) = run_covered();
+    let mut out = Vec::new();
+    coverage
+        .write_annotated_listing(&mut out, &source_map)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "lines executed: 6 of 8, branches taken at least once: 3 of 4
+        -:    1:;   3 | int n = 3;
+        -:    2:main:
+        1:    3:    MOV #3,r15
+        -:    4:loop:
+        -:    5:;   5 | while (--n) {}
+        3:    6:    SUB #1,r15
+        3:    7:    JNE loop
+                branch taken 2, not taken 1
+        -:    8:;   6 | if (n) { n = 1; }
+        1:    9:    CMP #0,r15
+        1:   10:    JNE never
+                branch taken 0, not taken 1
+        -:   11:end:
+        1:   12:    JMP end
+        -:   13:never:
+    #####:   14:    MOV #1,r15
+    #####:   15:    JMP end
+"
+    );
+}
+
+#[test]
+fn test_coverage_lcov() {
+    let (coverage, source_map) = run_covered();
+    let mut out = Vec::new();
+    coverage
+        .write_lcov(&mut out, &source_map, "main.asm", "main.c")
+        .unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let records: Vec<&str> = out.split_inclusive("end_of_record\n").collect();
+    assert_eq!(
+        records,
+        vec![
+            "TN:\nSF:main.asm\n\
+             BRDA:7,0,0,2\nBRDA:7,0,1,1\nBRDA:10,0,0,0\nBRDA:10,0,1,1\nBRF:4\nBRH:3\n\
+             DA:3,1\nDA:6,3\nDA:7,3\nDA:9,1\nDA:10,1\nDA:12,1\nDA:14,0\nDA:15,0\n\
+             LF:8\nLH:6\nend_of_record\n",
+            // C lines are counted by the instruction on them that ran the most times
+            "TN:\nSF:main.c\n\
+             BRDA:5,0,0,2\nBRDA:5,0,1,1\nBRDA:6,0,0,0\nBRDA:6,0,1,1\nBRF:4\nBRH:3\n\
+             DA:3,1\nDA:5,3\nDA:6,1\n\
+             LF:3\nLH:3\nend_of_record\n",
+        ]
+    );
+}
@@ -0,0 +1,63 @@
+use crate::emulator::{tests::convert_words_to_bytes, Emulator};
+
+#[test]
+fn test_instr_cycles() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x12B0, 0x000A, //  CALL  0x0000A
+        0x403F, 0x1101, //  MOV.W  #0x1101,R15
+        0x4130, // RET
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    assert_eq!(cpu_emu.run_one_instr(), Ok(2));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(5));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(3));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(2));
+    assert_eq!(cpu_emu.get_cycle_count(), 12);
+}
+
+#[test]
+fn test_addr_mode_cycles() {
+    let instrs: Vec<u16> = vec![
+        0x403F, 0x8000, //  MOV.W  #0x8000,R15
+        0x4F0E, //  MOV.W  R15,R14
+        0x40BF, 0x1234, 0x0000, //  MOV.W  #0x1234,0(R15)
+        0x3C00, //  JMP  $+2
+        0x5F9E, 0x0002, 0x0004, //  ADD.W  2(R15),4(R14)
+        0x4303, //  NOP
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    assert_eq!(cpu_emu.run_one_instr(), Ok(2));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(1));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(5));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(2));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(6));
+    assert_eq!(cpu_emu.run_one_instr(), Ok(1));
+    assert_eq!(cpu_emu.get_cycle_count(), 17);
+}
+
+#[test]
+fn test_run_cycles() {
+    let instrs: Vec<u16> = vec![
+        0x4303, //  NOP
+        0x4303, //  NOP
+        0x531F, //  INC.W  R15
+        0x3FFE, //  JMP  $-2
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    // the NOPs take 1 cycle each and each loop iteration takes 3 cycles
+    cpu_emu.run_cycles(32).unwrap();
+    assert_eq!(cpu_emu.get_cycle_count(), 32);
+    assert_eq!(cpu_emu.regs[15], 10);
+
+    // the extra cycle spent on the jump is made up for in the next call
+    cpu_emu.run_cycles(2).unwrap();
+    assert_eq!(cpu_emu.get_cycle_count(), 35);
+    cpu_emu.run_cycles(1).unwrap();
+    assert_eq!(cpu_emu.get_cycle_count(), 35);
+    cpu_emu.run_cycles(1).unwrap();
+    assert_eq!(cpu_emu.get_cycle_count(), 36);
+}
@@ -0,0 +1,93 @@
+use super::tracer::SharedBuffer;
+use crate::{
+    asm_tests::debug_info::{assemble, SOURCE},
+    emulator::{
+        debugger::Debugger,
+        tracer::{TraceFormat, Tracer},
+        Emulator,
+    },
+    get_verbs::debug_directives::get_debug_info,
+};
+
+#[test]
+fn test_debugger_c_source() {
+    let (bytes, symbols, source_map) = assemble();
+    let mut cpu_emu = Emulator::new(&bytes);
+    let mut debugger = Debugger::new(symbols);
+    debugger.set_source(source_map, get_debug_info(SOURCE));
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "break main.c:8"),
+        "breakpoint at 0x0012 <main+0x8>"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "continue"),
+        "breakpoint at 0x0012 <main+0x8>: ADD.W #1,&cursor_location   at main.c:8"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "locals"),
+        "count = 3 (0x0003)"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "print cursor_location"),
+        "cursor_location = 0 (0x0000)"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "cstep"),
+        "0x0016 <main+0xC>: MOV.B #2,&next_player   at main.c:9"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "print cursor_location"),
+        "cursor_location = 1 (0x0001)"
+    );
+    debugger.execute(&mut cpu_emu, "step");
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "print next_player"),
+        "next_player = 2 (0x02)"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "print board"),
+        "no variable board"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "break main.c:6"),
+        "unknown address main.c:6"
+    );
+}
+
+#[test]
+fn test_trace_c_source() {
+    let (bytes, symbols, source_map) = assemble();
+    let mut cpu_emu = Emulator::new(&bytes);
+    let buffer = SharedBuffer::default();
+    let mut tracer = Tracer::new(Box::new(buffer.clone()), TraceFormat::Text, false, symbols);
+    tracer.set_source_map(source_map);
+    cpu_emu.set_tracer(Some(tracer));
+    for _ in 0..7 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    cpu_emu.set_tracer(None).unwrap().finish().unwrap();
+
+    // the startup code has no C line, and a C line is only shown when it changes
+    let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    let lines: Vec<String> = output
+        .lines()
+        .map(|l| l.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "0 0000 MOV.W",
+            "2 0004 JMP",
+            "main.c:5",
+            "4 000A SUB.W",
+            "main.c:7",
+            "5 000C MOV.W",
+            "main.c:8",
+            "10 0012 ADD.W",
+            "main.c:9",
+            "14 0016 MOV.B",
+            "18 001A JMP",
+        ]
+    );
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::emulator::{debugger::Debugger, tests::convert_words_to_bytes, Emulator};
+
+fn setup() -> (Emulator, Debugger) {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x12B0, 0x0008, //  CALL  #func
+        0x3FFF, //  JMP  $
+        // func:
+        0x531F, //  ADD.W  #1,R15
+        0x531F, //  ADD.W  #1,R15
+        0x4130, //  RET
+    ];
+    let symbols = HashMap::from([("main".to_owned(), 0x0000), ("func".to_owned(), 0x000A)]);
+    (
+        Emulator::new(&convert_words_to_bytes(instrs)),
+        Debugger::new(symbols),
+    )
+}
+
+#[test]
+fn test_debugger_step_and_next() {
+    let (mut cpu_emu, mut debugger) = setup();
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "step"),
+        "0x0004 <main+0x4>: CALL #func"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "next"),
+        "0x0008 <main+0x8>: JMP 0x0008"
+    );
+    assert_eq!(cpu_emu.regs[1], 0x8000);
+    assert_eq!(cpu_emu.regs[15], 2);
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "x/3w func"),
+        "000A: 531F 531F 4130"
+    );
+    assert_eq!(debugger.execute(&mut cpu_emu, "x/2b 0x0004"), "0004: B0 12");
+    // counts that reach past the end of memory wrap around to address 0
+    let words = debugger.execute(&mut cpu_emu, "x/40000w 0");
+    assert_eq!(words.lines().count(), 5000);
+    assert!(words.ends_with("\n3870: 0000 0000 0000 0000 0000 0000 0000 0000"));
+    let bytes = debugger.execute(&mut cpu_emu, "x/65535b 0");
+    assert!(bytes.ends_with("\nFFF0: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00"));
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "x/2i func+0"),
+        "unknown address func+0"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "x/3i 0xC"),
+        "0x000C <func+0x2>: ADD.W #1,r15\n0x000E <func+0x4>: RET\n0x0010 <func+0x6>: .word 0x0000"
+    );
+}
+
+#[test]
+fn test_debugger_breakpoints_and_finish() {
+    let (mut cpu_emu, mut debugger) = setup();
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "break func"),
+        "breakpoint at 0x000A <func>"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "continue"),
+        "breakpoint at 0x000A <func>: ADD.W #1,r15"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "step"),
+        "0x000C <func+0x2>: ADD.W #1,r15"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "finish"),
+        "0x0008 <main+0x8>: JMP 0x0008"
+    );
+    assert_eq!(cpu_emu.regs[15], 2);
+
+    // run the call again from the start, stopping at the breakpoint inside it
+    assert_eq!(debugger.execute(&mut cpu_emu, "set reg pc 4"), "R0 = 0004");
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "next"),
+        "breakpoint at 0x000A <func>: ADD.W #1,r15"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "delete 0xA"),
+        "deleted breakpoint at 0x000A <func>"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "continue"),
+        "halted at 0x0008 <main+0x8>: JMP 0x0008"
+    );
+    assert_eq!(cpu_emu.regs[15], 4);
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "set reg r16 1"),
+        "unknown register r16"
+    );
+}
+
+#[test]
+fn test_debugger_watchpoints() {
+    let (mut cpu_emu, mut debugger) = setup();
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "watch 0x7FFE"),
+        "watchpoint 1: 7FFE..7FFF at 0x7FFE <func+0x7FF4>"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "continue"),
+        "watchpoint 1: write to 7FFE at pc 0004: 0000 -> 0006\nstopped at 0x000A <func>: ADD.W #1,r15"
+    );
+
+    // the RET reads the return address back, which a log watchpoint shows without stopping
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "unwatch 1"),
+        "deleted watchpoint 1"
+    );
+    debugger.execute(&mut cpu_emu, "rwatch 0x7FFE 2 log");
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "finish"),
+        "watchpoint 2: read of 7FFE at pc 000E: 0006\n0x0008 <main+0x8>: JMP 0x0008"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "unwatch 1"),
+        "no watchpoint 1"
+    );
+}
+
+#[test]
+fn test_debugger_reverse_execution() {
+    let (mut cpu_emu, mut debugger) = setup();
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "reverse-step"),
+        "no history to go back through, try record"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "record"),
+        "recording the last 1000000 steps"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "goto 5"),
+        "instruction 5: 0x0008 <main+0x8>: JMP 0x0008"
+    );
+    assert_eq!(cpu_emu.regs[15], 2);
+    debugger.execute(&mut cpu_emu, "break func");
+
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "reverse-step"),
+        "0x000E <func+0x4>: RET"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "reverse-continue"),
+        "breakpoint at 0x000A <func>: ADD.W #1,r15"
+    );
+    assert_eq!(cpu_emu.regs[15], 0);
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "reverse-continue"),
+        "reached the start of the history at 0x0000 <main>: MOV.W #32768,SP"
+    );
+    debugger.execute(&mut cpu_emu, "delete func");
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "goto 3"),
+        "instruction 3: 0x000C <func+0x2>: ADD.W #1,r15"
+    );
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "goto 1"),
+        "instruction 1: 0x0004 <main+0x4>: CALL #func"
+    );
+}
+
+#[test]
+fn test_debugger_backtrace() {
+    let (mut cpu_emu, mut debugger) = setup();
+
+    assert_eq!(debugger.execute(&mut cpu_emu, "bt"), "#0 0x0000 <main>");
+    for _ in 0..3 {
+        debugger.execute(&mut cpu_emu, "step");
+    }
+    assert_eq!(
+        debugger.execute(&mut cpu_emu, "backtrace"),
+        "#0 0x000C <func+0x2>\n#1 0x0004 <main+0x4>"
+    );
+}
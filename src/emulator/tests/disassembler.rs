@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::{
+    byte_generator::{generate_bytes, generate_bytes_and_symbols},
+    disassembler::disassemble_to_string,
+    emulator::{tests::convert_words_to_bytes, Emulator},
+    get_verbs::get_tokens,
+};
+
+#[test]
+fn test_disassemble_addr_modes() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x4303, //  MOV.W  #0,R3  (NOP)
+        0xD232, //  BIS.W  #8,SR
+        0x4392, 0x8A04, //  MOV.W  #1,&0x8A04
+        0x4F5E, 0x0004, //  MOV.B  4(R15),R14
+        0x4F35, //  MOV.W  @R15+,R5
+        0x93AE, 0xFFFE, //  CMP.W  #2,-2(R14)
+        0x1285, //  CALL  R5
+        0x1300, //  RETI
+        0x23FE, //  JNE  $-2
+        0x1380, // not an instruction
+    ];
+    let cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    let symbols = HashMap::new();
+
+    let mut addr = 0;
+    let mut lines = Vec::new();
+    while addr < 0x001E {
+        let (text, len) = disassemble_to_string(&cpu_emu.bus, addr, &symbols);
+        lines.push(text);
+        addr += len;
+    }
+    assert_eq!(
+        lines,
+        vec![
+            "MOV.W #32768,SP",
+            "NOP",
+            "BIS.W #8,SR",
+            "MOV.W #1,&0x8A04",
+            "MOV.B 4(r15),r14",
+            "MOV.W @r15+,r5",
+            "CMP.W #2,-2(r14)",
+            "CALL r5",
+            "RETI",
+            "JNE 0x0018",
+            ".word 0x1380",
+        ]
+    );
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let source = "
+main:
+        MOV.W     #5,r15
+        CALL      #func
+$C$L1:
+        SUB.W     #1,r15
+        JNE       $C$L1
+        MOV.B     &0x8A00,r14
+        MOV.W     r14,&0x8A04
+        JMP       main
+func:
+        PUSH.W    r10
+        MOV.W     2(SP),r10
+        ADD.W     @r10+,r15
+        XOR.B     #-1,r15
+        MOV.W     @SP+,r10
+        BR        #main
+        RET
+";
+    let (globals, lines) = get_tokens(source.to_owned());
+    let (bytes, symbols) = generate_bytes_and_symbols(globals, lines);
+    let cpu_emu = Emulator::new(&bytes);
+
+    // skip the stack setup and jump to main that the assembler adds
+    let mut addr = 0x0006;
+    let mut text = String::new();
+    while (addr as usize) < bytes.len() {
+        for (label, _) in symbols.iter().filter(|(_, a)| **a == addr) {
+            text.push_str(&format!("{}:\n", label));
+        }
+        let (line, len) = disassemble_to_string(&cpu_emu.bus, addr, &symbols);
+        text.push_str(&format!("        {}\n", line));
+        addr += len;
+    }
+    assert!(text.contains("CALL #func"));
+    assert!(text.contains("JNE $C$L1"));
+    assert!(text.contains("BR #main"));
+
+    let (globals, lines) = get_tokens(text);
+    assert_eq!(generate_bytes(globals, lines), bytes);
+}
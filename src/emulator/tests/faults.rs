@@ -0,0 +1,74 @@
+use crate::emulator::{fault::CpuFault, tests::convert_words_to_bytes, Emulator};
+
+#[test]
+fn test_illegal_opcodes() {
+    let instrs: Vec<u16> = vec![
+        0x0000, // not an instruction
+        0x1380, // single operand opcode 7
+        0x12F0, 0x0010, //  CALL.B  #0x0010
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::IllegalOpcode {
+            pc: 0x0000,
+            instr: 0x0000
+        })
+    );
+    // the faulting instruction is not retired
+    assert_eq!(cpu_emu.regs[0], 0x0000);
+    assert_eq!(cpu_emu.get_cycle_count(), 0);
+
+    cpu_emu.regs[0] = 0x0002;
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::IllegalOpcode {
+            pc: 0x0002,
+            instr: 0x1380
+        })
+    );
+    cpu_emu.regs[0] = 0x0004;
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::IllegalOpcode {
+            pc: 0x0004,
+            instr: 0x12F0
+        })
+    );
+}
+
+#[test]
+fn test_invalid_dst_addr_mode() {
+    let instrs: Vec<u16> = vec![
+        0x1013, //  RRC  #1
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::InvalidDstAddrMode {
+            pc: 0x0000,
+            instr: 0x1013
+        })
+    );
+}
+
+#[test]
+fn test_pc_faults() {
+    let mut cpu_emu = Emulator::new(&vec![]);
+
+    cpu_emu.regs[0] = 0x0101;
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::MisalignedFetch { pc: 0x0101 })
+    );
+
+    cpu_emu.bus.write_word(0xFFFE, 0x4303); //  NOP
+    cpu_emu.regs[0] = 0xFFFE;
+    assert_eq!(
+        cpu_emu.run_one_instr(),
+        Err(CpuFault::PcOverflow { pc: 0xFFFE })
+    );
+    assert_eq!(cpu_emu.regs[0], 0xFFFE);
+}
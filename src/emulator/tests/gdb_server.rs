@@ -0,0 +1,214 @@
+use std::io::{self, Cursor, Read, Write};
+
+use crate::emulator::{
+    gdb_server::{GdbConnection, GdbServer},
+    tests::convert_words_to_bytes,
+    Emulator,
+};
+
+struct MockConnection {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Read for MockConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MockConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl GdbConnection for MockConnection {
+    fn set_nonblocking(&self, _nonblocking: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn new_server(input: &[u8]) -> GdbServer<MockConnection> {
+    GdbServer::new(MockConnection {
+        input: Cursor::new(input.to_vec()),
+        output: Vec::new(),
+    })
+}
+
+#[test]
+fn test_gdb_regs_and_mem() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    let mut server = new_server(b"");
+
+    let regs = server.handle_packet(&mut cpu_emu, "g").unwrap();
+    assert_eq!(regs.len(), 16 * 8);
+    assert_eq!(&regs[..8], "00000000");
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "s").unwrap(), "S05");
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "p0").unwrap(),
+        "04000000"
+    );
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "p1").unwrap(),
+        "00800000"
+    );
+
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "Pf=34120000").unwrap(),
+        "OK"
+    );
+    assert_eq!(cpu_emu.regs[15], 0x1234);
+
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "m0,4").unwrap(),
+        "31400080"
+    );
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "M8a04,2:cdab").unwrap(),
+        "OK"
+    );
+    assert_eq!(cpu_emu.get_led_output(), 0xABCD);
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "m10000,2").unwrap(),
+        "E01"
+    );
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "m0,10000").unwrap(),
+        "E01"
+    );
+    assert_eq!(server.handle_packet(&mut cpu_emu, "\u{e9}0,2").unwrap(), "");
+
+    assert_eq!(
+        server
+            .handle_packet(&mut cpu_emu, "vMustReplyEmpty")
+            .unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn test_gdb_breakpoints() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x531F, //  ADD.W  #1,R15
+        0x3FFE, //  JMP  $-2
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    let mut server = new_server(b"");
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "Z0,4,2").unwrap(), "OK");
+    assert_eq!(server.handle_packet(&mut cpu_emu, "c").unwrap(), "S05");
+    assert_eq!(cpu_emu.regs[0], 0x0004);
+    assert_eq!(cpu_emu.regs[15], 0);
+
+    // continuing from a breakpoint runs past it
+    assert_eq!(server.handle_packet(&mut cpu_emu, "c").unwrap(), "S05");
+    assert_eq!(cpu_emu.regs[0], 0x0004);
+    assert_eq!(cpu_emu.regs[15], 1);
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "z0,4,2").unwrap(), "OK");
+    assert_eq!(server.handle_packet(&mut cpu_emu, "Z1,6,2").unwrap(), "OK");
+    assert_eq!(server.handle_packet(&mut cpu_emu, "c").unwrap(), "S05");
+    assert_eq!(cpu_emu.regs[0], 0x0006);
+    assert_eq!(cpu_emu.regs[15], 2);
+
+    // unknown breakpoint types are not supported
+    assert_eq!(server.handle_packet(&mut cpu_emu, "Z5,200,2").unwrap(), "");
+}
+
+#[test]
+fn test_gdb_watchpoints() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x43D2, 0x8A04, //  MOV.B  #1,&0x8A04
+        0x421F, 0x8A04, //  MOV.W  &0x8A04,R15
+        0x3FFF, //  JMP  $
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    let mut server = new_server(b"");
+
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "Z2,8a04,1").unwrap(),
+        "OK"
+    );
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "Z3,8a04,2").unwrap(),
+        "OK"
+    );
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "c").unwrap(),
+        "T05watch:8a04;"
+    );
+    assert_eq!(cpu_emu.regs[0], 0x0008);
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "s").unwrap(),
+        "T05rwatch:8a04;"
+    );
+
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "z3,8a04,2").unwrap(),
+        "OK"
+    );
+    assert_eq!(server.handle_packet(&mut cpu_emu, "Z4,0,0").unwrap(), "E01");
+}
+
+#[test]
+fn test_gdb_reverse_execution() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x531F, //  ADD.W  #1,R15
+        0x531F, //  ADD.W  #1,R15
+        0x3FFF, //  JMP  $
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_journal_capacity(100);
+    let mut server = new_server(b"");
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "Z0,4,2").unwrap(), "OK");
+    for _ in 0..3 {
+        assert_eq!(server.handle_packet(&mut cpu_emu, "s").unwrap(), "S05");
+    }
+    assert_eq!(cpu_emu.regs[15], 2);
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "bs").unwrap(), "S05");
+    assert_eq!(cpu_emu.regs[15], 1);
+    assert_eq!(server.handle_packet(&mut cpu_emu, "bc").unwrap(), "S05");
+    assert_eq!((cpu_emu.regs[0], cpu_emu.regs[15]), (0x0004, 0));
+    assert_eq!(
+        server.handle_packet(&mut cpu_emu, "bc").unwrap(),
+        "T05replaylog:begin;"
+    );
+    assert_eq!(cpu_emu.regs[0], 0x0000);
+}
+
+#[test]
+fn test_gdb_faults() {
+    let instrs: Vec<u16> = vec![
+        0x0000, // not an instruction
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    let mut server = new_server(b"");
+
+    assert_eq!(server.handle_packet(&mut cpu_emu, "c").unwrap(), "S04");
+    assert_eq!(server.handle_packet(&mut cpu_emu, "s").unwrap(), "S04");
+    assert_eq!(server.handle_packet(&mut cpu_emu, "s1").unwrap(), "S0a");
+}
+
+#[test]
+fn test_gdb_packets() {
+    let mut cpu_emu = Emulator::new(&Vec::new());
+    // a packet with a bad checksum is nacked and ignored
+    let mut server = new_server(b"+$?#00$p2#a2+$D#44");
+    server.run(&mut cpu_emu).unwrap();
+
+    let output = String::from_utf8(server.into_connection().output).unwrap();
+    assert_eq!(output, "-+$00000000#80+$OK#9a");
+}
@@ -0,0 +1,77 @@
+use crate::emulator::{
+    interrupts::{BTNS_VECTOR, TIMER_VECTOR},
+    peripherals::{
+        buttons::BTNS_IE_ADDR,
+        timer::{TIMER_CTL_ADDR, TIMER_CTL_ENABLE, TIMER_CTL_IE, TIMER_PERIOD_ADDR},
+    },
+    tests::convert_words_to_bytes,
+    Emulator,
+};
+
+#[test]
+fn test_btn_interrupt_and_reti() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0xD232, //  EINT
+        0x3FFF, //  JMP  $
+        // interrupt handler at 0x0008:
+        0x43C2, 0x8A07, //  MOV.B  #0,&0x8A07
+        0x403F, 0x1234, //  MOV.W  #0x1234,R15
+        0x1300, //  RETI
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_interrupt_vector(BTNS_VECTOR, 0x0006);
+    cpu_emu.bus.write_byte(BTNS_IE_ADDR, 0x01);
+
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0006);
+    assert_eq!(cpu_emu.regs[2], 0x0008);
+
+    cpu_emu.set_button_states(0x01);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0008);
+    assert_eq!(cpu_emu.regs[1], 0x7FFC);
+    assert_eq!(cpu_emu.regs[2], 0x0000);
+    assert_eq!(cpu_emu.bus.peek_byte(0x7FFE), 0x06);
+    assert_eq!(cpu_emu.bus.peek_byte(0x7FFC), 0x08);
+
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0x1234);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0006);
+    assert_eq!(cpu_emu.regs[1], 0x8000);
+    assert_eq!(cpu_emu.regs[2], 0x0008);
+
+    // the handler cleared the flag, so the loop keeps running
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0006);
+    assert_eq!(cpu_emu.regs[1], 0x8000);
+}
+
+#[test]
+fn test_interrupts_masked_without_gie() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x3FFF, //  JMP  $
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_interrupt_vector(TIMER_VECTOR, 0x0100);
+    cpu_emu
+        .bus
+        .write_byte(TIMER_CTL_ADDR, TIMER_CTL_ENABLE | TIMER_CTL_IE);
+    cpu_emu.bus.write_word(TIMER_PERIOD_ADDR, 2);
+
+    for _ in 0..10 {
+        cpu_emu.run_one_instr().unwrap();
+        assert_eq!(cpu_emu.regs[1], 0x8000);
+    }
+    assert_eq!(cpu_emu.regs[0], 0x0004);
+
+    cpu_emu.regs[2] |= 0x0008;
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0102);
+    assert_eq!(cpu_emu.regs[1], 0x7FFC);
+}
@@ -0,0 +1,98 @@
+use crate::emulator::{
+    tests::convert_words_to_bytes,
+    watchpoints::{WatchAction, WatchKind},
+    Emulator,
+};
+
+fn new_emulator() -> Emulator {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x40B2, 0x0064, 0x8A0A, //  MOV.W  #100,&0x8A0A
+        0x43D2, 0x8A08, //  MOV.B  #1,&0x8A08
+        0x43D2, 0x8A04, //  MOV.B  #1,&0x8A04
+        0x531F, //  ADD.W  #1,R15
+        0x120F, //  PUSH.W  R15
+        0x3FFF, //  JMP  $
+    ];
+    Emulator::new(&convert_words_to_bytes(instrs))
+}
+
+#[test]
+fn test_step_back_restores_state() {
+    let mut cpu_emu = new_emulator();
+    cpu_emu.set_journal_capacity(100);
+    // nothing has run yet
+    assert!(!cpu_emu.step_back());
+
+    for _ in 0..7 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.get_instr_index(), 7);
+    assert_eq!(cpu_emu.bus.peek_word(0x7FFE), 1);
+    assert_eq!(cpu_emu.get_led_output(), 1);
+    assert_ne!(cpu_emu.bus.peek_word(0x8A0C), 0); // the timer is counting
+
+    for _ in 0..7 {
+        assert!(cpu_emu.step_back());
+    }
+    assert!(!cpu_emu.step_back());
+    assert_eq!(cpu_emu.get_instr_index(), 0);
+    assert_eq!(cpu_emu.regs, [0; 16]);
+    assert_eq!(cpu_emu.get_cycle_count(), 0);
+    assert_eq!(cpu_emu.bus.peek_word(0x7FFE), 0);
+    assert_eq!(cpu_emu.get_led_output(), 0);
+    assert_eq!(cpu_emu.bus.peek_word(0x8A08), 0);
+    assert_eq!(cpu_emu.bus.peek_word(0x8A0A), 0);
+    assert_eq!(cpu_emu.bus.peek_word(0x8A0C), 0);
+}
+
+#[test]
+fn test_journal_capacity_and_rewind() {
+    let mut cpu_emu = new_emulator();
+    cpu_emu.set_journal_capacity(3);
+    for _ in 0..7 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    let cycle_count = cpu_emu.get_cycle_count();
+    assert_eq!(cpu_emu.get_journal_len(), 3);
+
+    // only the last 3 instructions can be undone
+    assert!(!cpu_emu.rewind_to(3));
+    assert!(!cpu_emu.rewind_to(8));
+    assert_eq!(cpu_emu.get_instr_index(), 7);
+    assert!(cpu_emu.rewind_to(4));
+    assert_eq!(cpu_emu.regs[0], 0x0012);
+    assert_eq!(cpu_emu.regs[15], 0);
+
+    // running forwards again ends up in the same place
+    for _ in 0..3 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.regs[0], 0x0016);
+    assert_eq!(cpu_emu.regs[15], 1);
+    assert_eq!(cpu_emu.get_cycle_count(), cycle_count);
+
+    cpu_emu.set_journal_capacity(0);
+    assert!(!cpu_emu.step_back());
+}
+
+#[test]
+fn test_step_back_to_watchpoint() {
+    let mut cpu_emu = new_emulator();
+    cpu_emu.set_journal_capacity(100);
+    for _ in 0..7 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+
+    // going backwards, the watchpoint triggers when the write to the LEDs is undone
+    let id = cpu_emu.add_watchpoint(0x8A04..=0x8A04, WatchKind::Write, WatchAction::Break);
+    let mut hits = Vec::new();
+    while hits.is_empty() && cpu_emu.step_back() {
+        hits = cpu_emu.take_watch_hits();
+    }
+    assert_eq!(cpu_emu.regs[0], 0x000E);
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].id, hits[0].pc, hits[0].addr), (id, 0x000E, 0x8A04));
+    assert_eq!((hits[0].old_value, hits[0].new_value), (0x00, 0x01));
+    assert_eq!(cpu_emu.get_led_output(), 0);
+}
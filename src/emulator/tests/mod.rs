@@ -1,7 +1,37 @@
 #[cfg(test)]
 pub mod call;
 #[cfg(test)]
+pub mod coverage;
+#[cfg(test)]
+pub mod cycles;
+#[cfg(test)]
+pub mod debug_info;
+#[cfg(test)]
+pub mod debugger;
+#[cfg(test)]
+pub mod disassembler;
+#[cfg(test)]
+pub mod faults;
+#[cfg(test)]
+pub mod gdb_server;
+#[cfg(test)]
+pub mod interrupts;
+#[cfg(test)]
+pub mod journal;
+#[cfg(test)]
+pub mod peripherals;
+#[cfg(test)]
+pub mod profiler;
+#[cfg(test)]
+pub mod snapshot;
+#[cfg(test)]
+pub mod test_byte_instrs;
+#[cfg(test)]
 pub mod test_double_operand_instrs;
+#[cfg(test)]
+pub mod tracer;
+#[cfg(test)]
+pub mod watchpoints;
 
 #[cfg(test)]
 fn convert_words_to_bytes(words: Vec<u16>) -> Vec<u8> {
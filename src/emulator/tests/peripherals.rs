@@ -0,0 +1,74 @@
+use std::any::Any;
+use std::ops::RangeInclusive;
+
+use crate::emulator::{bus::Peripheral, tests::convert_words_to_bytes, Emulator};
+
+struct ReadCounter {
+    num_reads: u8,
+    last_write: u8,
+}
+
+impl Peripheral for ReadCounter {
+    fn get_addr_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        vec![0x9000..=0x9000]
+    }
+    fn peek_byte(&self, _addr: u16) -> u8 {
+        self.num_reads
+    }
+    fn read_byte(&mut self, _addr: u16) -> u8 {
+        self.num_reads += 1;
+        self.num_reads
+    }
+    fn write_byte(&mut self, _addr: u16, value: u8) {
+        self.last_write = value;
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[test]
+fn test_custom_peripheral() {
+    let instrs: Vec<u16> = vec![
+        0x425F, 0x9000, //  MOV.B  &0x9000,R15
+        0x425F, 0x9000, //  MOV.B  &0x9000,R15
+        0x40F2, 0x00AB, 0x9000, //  MOV.B  #0xAB,&0x9000
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.register_peripheral(Box::new(ReadCounter {
+        num_reads: 0,
+        last_write: 0,
+    }));
+
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 1);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 2);
+    assert_eq!(cpu_emu.bus.peek_byte(0x9000), 2);
+    cpu_emu.run_one_instr().unwrap();
+    let counter = cpu_emu.bus.get_peripheral::<ReadCounter>().unwrap();
+    assert_eq!(counter.last_write, 0xAB);
+    assert_eq!(counter.num_reads, 2);
+}
+
+#[test]
+fn test_builtin_peripherals() {
+    let instrs: Vec<u16> = vec![
+        0x421F, 0x8A00, //  MOV.W  &0x8A00,R15
+        0x40B2, 0x1234, 0x8A04, //  MOV.W  #0x1234,&0x8A04
+        0x43A2, 0x8000, //  MOV.W  #2,&0x8000
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_switch_states(0xA5A5);
+
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0xA5A5);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.get_led_output(), 0x1234);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.get_gfx_buffer()[0], 0x02);
+    assert_eq!(cpu_emu.get_gfx_buffer()[1], 0x00);
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::emulator::{
+    interrupts::TIMER_VECTOR, profiler::Profiler, tests::convert_words_to_bytes, Emulator,
+};
+
+fn run_profiled(num_instrs: usize) -> Profiler {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x12B0, 0x000C, //  CALL  #func
+        0x12B0, 0x000C, //  CALL  #func
+        0x3FFF, //  JMP  $
+        // func:
+        0x12B0, 0x0012, //  CALL  #leaf
+        0x4130, //  RET
+        // leaf:
+        0x531F, //  ADD.W  #1,R15
+        0x4130, //  RET
+    ];
+    let symbols = HashMap::from([
+        ("main".to_owned(), 0x0000),
+        ("func".to_owned(), 0x000E),
+        ("leaf".to_owned(), 0x0014),
+        ("$C$L1".to_owned(), 0x0014),
+    ]);
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_profiler(Some(Profiler::new(&symbols)));
+    for _ in 0..num_instrs {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.regs[15], 2);
+    cpu_emu.set_profiler(None).unwrap()
+}
+
+#[test]
+fn test_profiler_folded_stacks() {
+    let profiler = run_profiled(13);
+    let mut out = Vec::new();
+    profiler.write_folded_stacks(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "main 16\nmain;func 16\nmain;func;leaf 8\n"
+    );
+}
+
+#[test]
+fn test_profiler_flat_profile() {
+    let profiler = run_profiled(13);
+    let mut out = Vec::new();
+    profiler.write_flat_profile(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "function                    calls     instrs  self cycles    self total cycles   total",
+            "func                            2          4           16  40.00%           24  60.00%",
+            "main                            0          5           16  40.00%           40 100.00%",
+            "leaf                            2          4            8  20.00%            8  20.00%",
+        ]
+    );
+}
+
+#[test]
+fn test_profiler_interrupts() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0xD232, //  BIS.W  #8,SR
+        0x3FFF, //  JMP  $
+        // isr:
+        0x531E, //  ADD.W  #1,R14
+        0x1300, //  RETI
+    ];
+    let symbols = HashMap::from([("main".to_owned(), 0x0000), ("isr".to_owned(), 0x0008)]);
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_interrupt_vector(TIMER_VECTOR, 0x0006);
+    cpu_emu.set_profiler(Some(Profiler::new(&symbols)));
+    for _ in 0..3 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    cpu_emu.request_interrupt(TIMER_VECTOR);
+    for _ in 0..3 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    assert_eq!(cpu_emu.regs[0], 0x0006);
+    assert_eq!(cpu_emu.regs[14], 1);
+
+    let mut out = Vec::new();
+    let profiler = cpu_emu.set_profiler(None).unwrap();
+    profiler.write_folded_stacks(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "main 5\nmain;isr 12\n");
+}
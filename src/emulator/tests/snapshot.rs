@@ -0,0 +1,75 @@
+use crate::emulator::{tests::convert_words_to_bytes, Emulator};
+
+#[test]
+fn test_snapshot_round_trip() {
+    let instrs: Vec<u16> = vec![
+        0x403F, 0x8000, //  MOV.W  #0x8000,R15
+        0x40B2, 0x1234, 0x8A04, //  MOV.W  #0x1234,&0x8A04
+        0x531F, //  INC.W  R15
+        0x4F8F, 0x0000, //  MOV.W  R15,0(R15)
+        0x3FFC, //  JMP  $-6
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_button_states(0x04);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    let snapshot = cpu_emu.save_snapshot();
+    let regs = cpu_emu.regs;
+    let cycle_count = cpu_emu.get_cycle_count();
+    let instr_index = cpu_emu.get_instr_index();
+
+    for _ in 0..10 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    cpu_emu.set_button_states(0x00);
+    assert_ne!(cpu_emu.regs, regs);
+
+    cpu_emu.load_snapshot(&snapshot).unwrap();
+    assert_eq!(cpu_emu.regs, regs);
+    assert_eq!(cpu_emu.get_cycle_count(), cycle_count);
+    assert_eq!(cpu_emu.get_instr_index(), instr_index);
+    assert_eq!(cpu_emu.get_led_output(), 0x1234);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8A02), 0x04);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8003), 0x00);
+
+    // a restored emulator runs exactly like the original one did
+    let mut restored_emu = Emulator::new(&vec![]);
+    restored_emu.load_snapshot(&snapshot).unwrap();
+    for _ in 0..10 {
+        cpu_emu.run_one_instr().unwrap();
+        restored_emu.run_one_instr().unwrap();
+        assert_eq!(cpu_emu.regs, restored_emu.regs);
+    }
+    assert_eq!(cpu_emu.get_gfx_buffer(), restored_emu.get_gfx_buffer());
+    assert_eq!(cpu_emu.get_cycle_count(), restored_emu.get_cycle_count());
+    assert_eq!(cpu_emu.get_instr_index(), restored_emu.get_instr_index());
+}
+
+#[test]
+fn test_invalid_snapshot() {
+    let mut cpu_emu = Emulator::new(&vec![]);
+    assert!(cpu_emu.load_snapshot(b"not a snapshot").is_err());
+
+    let mut snapshot = cpu_emu.save_snapshot();
+    snapshot.truncate(snapshot.len() - 1);
+    assert!(cpu_emu.load_snapshot(&snapshot).is_err());
+
+    // such as the state of a peripheral that this build doesn't have
+    let mut snapshot = cpu_emu.save_snapshot();
+    snapshot.push(0);
+    let error = cpu_emu.load_snapshot(&snapshot).unwrap_err();
+    assert_eq!(error.to_string(), "trailing data in snapshot");
+
+    // a snapshot that fails part of the way through leaves the emulator as it was
+    let mut other_emu = Emulator::new(&convert_words_to_bytes(vec![0x4035, 0x0007])); // MOV #7,R5
+    other_emu.run_one_instr().unwrap();
+    other_emu.set_button_states(0x04);
+    let mut snapshot = other_emu.save_snapshot();
+    snapshot.truncate(snapshot.len() - 1);
+    let regs = cpu_emu.regs;
+    assert!(cpu_emu.load_snapshot(&snapshot).is_err());
+    assert_eq!(cpu_emu.regs, regs);
+    assert_eq!(cpu_emu.bus.peek_word(0x0000), 0);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8A02), 0);
+}
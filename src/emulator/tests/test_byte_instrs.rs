@@ -0,0 +1,75 @@
+use crate::emulator::{tests::convert_words_to_bytes, Emulator};
+
+#[test]
+fn test_mov_byte_auto_inc() {
+    let instrs: Vec<u16> = vec![
+        0x403F, 0x8000, //  MOV.W  #0x8000,R15
+        0x40BF, 0x1234, 0x0000, //  MOV.W  #0x1234,0(R15)
+        0x4F7E, //  MOV.B  @R15+,R14
+        0x4F6D, //  MOV.B  @R15,R13
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    cpu_emu.regs[14] = 0xFFFF;
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0xC);
+    assert_eq!(cpu_emu.regs[14], 0x0034);
+    assert_eq!(cpu_emu.regs[15], 0x8001);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[13], 0x0012);
+}
+
+#[test]
+fn test_mov_byte_to_memory() {
+    let instrs: Vec<u16> = vec![
+        0x403F, 0x8000, //  MOV.W  #0x8000,R15
+        0x40BF, 0x1234, 0x0000, //  MOV.W  #0x1234,0(R15)
+        0x40FF, 0x00AB, 0x0001, //  MOV.B  #0xAB,1(R15)
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x10);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8000), 0x34);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8001), 0xAB);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8002), 0x00);
+}
+
+#[test]
+fn test_add_byte_flags() {
+    let instrs: Vec<u16> = vec![
+        0x403E, 0x12FF, //  MOV.W  #0x12FF,R14
+        0x535E, //  ADD.B  #1,R14
+        // assert status of flags here, should have Z, C set
+        0x403E, 0x017F, //  MOV.W  #0x017F,R14
+        0x535E, //  ADD.B  #1,R14
+                // assert status of flags here, should have N, V set
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[14], 0x0000);
+    assert_eq!(cpu_emu.regs[2], 0x0003);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[14], 0x0080);
+    assert_eq!(cpu_emu.regs[2], 0x0104);
+}
+
+#[test]
+fn test_byte_auto_inc_on_sp() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x7FFE, //  MOV.W  #0x7FFE,SP
+        0x417F, //  MOV.B  @SP+,R15
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[1], 0x8000);
+}
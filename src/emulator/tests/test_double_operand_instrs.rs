@@ -5,7 +5,7 @@ fn test_mov_imm() {
     let instrs: Vec<u16> = vec![0x4031, 0x8000]; //  MOV.W  #0x8000,SP
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
 
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 4);
     assert_eq!(cpu_emu.regs[1], 0x8000);
 }
@@ -18,10 +18,10 @@ fn test_mov_between_regs() {
 
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
 
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 4);
     assert_eq!(cpu_emu.regs[15], 0x8000);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 6);
     assert_eq!(cpu_emu.regs[15], 0x8000);
     assert_eq!(cpu_emu.regs[14], 0x8000);
@@ -34,12 +34,12 @@ fn test_indirect_addressing() {
     //  MOV.W  #0x1234,@R15
 
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 4);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xA);
-    assert_eq!(cpu_emu.mem[0x8000], 0x34);
-    assert_eq!(cpu_emu.mem[0x8001], 0x12);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8000), 0x34);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8001), 0x12);
 }
 
 #[test]
@@ -50,13 +50,13 @@ fn test_indirect_auto_inc_addressing() {
     //  MOV.W  @R15+,R14
 
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 4);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xA);
-    assert_eq!(cpu_emu.mem[0x8000], 0x34);
-    assert_eq!(cpu_emu.mem[0x8001], 0x12);
-    cpu_emu.run_one_instr();
+    assert_eq!(cpu_emu.bus.peek_byte(0x8000), 0x34);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8001), 0x12);
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xC);
     assert_eq!(cpu_emu.regs[15], 0x8002);
     assert_eq!(cpu_emu.regs[14], 0x1234);
@@ -73,15 +73,15 @@ fn test_abs_addressing() {
     ];
 
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x4);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xA);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xE);
     assert_eq!(cpu_emu.regs[14], 0x1234);
-    cpu_emu.run_one_instr();
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x18);
     assert_eq!(cpu_emu.regs[14], 0x2256);
 }
@@ -96,24 +96,24 @@ fn test_indexed_addressing() {
         0x43BF, 0x0000, // MOV.W #-1, 0(R15)
     ];
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x4);
     assert_eq!(cpu_emu.regs[15], 0x8000);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0xA);
-    assert_eq!(cpu_emu.mem[0x8002], 0x34);
-    assert_eq!(cpu_emu.mem[0x8003], 0x12);
-    cpu_emu.run_one_instr();
+    assert_eq!(cpu_emu.bus.peek_byte(0x8002), 0x34);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8003), 0x12);
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x10);
-    assert_eq!(cpu_emu.mem[0x8004], 0x68);
-    assert_eq!(cpu_emu.mem[0x8005], 0x24);
-    cpu_emu.run_one_instr();
+    assert_eq!(cpu_emu.bus.peek_byte(0x8004), 0x68);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8005), 0x24);
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[0], 0x14);
-    assert_eq!(cpu_emu.mem[0x8000], 0xFF);
-    assert_eq!(cpu_emu.mem[0x8001], 0xFF);
-    cpu_emu.run_one_instr();
-    assert_eq!(cpu_emu.mem[0x8000], 0xFF);
-    assert_eq!(cpu_emu.mem[0x8001], 0xFF);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8000), 0xFF);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8001), 0xFF);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.bus.peek_byte(0x8000), 0xFF);
+    assert_eq!(cpu_emu.bus.peek_byte(0x8001), 0xFF);
 }
 
 #[test]
@@ -130,13 +130,62 @@ fn test_sub_instruction() {
     ];
 
     let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
-    cpu_emu.run_one_instr();
-    cpu_emu.run_one_instr();
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[2], 0x0003);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[2], 0x0004);
-    cpu_emu.run_one_instr();
+    cpu_emu.run_one_instr().unwrap();
     assert_eq!(cpu_emu.regs[2], 0x01);
     assert_eq!(cpu_emu.regs[14], 1);
 }
+
+#[test]
+fn test_dadd_instruction() {
+    let instrs: Vec<u16> = vec![
+        0x403F, 0x0999, //  MOV.W  #0x0999,R15
+        0x431E, //  MOV.W  #1,R14
+        0xAE0F, //  DADD.W R14,R15
+        // assert status of flags here, should have none set
+        0x403F, 0x9999, //  MOV.W  #0x9999,R15
+        0xAE0F, //  DADD.W R14,R15
+        // assert status of flags here, should have Z, C set
+        0x403F, 0x1299, //  MOV.W  #0x1299,R15
+        0xAE4F, //  DADD.B R14,R15
+                // carry is still set, so 0x99 + 0x01 + 1 = 0x01 with C set
+    ];
+
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0x1000);
+    assert_eq!(cpu_emu.regs[2], 0x0000);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0x0000);
+    assert_eq!(cpu_emu.regs[2], 0x0003);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0x0001);
+    assert_eq!(cpu_emu.regs[2], 0x0001);
+}
+
+#[test]
+fn test_access_and_jump_to_addr_0() {
+    let instrs: Vec<u16> = vec![
+        0x421F, 0x0000, //  MOV.W  &0x0000,R15
+        0x40B2, 0x4303, 0x0000, //  MOV.W  #0x4303,&0x0000
+        0x3FFA, //  JMP  0x0000
+    ];
+
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[15], 0x421F);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.bus.peek_byte(0x0000), 0x03);
+    assert_eq!(cpu_emu.bus.peek_byte(0x0001), 0x43);
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0000);
+}
@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::emulator::{
+    interrupts::TIMER_VECTOR,
+    tests::convert_words_to_bytes,
+    tracer::{TraceFormat, Tracer},
+    Emulator,
+};
+
+#[derive(Clone, Default)]
+pub(super) struct SharedBuffer(pub(super) Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run_traced(format: TraceFormat, is_verbose: bool) -> Vec<String> {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x43D2, 0x8A04, //  MOV.B  #1,&0x8A04
+        0x831F, //  SUB.W  #1,R15
+        0xD232, //  BIS.W  #8,SR
+        0x3FFF, //  JMP  $
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_interrupt_vector(TIMER_VECTOR, 0x000C);
+    let buffer = SharedBuffer::default();
+    let tracer = Tracer::new(Box::new(buffer.clone()), format, is_verbose, HashMap::new());
+    cpu_emu.set_tracer(Some(tracer));
+
+    for _ in 0..4 {
+        cpu_emu.run_one_instr().unwrap();
+    }
+    cpu_emu.request_interrupt(TIMER_VECTOR);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.set_tracer(None).unwrap().finish().unwrap();
+
+    let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    output.lines().map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_text_trace() {
+    let lines: Vec<String> = run_traced(TraceFormat::Text, false)
+        .iter()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "0 0000 MOV.W #32768,SP SP=8000",
+            "2 0004 MOV.B #1,&0x8A04 [8A04]=01",
+            "6 0008 SUB.W #1,r15 r15=FFFF N=1",
+            "7 000A BIS.W #8,SR GIE=1",
+            "8 000C interrupt 9 SP=7FFC GIE=0 [7FFE]=000C [7FFC]=000C",
+        ]
+    );
+}
+
+#[test]
+fn test_verbose_json_trace() {
+    let lines = run_traced(TraceFormat::JsonLines, true);
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].starts_with(
+        "{\"type\":\"instr\",\"cycle\":2,\"pc\":4,\"instr\":\"MOV.B #1,&0x8A04\",\"regs\":{},\"flags\":{},\"mem_writes\":[{\"addr\":35332,\"value\":1,\"size\":1}],\"cycles\":4,"
+    ));
+    assert!(lines[2].contains("\"regs\":{\"r15\":65535},\"flags\":{\"N\":true}"));
+    assert!(lines[2].contains("\"stages\":{\"0\":{\"curr_instr\":33567,"));
+    assert!(lines[2].contains("\"4\":{\"result\":65535,\"new_cf\":false,"));
+    assert_eq!(
+        lines[4],
+        "{\"type\":\"interrupt\",\"cycle\":8,\"pc\":12,\"vector\":9,\"regs\":{\"SP\":32764},\"flags\":{\"GIE\":false},\"mem_writes\":[{\"addr\":32766,\"value\":12,\"size\":2},{\"addr\":32764,\"value\":12,\"size\":2}]}"
+    );
+}
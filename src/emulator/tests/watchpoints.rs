@@ -0,0 +1,102 @@
+use crate::emulator::{
+    interrupts::TIMER_VECTOR,
+    tests::convert_words_to_bytes,
+    watchpoints::{WatchAction, WatchHit, WatchKind},
+    Emulator,
+};
+
+fn new_emulator() -> Emulator {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0x43D2, 0x8A04, //  MOV.B  #1,&0x8A04
+        0x421F, 0x8A04, //  MOV.W  &0x8A04,R15
+        0x120F, //  PUSH.W  R15
+        0x3FFF, //  JMP  $
+    ];
+    Emulator::new(&convert_words_to_bytes(instrs))
+}
+
+#[test]
+fn test_write_watchpoint_breaks() {
+    let mut cpu_emu = new_emulator();
+    let id = cpu_emu.add_watchpoint(0x8A04..=0x8A04, WatchKind::Write, WatchAction::Break);
+
+    // run_cycles stops right after the instruction that wrote to the LEDs
+    cpu_emu.run_cycles(1000).unwrap();
+    assert_eq!(cpu_emu.regs[0], 0x0008);
+    assert_eq!(
+        cpu_emu.take_watch_hits(),
+        vec![WatchHit {
+            id,
+            action: WatchAction::Break,
+            pc: 0x0004,
+            addr: 0x8A04,
+            is_write: true,
+            is_byte: true,
+            old_value: 0x00,
+            new_value: 0x01,
+        }]
+    );
+
+    // the read that follows does not trigger a write watchpoint
+    cpu_emu.run_one_instr().unwrap();
+    assert_eq!(cpu_emu.take_watch_hits(), vec![]);
+
+    assert!(cpu_emu.remove_watchpoint(id));
+    assert!(!cpu_emu.remove_watchpoint(id));
+}
+
+#[test]
+fn test_read_and_access_watchpoints() {
+    let mut cpu_emu = new_emulator();
+    // a word access to 0x8A04 also covers 0x8A05
+    let read_id = cpu_emu.add_watchpoint(0x8A05..=0x8A05, WatchKind::Read, WatchAction::Log);
+    let stack_id = cpu_emu.add_watchpoint(0x7F00..=0x7FFF, WatchKind::Access, WatchAction::Log);
+
+    // log watchpoints don't stop run_cycles
+    cpu_emu.run_cycles(1000).unwrap();
+    let hits = cpu_emu.take_watch_hits();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(
+        (hits[0].id, hits[0].pc, hits[0].addr, hits[0].is_write),
+        (read_id, 0x0008, 0x8A04, false)
+    );
+    assert_eq!(hits[0].new_value, 0x0001);
+    assert_eq!(
+        (hits[1].id, hits[1].pc, hits[1].addr, hits[1].is_write),
+        (stack_id, 0x000C, 0x7FFE, true)
+    );
+    assert_eq!((hits[1].old_value, hits[1].new_value), (0x0000, 0x0001));
+
+    // instruction fetches are not reads
+    let code_id = cpu_emu.add_watchpoint(0x0000..=0x00FF, WatchKind::Read, WatchAction::Break);
+    cpu_emu.run_one_instr().unwrap();
+    assert!(cpu_emu.take_watch_hits().iter().all(|h| h.id != code_id));
+}
+
+#[test]
+fn test_watchpoint_on_interrupt_entry() {
+    let instrs: Vec<u16> = vec![
+        0x4031, 0x8000, //  MOV.W  #0x8000,SP
+        0xD232, //  BIS.W  #8,SR
+        0x3FFF, //  JMP  $
+    ];
+    let mut cpu_emu = Emulator::new(&convert_words_to_bytes(instrs));
+    cpu_emu.set_interrupt_vector(TIMER_VECTOR, 0x0004);
+    cpu_emu.run_one_instr().unwrap();
+    cpu_emu.run_one_instr().unwrap();
+
+    // the pushes of PC and SR are reported at the interrupted instruction
+    cpu_emu.add_watchpoint(0x7FFC..=0x7FFF, WatchKind::Write, WatchAction::Break);
+    cpu_emu.request_interrupt(TIMER_VECTOR);
+    cpu_emu.run_one_instr().unwrap();
+    let hits: Vec<(u16, u16, u16)> = cpu_emu
+        .take_watch_hits()
+        .iter()
+        .map(|h| (h.pc, h.addr, h.new_value))
+        .collect();
+    assert_eq!(
+        hits,
+        vec![(0x0006, 0x7FFE, 0x0006), (0x0006, 0x7FFC, 0x0008)]
+    );
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::disassembler::disassemble_to_string;
+use crate::emulator::bus::Bus;
+use crate::emulator::fault::CpuFault;
+use crate::source_map::SourceMap;
+
+// Records what the emulator does, one line per retired instruction, interrupt, or fault.
+// In verbose mode the latch values written by every stage are recorded as well.
+// Given a source map of a C program, text traces show each C line as it is reached, and JSON
+// traces give the C line of every instruction.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Text,
+    JsonLines,
+}
+
+// the stages that write latches, in the order that they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Stage0,
+    Stage1,
+    Stage2a,
+    Stage2b,
+    Stage3a,
+    Stage3b,
+    Stage4,
+    Stage5a,
+}
+
+impl Stage {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Stage::Stage0 => "0",
+            Stage::Stage1 => "1",
+            Stage::Stage2a => "2a",
+            Stage::Stage2b => "2b",
+            Stage::Stage3a => "3a",
+            Stage::Stage3b => "3b",
+            Stage::Stage4 => "4",
+            Stage::Stage5a => "5a",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LatchValue {
+    Word(u16),
+    Byte(u8),
+    Bool(bool),
+    Flag(Option<bool>), // the new_*f latches, None if the flag is left unchanged
+}
+
+// the SR bits that are shown as flags rather than as a change to SR
+const FLAG_BITS: [(&str, u16); 6] = [
+    ("C", 0x0001),
+    ("Z", 0x0002),
+    ("N", 0x0004),
+    ("V", 0x0100),
+    ("GIE", 0x0008),
+    ("CPUOFF", 0x0010),
+];
+
+const REG_NAMES: [&str; 16] = [
+    "PC", "SP", "SR", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "r13", "r14",
+    "r15",
+];
+
+pub struct Tracer {
+    out: Box<dyn Write>,
+    format: TraceFormat,
+    is_verbose: bool,
+    symbols: HashMap<String, u16>,
+    source_map: Option<SourceMap>,
+    prev_c_location: Option<String>,
+    // state of the instruction being traced
+    instr_text: String,
+    stages: Vec<(Stage, Vec<(&'static str, LatchValue)>)>,
+    // the first error hit while writing, reported by finish
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new(
+        out: Box<dyn Write>,
+        format: TraceFormat,
+        is_verbose: bool,
+        symbols: HashMap<String, u16>,
+    ) -> Self {
+        Tracer {
+            out,
+            format,
+            is_verbose,
+            symbols,
+            source_map: None,
+            prev_c_location: None,
+            instr_text: String::new(),
+            stages: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn set_source_map(&mut self, source_map: SourceMap) {
+        self.source_map = Some(source_map);
+    }
+
+    pub fn is_verbose(&self) -> bool {
+        self.is_verbose
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+
+    pub fn begin_instr(&mut self, bus: &Bus, pc: u16) {
+        // disassembled before the instruction runs, in case it overwrites itself
+        self.instr_text = disassemble_to_string(bus, pc, &self.symbols).0;
+        self.stages.clear();
+    }
+
+    pub fn record_stage(&mut self, stage: Stage, latches: Vec<(&'static str, LatchValue)>) {
+        self.stages.push((stage, latches));
+    }
+
+    pub fn trace_instr(
+        &mut self,
+        cycle: u64,
+        pc: u16,
+        regs_before: &[u16; 16],
+        regs_after: &[u16; 16],
+        mem_write: Option<(u16, LatchValue)>,
+        num_cycles: u8,
+    ) {
+        let deltas = Deltas::new(regs_before, regs_after, mem_write.into_iter().collect());
+        let c_location = self
+            .source_map
+            .as_ref()
+            .and_then(|s| s.format_c_location(pc));
+        let line = match self.format {
+            TraceFormat::Text => {
+                match &c_location {
+                    Some(loc) if c_location != self.prev_c_location => {
+                        self.write_line(&format!("{:>12}{}", "", loc))
+                    }
+                    _ => {}
+                }
+                let mut line = format!(
+                    "{:>10}  {:04X}  {:<28}{}",
+                    cycle,
+                    pc,
+                    self.instr_text,
+                    deltas.to_text()
+                )
+                .trim_end()
+                .to_owned();
+                for (stage, latches) in &self.stages {
+                    line.push_str(&format!("\n            stage {}:", stage.get_name()));
+                    for (name, value) in latches {
+                        line.push_str(&format!(" {}={}", name, format_latch_text(value)));
+                    }
+                }
+                line
+            }
+            TraceFormat::JsonLines => {
+                let mut line = format!(
+                    "{{\"type\":\"instr\",\"cycle\":{},\"pc\":{},\"instr\":\"{}\",{},\"cycles\":{}",
+                    cycle,
+                    pc,
+                    escape_json(&self.instr_text),
+                    deltas.to_json(),
+                    num_cycles
+                );
+                if let Some(c_location) = &c_location {
+                    line.push_str(&format!(",\"src\":\"{}\"", escape_json(c_location)));
+                }
+                if self.is_verbose {
+                    let stages: Vec<String> = self
+                        .stages
+                        .iter()
+                        .map(|(stage, latches)| {
+                            let latches: Vec<String> = latches
+                                .iter()
+                                .map(|(name, value)| {
+                                    format!("\"{}\":{}", name, format_latch_json(value))
+                                })
+                                .collect();
+                            format!("\"{}\":{{{}}}", stage.get_name(), latches.join(","))
+                        })
+                        .collect();
+                    line.push_str(&format!(",\"stages\":{{{}}}", stages.join(",")));
+                }
+                line.push('}');
+                line
+            }
+        };
+        self.write_line(&line);
+        if c_location.is_some() {
+            self.prev_c_location = c_location;
+        }
+    }
+
+    pub fn trace_interrupt(
+        &mut self,
+        cycle: u64,
+        vector: u16,
+        regs_before: &[u16; 16],
+        regs_after: &[u16; 16],
+        pushes: [(u16, u16); 2], // the addresses and values of the pushes of PC and SR
+    ) {
+        let mem_writes = pushes
+            .iter()
+            .map(|(addr, value)| (*addr, LatchValue::Word(*value)))
+            .collect();
+        let deltas = Deltas::new(regs_before, regs_after, mem_writes);
+        let line = match self.format {
+            TraceFormat::Text => format!(
+                "{:>10}  {:04X}  {:<28}{}",
+                cycle,
+                regs_before[0],
+                format!("interrupt {}", vector),
+                deltas.to_text()
+            )
+            .trim_end()
+            .to_owned(),
+            TraceFormat::JsonLines => format!(
+                "{{\"type\":\"interrupt\",\"cycle\":{},\"pc\":{},\"vector\":{},{}}}",
+                cycle,
+                regs_before[0],
+                vector,
+                deltas.to_json()
+            ),
+        };
+        self.write_line(&line);
+    }
+
+    pub fn trace_fault(&mut self, cycle: u64, pc: u16, fault: &CpuFault) {
+        let line = match self.format {
+            TraceFormat::Text => format!(
+                "{:>10}  {:04X}  {:<28}fault: {}",
+                cycle, pc, self.instr_text, fault
+            ),
+            TraceFormat::JsonLines => format!(
+                "{{\"type\":\"fault\",\"cycle\":{},\"pc\":{},\"instr\":\"{}\",\"fault\":\"{}\"}}",
+                cycle,
+                pc,
+                escape_json(&self.instr_text),
+                escape_json(&fault.to_string())
+            ),
+        };
+        self.write_line(&line);
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = writeln!(self.out, "{}", line) {
+            self.error = Some(e);
+        }
+    }
+}
+
+struct Deltas {
+    regs: Vec<(usize, u16)>,
+    flags: Vec<(&'static str, bool)>,
+    mem_writes: Vec<(u16, LatchValue)>, // in the order of the writes
+}
+
+impl Deltas {
+    fn new(
+        regs_before: &[u16; 16],
+        regs_after: &[u16; 16],
+        mem_writes: Vec<(u16, LatchValue)>,
+    ) -> Self {
+        // the pc changes on every instruction, so it is left out
+        let regs = (1..16)
+            .filter(|i| *i != 2 && regs_before[*i] != regs_after[*i])
+            .map(|i| (i, regs_after[i]))
+            .collect();
+        let flags = FLAG_BITS
+            .iter()
+            .filter(|(_, bit)| (regs_before[2] ^ regs_after[2]) & bit != 0)
+            .map(|(name, bit)| (*name, regs_after[2] & bit != 0))
+            .collect();
+        Deltas {
+            regs,
+            flags,
+            mem_writes,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut parts = Vec::new();
+        for (i, val) in &self.regs {
+            parts.push(format!("{}={:04X}", REG_NAMES[*i], val));
+        }
+        for (name, is_set) in &self.flags {
+            parts.push(format!("{}={}", name, *is_set as u8));
+        }
+        for (addr, value) in &self.mem_writes {
+            parts.push(format!("[{:04X}]={}", addr, format_latch_text(value)));
+        }
+        parts.join(" ")
+    }
+
+    fn to_json(&self) -> String {
+        let regs: Vec<String> = self
+            .regs
+            .iter()
+            .map(|(i, val)| format!("\"{}\":{}", REG_NAMES[*i], val))
+            .collect();
+        let flags: Vec<String> = self
+            .flags
+            .iter()
+            .map(|(name, is_set)| format!("\"{}\":{}", name, is_set))
+            .collect();
+        let mem_writes: Vec<String> = self
+            .mem_writes
+            .iter()
+            .map(|(addr, value)| {
+                let size = match value {
+                    LatchValue::Byte(_) => 1,
+                    _ => 2,
+                };
+                format!(
+                    "{{\"addr\":{},\"value\":{},\"size\":{}}}",
+                    addr,
+                    format_latch_json(value),
+                    size
+                )
+            })
+            .collect();
+        format!(
+            "\"regs\":{{{}}},\"flags\":{{{}}},\"mem_writes\":[{}]",
+            regs.join(","),
+            flags.join(","),
+            mem_writes.join(",")
+        )
+    }
+}
+
+fn format_latch_text(value: &LatchValue) -> String {
+    match value {
+        LatchValue::Word(w) => format!("{:04X}", w),
+        LatchValue::Byte(b) => format!("{:02X}", b),
+        LatchValue::Bool(b) => format!("{}", *b as u8),
+        LatchValue::Flag(None) => "-".to_owned(),
+        LatchValue::Flag(Some(b)) => format!("{}", *b as u8),
+    }
+}
+
+fn format_latch_json(value: &LatchValue) -> String {
+    match value {
+        LatchValue::Word(w) => format!("{}", w),
+        LatchValue::Byte(b) => format!("{}", b),
+        LatchValue::Bool(b) | LatchValue::Flag(Some(b)) => format!("{}", b),
+        LatchValue::Flag(None) => "null".to_owned(),
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+// Watchpoints trigger on memory accesses made by instructions (and by interrupt entry),
+// but not on instruction fetches or on peeks from debuggers.
+// A hit is recorded after the instruction that caused it has been retired.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access, // reads and writes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAction {
+    Break, // stop running, like a breakpoint
+    Log,   // record the hit and keep going
+}
+
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    pub id: usize,
+    pub range: RangeInclusive<u16>,
+    pub kind: WatchKind,
+    pub action: WatchAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: usize,
+    pub action: WatchAction,
+    pub pc: u16, // the instruction that made the access
+    pub addr: u16,
+    pub is_write: bool,
+    pub is_byte: bool,
+    pub old_value: u16,
+    pub new_value: u16, // the same as old_value for reads
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = if self.is_byte { 2 } else { 4 };
+        if self.is_write {
+            write!(
+                f,
+                "watchpoint {}: write to {:04X} at pc {:04X}: {:0w$X} -> {:0w$X}",
+                self.id,
+                self.addr,
+                self.pc,
+                self.old_value,
+                self.new_value,
+                w = width
+            )
+        } else {
+            write!(
+                f,
+                "watchpoint {}: read of {:04X} at pc {:04X}: {:0w$X}",
+                self.id,
+                self.addr,
+                self.pc,
+                self.new_value,
+                w = width
+            )
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    next_id: usize,
+    hits: Vec<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn add(
+        &mut self,
+        range: RangeInclusive<u16>,
+        kind: WatchKind,
+        action: WatchAction,
+    ) -> usize {
+        self.next_id += 1;
+        self.list.push(Watchpoint {
+            id: self.next_id,
+            range,
+            kind,
+            action,
+        });
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let len = self.list.len();
+        self.list.retain(|w| w.id != id);
+        self.list.len() != len
+    }
+
+    pub fn get_all(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn check(
+        &mut self,
+        pc: u16,
+        addr: u16,
+        is_byte: bool,
+        is_write: bool,
+        old_value: u16,
+        new_value: u16,
+    ) {
+        let last_addr = if is_byte {
+            addr
+        } else {
+            addr.overflowing_add(1).0
+        };
+        for w in &self.list {
+            let is_kind_matched = match w.kind {
+                WatchKind::Read => !is_write,
+                WatchKind::Write => is_write,
+                WatchKind::Access => true,
+            };
+            let is_addr_matched = w.range.contains(&addr) || w.range.contains(&last_addr);
+            if is_kind_matched && is_addr_matched {
+                self.hits.push(WatchHit {
+                    id: w.id,
+                    action: w.action,
+                    pc,
+                    addr,
+                    is_write,
+                    is_byte,
+                    old_value,
+                    new_value,
+                });
+            }
+        }
+    }
+
+    pub fn has_break_hit(&self) -> bool {
+        self.hits.iter().any(|h| h.action == WatchAction::Break)
+    }
+
+    pub fn take_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.hits)
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// A constant expression in an operand or a directive, such as "table+2*SIZE" or "$-4".
+// Expressions that only have numbers in them are evaluated as soon as they are read, so that
+// immediates can use the constant generator. The rest are kept until the addresses of labels are
+// known: .set and .equ constants are put in by get_tokens, and labels and $ (the address of the
+// current instruction) by resolve_labels.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(i64),
+    Label(String),
+    Here, // $
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Shl,
+    Shr,
+    And,
+    Or,
+}
+
+impl Expr {
+    pub fn eval(
+        &self,
+        lookup: &dyn Fn(&str) -> Option<i64>,
+        here: Option<u16>,
+    ) -> Result<i64, String> {
+        // lookup gives the value of a label, and here is the value of $
+        let value = match self {
+            Expr::Num(n) => *n,
+            Expr::Label(label) => match lookup(label) {
+                Some(value) => value,
+                None => return Err(format!("unresolved label {}", label)),
+            },
+            Expr::Here => match here {
+                Some(here) => here as i64,
+                None => return Err("$ can't be used here".to_owned()),
+            },
+            Expr::Neg(e) => e.eval(lookup, here)?.wrapping_neg(),
+            Expr::Not(e) => !e.eval(lookup, here)?,
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(lookup, here)?, r.eval(lookup, here)?);
+                match op {
+                    BinaryOp::Add => l.wrapping_add(r),
+                    BinaryOp::Sub => l.wrapping_sub(r),
+                    BinaryOp::Mul => l.wrapping_mul(r),
+                    BinaryOp::Div if r == 0 => return Err("division by zero".to_owned()),
+                    BinaryOp::Div => l.wrapping_div(r),
+                    BinaryOp::Shl | BinaryOp::Shr if !(0..64).contains(&r) => {
+                        return Err(format!("can't shift by {}", r))
+                    }
+                    BinaryOp::Shl => l << r,
+                    BinaryOp::Shr => l >> r,
+                    BinaryOp::And => l & r,
+                    BinaryOp::Or => l | r,
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    pub fn get_constant(&self) -> Option<i64> {
+        // the value, if there are no labels or $ in the expression
+        self.eval(&|_| None, None).ok()
+    }
+
+    pub fn get_labels(&self) -> Vec<&str> {
+        // the labels and constants that the expression refers to
+        match self {
+            Expr::Num(_) | Expr::Here => Vec::new(),
+            Expr::Label(label) => vec![label.as_str()],
+            Expr::Neg(e) | Expr::Not(e) => e.get_labels(),
+            Expr::Binary(_, l, r) => [l.get_labels(), r.get_labels()].concat(),
+        }
+    }
+
+    pub fn substitute(&self, constants: &HashMap<String, Expr>) -> Expr {
+        // puts in the expressions of constants, and folds the parts that become constant
+        let expr = match self {
+            Expr::Num(_) | Expr::Here => self.clone(),
+            Expr::Label(label) => match constants.get(label) {
+                Some(e) => e.clone(),
+                None => self.clone(),
+            },
+            Expr::Neg(e) => Expr::Neg(Box::new(e.substitute(constants))),
+            Expr::Not(e) => Expr::Not(Box::new(e.substitute(constants))),
+            Expr::Binary(op, l, r) => Expr::Binary(
+                *op,
+                Box::new(l.substitute(constants)),
+                Box::new(r.substitute(constants)),
+            ),
+        };
+        match expr.get_constant() {
+            Some(n) => Expr::Num(n),
+            None => expr,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // numbers are written in hexadecimal, since they are usually addresses. nested binary
+        // operations are put in parentheses, so the precedence doesn't need to be known
+        let write_operand = |f: &mut fmt::Formatter<'_>, e: &Expr| match e {
+            Expr::Binary(..) => write!(f, "({})", e),
+            _ => write!(f, "{}", e),
+        };
+        match self {
+            Expr::Num(n) if *n < 0 => write!(f, "-0x{:04X}", n.unsigned_abs()),
+            Expr::Num(n) => write!(f, "0x{:04X}", n),
+            Expr::Label(label) => write!(f, "{}", label),
+            Expr::Here => write!(f, "$"),
+            Expr::Neg(e) => {
+                write!(f, "-")?;
+                write_operand(f, e)
+            }
+            Expr::Not(e) => {
+                write!(f, "~")?;
+                write_operand(f, e)
+            }
+            Expr::Binary(op, l, r) => {
+                let op = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Shl => "<<",
+                    BinaryOp::Shr => ">>",
+                    BinaryOp::And => "&",
+                    BinaryOp::Or => "|",
+                };
+                write_operand(f, l)?;
+                write!(f, "{}", op)?;
+                write_operand(f, r)
+            }
+        }
+    }
+}
@@ -1,298 +1,414 @@
+pub mod data_directives;
+pub mod debug_directives;
+pub mod parse_expr;
 pub mod parse_operand;
 
 use crate::{
+    asm_error::AsmError,
     asm_line::AsmLine,
     ccode::CC,
-    get_verbs::parse_operand::parse_operand,
+    expr::Expr,
+    get_verbs::{
+        data_directives::{parse_directive, resolve_constants, substitute_constants, DataState},
+        parse_expr::parse_expr,
+        parse_operand::{expect_char, parse_dst_operand, parse_operand},
+    },
     operand::{Operand, Reg},
     source_cursor::SourceCodeCursor,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Global {
-    pub label: String,
+    pub label: String, // empty for data that has no label
     pub initial_bytes: Vec<u8>,
+    pub align: usize, // the address is a multiple of align
+    // the offsets of the words in initial_bytes that depend on a label, such as its address, and
+    // the source line of each
+    pub label_words: Vec<(usize, Expr, usize)>,
 }
 
+// the globals, the lines, and the source line of every line, counting from 1
+pub type Tokens = (Vec<Global>, Vec<AsmLine>, Vec<usize>);
+
 pub fn get_tokens(source_code_contents: String) -> (Vec<Global>, Vec<AsmLine>) {
+    let (globals, lines, _) = get_tokens_with_line_nums(source_code_contents);
+    (globals, lines)
+}
+
+pub fn get_tokens_with_line_nums(source_code_contents: String) -> Tokens {
+    // for sources that are known to assemble, such as in tests. panics if there are errors
+    match try_get_tokens("<source>", source_code_contents) {
+        Ok(tokens) => tokens,
+        Err(errors) => panic!(
+            "{}",
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+pub fn try_get_tokens(
+    file_name: &str,
+    source_code_contents: String,
+) -> Result<Tokens, Vec<AsmError>> {
+    // a line with an error is left out, and the lines after it are still parsed to find any
+    // more errors. file_name is only used in the errors
     let mut cursor = SourceCodeCursor::new(source_code_contents);
 
-    let mut globals = Vec::new();
+    let mut data = DataState::default();
 
     let mut lines: Vec<AsmLine> = Vec::new();
+    let mut line_nums: Vec<usize> = Vec::new();
+    let mut errors: Vec<AsmError> = Vec::new();
 
     while cursor.peek().is_some() {
         // this loop will consume one line per iteration:
 
         // consume leading whitespace
         consume_whitespace(&mut cursor);
+        let line_num = cursor.get_line();
         match cursor.peek() {
             None => break,
-            Some('\n') | Some(';') | Some('.') => {
-                if cursor.begins_with(".bits") {
-                    // parse a global variable declaration
-                    for _ in 0..5 {
-                        cursor.next();
-                    }
-                    consume_whitespace(&mut cursor);
-                    let label = lines.pop().unwrap().as_label_str();
-                    let initial_bytes = parse_initial_bytes(&mut cursor);
-
-                    globals.push(Global {
-                        label,
-                        initial_bytes,
-                    });
+            Some('\n') | Some(';') => {
+                // empty line or comment. Consume the empty line.
+                consume_rest_of_line(&mut cursor);
+            }
+            Some('.') => {
+                if let Err(e) = parse_directive(&mut cursor, None, &mut lines, &mut data) {
+                    errors.push(e);
                 }
-                // empty line or comment/directive. Consume the empty line.
                 consume_rest_of_line(&mut cursor);
             }
 
             _ => {
-                let mut component_1 = String::new();
-                while cursor.peek().is_some() && !cursor.peek().unwrap().is_ascii_whitespace() {
-                    let c = cursor.next().unwrap();
-                    component_1.push(c);
-                }
-
-                if component_1.ends_with(":") {
-                    // parse label
-                    lines.push(AsmLine::Label(
-                        component_1.strip_suffix(":").unwrap().to_owned(),
-                    ));
-                } else {
-                    let component_1_base;
-                    let mut is_byte_instr = false;
-                    if component_1.ends_with(".W") || component_1.ends_with(".B") {
-                        is_byte_instr = component_1.ends_with(".B");
-                        component_1_base = &component_1[..component_1.len() - 2];
-                    } else {
-                        component_1_base = &component_1;
-                    }
+                let col = cursor.get_col();
+                let component_1 = read_component(&mut cursor);
 
-                    match component_1_base {
-                        "RRC" | "SWPB" | "RRA" | "SXT" | "PUSH" | "CALL" => {
-                            // SINGLE OPERAND FAMILY
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            match component_1_base {
-                                "RRC" => {
-                                    lines.push(AsmLine::RRC(operand, is_byte_instr));
-                                }
-                                "SWPB" => {
-                                    lines.push(AsmLine::SWPB(operand, false));
-                                }
-                                "RRA" => {
-                                    lines.push(AsmLine::RRA(operand, is_byte_instr));
-                                }
-                                "SXT" => {
-                                    lines.push(AsmLine::SXT(operand, false));
-                                }
-                                "PUSH" => {
-                                    lines.push(AsmLine::PUSH(operand, is_byte_instr));
-                                }
-                                "CALL" => {
-                                    lines.push(AsmLine::CALL(operand, false));
-                                }
-                                _ => unreachable!(),
+                let result = match component_1.strip_suffix(":") {
+                    Some(label) => {
+                        // a label, which can have a directive or an instruction after it on the
+                        // same line
+                        data.define_label(label.to_owned(), &mut lines);
+                        match cursor.peek() {
+                            None | Some('\n') | Some('\r') | Some(';') => Ok(()),
+                            Some('.') => parse_directive(&mut cursor, None, &mut lines, &mut data),
+                            Some(_) => {
+                                let col = cursor.get_col();
+                                let instr = read_component(&mut cursor);
+                                parse_text_instr(&mut cursor, &instr, col, &mut lines, &data)
                             }
                         }
-                        "RETI" => {
-                            lines.push(AsmLine::RETI);
-                        }
+                    }
+                    None if cursor.peek() == Some('.') => {
+                        // a labelled directive, such as name .set 4, or the .dwtag directives
+                        // that cl430 writes with --symdebug:dwarf, which are read by
+                        // get_debug_info
+                        parse_directive(&mut cursor, Some(component_1), &mut lines, &mut data)
+                    }
+                    None => parse_text_instr(&mut cursor, &component_1, col, &mut lines, &data),
+                };
+                if let Err(e) = result {
+                    errors.push(e);
+                }
 
-                        // JUMPS
-                        "JNE" | "JNZ" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::NotEq, label));
-                        }
-                        "JEQ" | "JZ" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::Eq, label));
-                        }
-                        "JNC" | "JLO" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::NoCarry, label));
-                        }
-                        "JC" | "JHS" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::Carry, label));
-                        }
-                        "JN" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::Neg, label));
-                        }
-                        "JGE" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::GreaterEq, label));
-                        }
-                        "JL" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::Less, label));
-                        }
-                        "JMP" => {
-                            let label = parse_jmp_label(&mut cursor);
-                            lines.push(AsmLine::Jump(CC::Unconditional, label));
-                        }
+                consume_rest_of_line(&mut cursor);
+            }
+        }
+        // data directives take the label before them out of lines
+        line_nums.resize(lines.len(), line_num);
+    }
+    if let Err(constant_errors) = resolve_constants(&mut data) {
+        errors.extend(constant_errors);
+        errors.sort_by_key(|e| e.line);
+    }
+    substitute_constants(&mut lines, &mut data);
 
-                        "MOV" | "ADD" | "ADDC" | "SUB" | "SUBC" | "CMP" | "DADD" | "BIT"
-                        | "BIC" | "BIS" | "OR" | "XOR" | "AND" => {
-                            // DOUBLE OPERAND FAMILY
-                            consume_whitespace(&mut cursor);
-                            let operand_1 = parse_operand(&mut cursor);
-                            assert_eq!(cursor.next(), Some(','));
-                            let operand_2 = parse_operand(&mut cursor);
-                            match component_1_base {
-                                "MOV" => {
-                                    lines.push(AsmLine::MOV(operand_1, operand_2, is_byte_instr));
-                                }
-                                "ADD" => {
-                                    lines.push(AsmLine::ADD(operand_1, operand_2, is_byte_instr));
-                                }
-                                "ADDC" => {
-                                    lines.push(AsmLine::ADDC(operand_1, operand_2, is_byte_instr));
-                                }
-                                "SUB" => {
-                                    lines.push(AsmLine::SUB(operand_1, operand_2, is_byte_instr));
-                                }
-                                "SUBC" => {
-                                    lines.push(AsmLine::SUBC(operand_1, operand_2, is_byte_instr));
-                                }
-                                "CMP" => {
-                                    lines.push(AsmLine::CMP(operand_1, operand_2, is_byte_instr));
-                                }
-                                "DADD" => {
-                                    lines.push(AsmLine::DADD(operand_1, operand_2, is_byte_instr));
-                                }
-                                "BIT" => {
-                                    lines.push(AsmLine::BIT(operand_1, operand_2, is_byte_instr));
-                                }
-                                "BIC" => {
-                                    lines.push(AsmLine::BIC(operand_1, operand_2, is_byte_instr));
-                                }
-                                "BIS" | "OR" => {
-                                    lines.push(AsmLine::BIS(operand_1, operand_2, is_byte_instr));
-                                }
-                                "XOR" => {
-                                    lines.push(AsmLine::XOR(operand_1, operand_2, is_byte_instr));
-                                }
-                                "AND" => {
-                                    lines.push(AsmLine::AND(operand_1, operand_2, is_byte_instr));
-                                }
-                                _ => unreachable!(),
-                            }
-                        }
+    if !errors.is_empty() {
+        for error in &mut errors {
+            error.file_name = file_name.to_owned();
+        }
+        return Err(errors);
+    }
+    return Ok((data.globals, lines, line_nums));
+}
 
-                        // ========================
-                        // Pseudo-operations
-                        // ========================
-                        "ADC" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::ADDC(Operand::Imm(0), operand, is_byte_instr));
-                        }
-                        "BR" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::MOV(operand, Operand::Reg(Reg::PC), false));
-                        }
-                        "CLR" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::MOV(Operand::Imm(0), operand, is_byte_instr));
-                        }
+fn read_component(cursor: &mut SourceCodeCursor) -> String {
+    // a label, a directive or a mnemonic, and the whitespace after it
+    let mut component = String::new();
+    while cursor.peek().is_some() && !cursor.peek().unwrap().is_ascii_whitespace() {
+        component.push(cursor.next().unwrap());
+    }
+    consume_whitespace(cursor);
+    component
+}
 
-                        "DEC" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::SUB(Operand::Imm(1), operand, is_byte_instr));
-                        }
-                        "DECD" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::SUB(Operand::Imm(2), operand, is_byte_instr));
-                        }
-                        "INC" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::ADD(Operand::Imm(1), operand, is_byte_instr));
-                        }
-                        "INCD" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::ADD(Operand::Imm(2), operand, is_byte_instr));
-                        }
-                        "INV" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::XOR(Operand::Imm(0xFFFF), operand, is_byte_instr));
-                        }
-                        "NOP" => {
-                            lines.push(AsmLine::MOV(Operand::Imm(0), Operand::Reg(Reg::CG), false));
-                        }
-                        "POP" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::MOV(
-                                Operand::IndirectAutoInc(Reg::SP),
-                                operand,
-                                is_byte_instr,
-                            ));
-                        }
-                        "RET" => {
-                            lines.push(AsmLine::MOV(
-                                Operand::IndirectAutoInc(Reg::SP),
-                                Operand::Reg(Reg::PC),
-                                false,
-                            ));
-                        }
-                        "RLA" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::ADD(operand.clone(), operand, is_byte_instr));
-                        }
-                        "RLC" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::ADDC(operand.clone(), operand, is_byte_instr));
-                        }
-                        "SBC" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::SUBC(Operand::Imm(0), operand, is_byte_instr));
-                        }
+fn parse_text_instr(
+    cursor: &mut SourceCodeCursor,
+    component_1: &str,
+    col: usize,
+    lines: &mut Vec<AsmLine>,
+    data: &DataState,
+) -> Result<(), AsmError> {
+    // instructions are only allowed in the .text section
+    if data.is_data_section {
+        return Err(cursor.make_error(col, format!("{} is not in a .text section", component_1)));
+    }
+    parse_instr(cursor, component_1, col, lines)
+}
 
-                        "TST" => {
-                            consume_whitespace(&mut cursor);
-                            let operand = parse_operand(&mut cursor);
-                            lines.push(AsmLine::CMP(Operand::Imm(0), operand, is_byte_instr));
-                        }
-                        // ========================
-                        // end of Pseudo-operations
-                        // ========================
-                        _ => {
-                            panic!("unrecognized instruction {}", component_1_base);
-                        }
-                    }
+fn parse_instr(
+    cursor: &mut SourceCodeCursor,
+    component_1: &str,
+    col: usize,
+    lines: &mut Vec<AsmLine>,
+) -> Result<(), AsmError> {
+    // parses the operands of the instruction named by component_1, which starts at col
+    let component_1_base;
+    let mut is_byte_instr = false;
+    if component_1.ends_with(".W") || component_1.ends_with(".B") {
+        is_byte_instr = component_1.ends_with(".B");
+        component_1_base = &component_1[..component_1.len() - 2];
+    } else {
+        component_1_base = component_1;
+    }
+
+    match component_1_base {
+        "RRC" | "SWPB" | "RRA" | "SXT" | "PUSH" | "CALL" => {
+            // SINGLE OPERAND FAMILY
+            consume_whitespace(cursor);
+            let operand = parse_operand(cursor)?;
+            match component_1_base {
+                "RRC" => {
+                    lines.push(AsmLine::RRC(operand, is_byte_instr));
+                }
+                "SWPB" => {
+                    lines.push(AsmLine::SWPB(operand, false));
+                }
+                "RRA" => {
+                    lines.push(AsmLine::RRA(operand, is_byte_instr));
+                }
+                "SXT" => {
+                    lines.push(AsmLine::SXT(operand, false));
                 }
+                "PUSH" => {
+                    lines.push(AsmLine::PUSH(operand, is_byte_instr));
+                }
+                "CALL" => {
+                    lines.push(AsmLine::CALL(operand, false));
+                }
+                _ => unreachable!(),
+            }
+        }
+        "RETI" => {
+            lines.push(AsmLine::RETI);
+        }
 
-                consume_rest_of_line(&mut cursor);
+        // JUMPS
+        "JNE" | "JNZ" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::NotEq, label));
+        }
+        "JEQ" | "JZ" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::Eq, label));
+        }
+        "JNC" | "JLO" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::NoCarry, label));
+        }
+        "JC" | "JHS" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::Carry, label));
+        }
+        "JN" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::Neg, label));
+        }
+        "JGE" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::GreaterEq, label));
+        }
+        "JL" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::Less, label));
+        }
+        "JMP" => {
+            let label = parse_jmp_label(cursor)?;
+            lines.push(AsmLine::Jump(CC::Unconditional, label));
+        }
+
+        "MOV" | "ADD" | "ADDC" | "SUB" | "SUBC" | "CMP" | "DADD" | "BIT" | "BIC" | "BIS" | "OR"
+        | "XOR" | "AND" => {
+            // DOUBLE OPERAND FAMILY
+            consume_whitespace(cursor);
+            let operand_1 = parse_operand(cursor)?;
+            expect_char(cursor, ',')?;
+            consume_whitespace(cursor);
+            let operand_2 = parse_dst_operand(cursor)?;
+            match component_1_base {
+                "MOV" => {
+                    lines.push(AsmLine::MOV(operand_1, operand_2, is_byte_instr));
+                }
+                "ADD" => {
+                    lines.push(AsmLine::ADD(operand_1, operand_2, is_byte_instr));
+                }
+                "ADDC" => {
+                    lines.push(AsmLine::ADDC(operand_1, operand_2, is_byte_instr));
+                }
+                "SUB" => {
+                    lines.push(AsmLine::SUB(operand_1, operand_2, is_byte_instr));
+                }
+                "SUBC" => {
+                    lines.push(AsmLine::SUBC(operand_1, operand_2, is_byte_instr));
+                }
+                "CMP" => {
+                    lines.push(AsmLine::CMP(operand_1, operand_2, is_byte_instr));
+                }
+                "DADD" => {
+                    lines.push(AsmLine::DADD(operand_1, operand_2, is_byte_instr));
+                }
+                "BIT" => {
+                    lines.push(AsmLine::BIT(operand_1, operand_2, is_byte_instr));
+                }
+                "BIC" => {
+                    lines.push(AsmLine::BIC(operand_1, operand_2, is_byte_instr));
+                }
+                "BIS" | "OR" => {
+                    lines.push(AsmLine::BIS(operand_1, operand_2, is_byte_instr));
+                }
+                "XOR" => {
+                    lines.push(AsmLine::XOR(operand_1, operand_2, is_byte_instr));
+                }
+                "AND" => {
+                    lines.push(AsmLine::AND(operand_1, operand_2, is_byte_instr));
+                }
+                _ => unreachable!(),
             }
         }
+
+        // ========================
+        // Pseudo-operations
+        // ========================
+        "ADC" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::ADDC(Operand::Imm(0), operand, is_byte_instr));
+        }
+        "BR" => {
+            consume_whitespace(cursor);
+            let operand = parse_operand(cursor)?;
+            lines.push(AsmLine::MOV(operand, Operand::Reg(Reg::PC), false));
+        }
+        "CLR" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::MOV(Operand::Imm(0), operand, is_byte_instr));
+        }
+
+        "DEC" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::SUB(Operand::Imm(1), operand, is_byte_instr));
+        }
+        "DECD" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::SUB(Operand::Imm(2), operand, is_byte_instr));
+        }
+        "DINT" => {
+            lines.push(AsmLine::BIC(Operand::Imm(8), Operand::Reg(Reg::SR), false));
+        }
+        "EINT" => {
+            lines.push(AsmLine::BIS(Operand::Imm(8), Operand::Reg(Reg::SR), false));
+        }
+        "INC" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::ADD(Operand::Imm(1), operand, is_byte_instr));
+        }
+        "INCD" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::ADD(Operand::Imm(2), operand, is_byte_instr));
+        }
+        "INV" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::XOR(Operand::Imm(0xFFFF), operand, is_byte_instr));
+        }
+        "NOP" => {
+            lines.push(AsmLine::MOV(Operand::Imm(0), Operand::Reg(Reg::CG), false));
+        }
+        "POP" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::MOV(
+                Operand::IndirectAutoInc(Reg::SP),
+                operand,
+                is_byte_instr,
+            ));
+        }
+        "RET" => {
+            lines.push(AsmLine::MOV(
+                Operand::IndirectAutoInc(Reg::SP),
+                Operand::Reg(Reg::PC),
+                false,
+            ));
+        }
+        "RLA" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::ADD(operand.clone(), operand, is_byte_instr));
+        }
+        "RLC" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::ADDC(operand.clone(), operand, is_byte_instr));
+        }
+        "SBC" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::SUBC(Operand::Imm(0), operand, is_byte_instr));
+        }
+
+        "TST" => {
+            consume_whitespace(cursor);
+            let operand = parse_dst_operand(cursor)?;
+            lines.push(AsmLine::CMP(Operand::Imm(0), operand, is_byte_instr));
+        }
+        // ========================
+        // end of Pseudo-operations
+        // ========================
+        _ => {
+            return Err(cursor.make_error(
+                col,
+                format!("unrecognized instruction {}", component_1_base),
+            ));
+        }
     }
 
-    return (globals, lines);
+    // anything after the operands must be a comment
+    consume_whitespace(cursor);
+    match cursor.peek() {
+        None | Some('\n') | Some('\r') | Some(';') => Ok(()),
+        Some(c) => Err(cursor.make_error(
+            cursor.get_col(),
+            format!("unexpected {} after instruction", c),
+        )),
+    }
 }
 
-fn parse_jmp_label(cursor: &mut SourceCodeCursor) -> String {
+fn parse_jmp_label(cursor: &mut SourceCodeCursor) -> Result<Expr, AsmError> {
+    // a label, or an expression such as $+4
     consume_whitespace(cursor);
-    let mut res = String::new();
-    while cursor.peek().is_some() && !cursor.peek().unwrap().is_ascii_whitespace() {
-        let c = cursor.next().unwrap();
-        res.push(c);
+    let col = cursor.get_col();
+    if matches!(cursor.peek(), None | Some('\n') | Some('\r') | Some(';')) {
+        return Err(cursor.make_error(col, "expected a label to jump to".to_owned()));
     }
-    consume_rest_of_line(cursor);
-
-    res
+    parse_expr(cursor)
 }
 
 pub fn consume_rest_of_line(cursor: &mut SourceCodeCursor) {
@@ -309,8 +425,9 @@ pub fn consume_whitespace(cursor: &mut SourceCodeCursor) {
     }
 }
 
-pub fn parse_initial_bytes(cursor: &mut SourceCodeCursor) -> Vec<u8> {
+pub fn parse_initial_bytes(cursor: &mut SourceCodeCursor) -> Result<Vec<u8>, AsmError> {
     consume_whitespace(cursor);
+    let col = cursor.get_col();
     if cursor.begins_with("0x") {
         cursor.next();
         cursor.next();
@@ -320,16 +437,25 @@ pub fn parse_initial_bytes(cursor: &mut SourceCodeCursor) -> Vec<u8> {
     while cursor.peek().is_some() && cursor.peek().unwrap().is_digit(16) {
         base_16_lit.push(cursor.next().unwrap());
     }
-    assert_eq!(Some(','), cursor.next());
+    expect_char(cursor, ',')?;
     let mut num_bits_base_10_lit = String::new();
     while cursor.peek().is_some() && cursor.peek().unwrap().is_numeric() {
         num_bits_base_10_lit.push(cursor.next().unwrap());
     }
 
-    let base_16_int = u64::from_str_radix(&base_16_lit, 16).unwrap();
-    let num_bytes = usize::from_str_radix(&num_bits_base_10_lit, 10).unwrap() / 8;
+    let base_16_int = u64::from_str_radix(&base_16_lit, 16);
+    let num_bits = usize::from_str_radix(&num_bits_base_10_lit, 10);
+    let (base_16_int, num_bytes) = match (base_16_int, num_bits) {
+        (Ok(value), Ok(num_bits)) if num_bits % 8 == 0 && num_bits <= 64 => (value, num_bits / 8),
+        _ => {
+            return Err(cursor.make_error(
+                col,
+                "expected an initial value and a number of bits, such as 0x1F,16".to_owned(),
+            ))
+        }
+    };
 
     let bytes = base_16_int.to_le_bytes();
 
-    bytes.into_iter().take(num_bytes).collect()
+    Ok(bytes.into_iter().take(num_bytes).collect())
 }
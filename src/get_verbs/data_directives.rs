@@ -0,0 +1,412 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    asm_error::AsmError,
+    asm_line::AsmLine,
+    expr::Expr,
+    get_verbs::{
+        consume_whitespace, debug_directives::split_args, parse_expr::parse_expr_str,
+        parse_initial_bytes, Global,
+    },
+    operand::Operand,
+    source_cursor::SourceCodeCursor,
+};
+
+// Reads the directives that lay out data and name constants:
+//     .word a,b,...          words, which can depend on the address of a label, as in table+4
+//     .field value[,bits]    a field of 8, 16 or 32 bits (16 by default)
+//     .byte a,b,...          bytes
+//     .string "s",a,...      the characters of strings, and bytes
+//     .cstring "s",a,...     the same, with a 0 byte at the end
+//     .space n               n zero bytes
+//     .bss name,n[,align]    n zero bytes named name
+//     .align n               aligns the next data to a multiple of n bytes
+//     .text, .data, .sect "name", .section name
+//     name .set value, name .equ value, or .set name,value
+//     .global name           has no effect, since programs are a single file
+// Values are expressions (see get_verbs::parse_expr). Only words can depend on labels, and sizes
+// and alignments can only use the constants that are defined before them.
+// All data is laid out together after the startup code, in the order that it appears, so
+// byte_generator only sees globals. Data directives add to the global named by the label before
+// them, or to the last global if nothing came between. In sections other than .text, labels
+// always name data, and instructions are not allowed.
+// .bits is read as before, with its value in hexadecimal.
+
+#[derive(Default)]
+pub struct DataState {
+    pub globals: Vec<Global>,
+    pub constants: HashMap<String, Expr>, // with the constants before them put in
+    // an error at the .set of each constant, with the message left out, for a constant that is
+    // defined in terms of itself
+    constant_errors: HashMap<String, AsmError>,
+    pub is_data_section: bool,
+    align: usize, // from an .align directive, for the next global
+    // lines.len() when the last global was started or added to, since data directives only add
+    // to the last global if no line came between
+    last_global_lines_len: Option<usize>,
+}
+
+impl DataState {
+    pub fn define_label(&mut self, label: String, lines: &mut Vec<AsmLine>) {
+        match self.is_data_section {
+            true => self.start_global(label, lines),
+            false => lines.push(AsmLine::Label(label)),
+        }
+    }
+
+    fn start_global(&mut self, label: String, lines: &[AsmLine]) {
+        self.globals.push(Global {
+            label,
+            initial_bytes: Vec::new(),
+            align: self.align.max(1),
+            label_words: Vec::new(),
+        });
+        self.align = 1;
+        self.last_global_lines_len = Some(lines.len());
+    }
+
+    fn get_global(&mut self, lines: &mut Vec<AsmLine>) -> &mut Global {
+        // the global to add data to, taking the label before the data out of lines if the data
+        // starts a new global
+        if self.last_global_lines_len != Some(lines.len()) {
+            let label = match lines.last() {
+                Some(AsmLine::Label(_)) => lines.pop().unwrap().as_label_str(),
+                _ => String::new(),
+            };
+            self.start_global(label, lines);
+        }
+        self.globals.last_mut().unwrap()
+    }
+}
+
+pub fn parse_directive(
+    cursor: &mut SourceCodeCursor,
+    label: Option<String>, // a label without a colon, in front of the directive
+    lines: &mut Vec<AsmLine>,
+    data: &mut DataState,
+) -> Result<(), AsmError> {
+    let col = cursor.get_col();
+    let mut directive = String::new();
+    while cursor.peek().is_some() && !cursor.peek().unwrap().is_ascii_whitespace() {
+        directive.push(cursor.next().unwrap());
+    }
+    consume_whitespace(cursor);
+
+    let is_data_directive = matches!(
+        directive.as_str(),
+        ".bits" | ".field" | ".word" | ".byte" | ".string" | ".cstring" | ".space"
+    );
+    if is_data_directive {
+        if let Some(label) = label.clone() {
+            data.define_label(label, lines);
+        }
+    }
+    if directive == ".bits" {
+        let initial_bytes = parse_initial_bytes(cursor)?;
+        let global = data.get_global(lines);
+        global.initial_bytes.extend(initial_bytes);
+        data.last_global_lines_len = Some(lines.len());
+        return Ok(());
+    }
+
+    let args_str = read_args(cursor);
+    let args = match args_str.is_empty() {
+        true => Vec::new(),
+        false => split_args(&args_str),
+    };
+    let error = |message: String| cursor.make_error(col, message);
+    let constants = &data.constants;
+    let expect_num = |arg: &str, min: i64, max: i64| match parse_value(arg, constants) {
+        Ok(Expr::Num(n)) if n >= min && n <= max => Ok(n),
+        Err(message) => Err(error(message)),
+        _ => Err(error(format!(
+            "expected a number from {} to {}, not {}",
+            min, max, arg
+        ))),
+    };
+
+    match directive.as_str() {
+        ".text" => data.is_data_section = false,
+        ".data" => data.is_data_section = true,
+        ".sect" | ".section" => match args.first() {
+            Some(name) => data.is_data_section = !name.trim_matches('"').starts_with(".text"),
+            None => {
+                return Err(error(format!(
+                    "expected a section name after {}",
+                    directive
+                )))
+            }
+        },
+        ".align" => {
+            let align = match args.as_slice() {
+                [n] => expect_num(n, 1, 0x8000)? as usize,
+                _ => return Err(error("expected .align n".to_owned())),
+            };
+            if !align.is_power_of_two() {
+                return Err(error(format!("can't align to {} bytes", align)));
+            }
+            data.align = align;
+            data.last_global_lines_len = None;
+        }
+        ".bss" => {
+            let (name, size, align) = match args.as_slice() {
+                [name, size] => (name, size, "1"),
+                [name, size, align] => (name, size, *align),
+                _ => return Err(error("expected .bss name,size[,align]".to_owned())),
+            };
+            let size = expect_num(size, 0, 0xFFFF)? as usize;
+            data.align = expect_num(align, 1, 0x8000)? as usize;
+            data.start_global(name.to_string(), lines);
+            data.globals.last_mut().unwrap().initial_bytes = vec![0; size];
+            data.last_global_lines_len = None;
+        }
+        ".space" => {
+            let size = match args.as_slice() {
+                [size] => expect_num(size, 0, 0xFFFF)? as usize,
+                _ => return Err(error("expected .space size".to_owned())),
+            };
+            let global = data.get_global(lines);
+            global
+                .initial_bytes
+                .resize(global.initial_bytes.len() + size, 0);
+        }
+        ".byte" => {
+            let mut bytes = Vec::new();
+            for arg in &args {
+                bytes.push(expect_num(arg, i8::MIN as i64, u8::MAX as i64)? as u8);
+            }
+            data.get_global(lines).initial_bytes.extend(bytes);
+        }
+        ".string" | ".cstring" => {
+            let mut bytes = Vec::new();
+            for arg in &args {
+                match arg.strip_prefix('"').and_then(|a| a.strip_suffix('"')) {
+                    Some(s) => bytes.extend(parse_string(s).ok_or_else(|| {
+                        error(format!("strings can only have ASCII characters: {}", arg))
+                    })?),
+                    None => bytes.push(expect_num(arg, i8::MIN as i64, u8::MAX as i64)? as u8),
+                }
+            }
+            if directive == ".cstring" {
+                bytes.push(0);
+            }
+            data.get_global(lines).initial_bytes.extend(bytes);
+        }
+        ".word" | ".field" => {
+            let (values, num_bits) = match (directive.as_str(), args.as_slice()) {
+                (".word", _) | (".field", [_]) => (&args[..], 16),
+                (".field", [_, bits]) => (&args[..1], expect_num(bits, 8, 32)?),
+                _ => return Err(error("expected .field value[,bits]".to_owned())),
+            };
+            if num_bits % 8 != 0 {
+                return Err(error(format!(
+                    "fields of {} bits are not supported",
+                    num_bits
+                )));
+            }
+            let mut exprs = Vec::new();
+            for value in values {
+                exprs.push(parse_value(value, constants).map_err(error)?);
+            }
+            let global = data.get_global(lines);
+            if num_bits >= 16 {
+                // words are aligned on an even byte boundary
+                match global.initial_bytes.is_empty() {
+                    true => global.align = global.align.max(2),
+                    false if !global.initial_bytes.len().is_multiple_of(2) => {
+                        global.initial_bytes.push(0)
+                    }
+                    false => {}
+                }
+            }
+            for (value, expr) in values.iter().zip(exprs) {
+                let num_bytes = num_bits as usize / 8;
+                match expr {
+                    Expr::Num(n) => {
+                        let max = (1i64 << num_bits) - 1;
+                        if n < -(1i64 << (num_bits - 1)) || n > max {
+                            return Err(error(format!(
+                                "{} doesn't fit in {} bits",
+                                value, num_bits
+                            )));
+                        }
+                        global.initial_bytes.extend(&n.to_le_bytes()[..num_bytes]);
+                    }
+                    expr if num_bits == 16 => {
+                        let offset = global.initial_bytes.len();
+                        global.label_words.push((offset, expr, cursor.get_line()));
+                        global.initial_bytes.extend([0, 0]);
+                    }
+                    _ => {
+                        return Err(error(format!(
+                            "only fields of 16 bits can use labels, such as {}",
+                            value
+                        )))
+                    }
+                }
+            }
+        }
+        ".set" | ".equ" => {
+            let (name, value) = match (label, args.as_slice()) {
+                (Some(name), [value]) => (name, *value),
+                (None, [name, value]) => (name.to_string(), *value),
+                _ => return Err(error(format!("expected name {} value", directive))),
+            };
+            let expr = parse_value(value, constants).map_err(error)?;
+            if let Expr::Num(n) = expr {
+                if n < i16::MIN as i64 || n > u16::MAX as i64 {
+                    return Err(error(format!("{} doesn't fit in 16 bits", value)));
+                }
+            }
+            data.constant_errors
+                .insert(name.clone(), cursor.make_error(col, String::new()));
+            data.constants.insert(name, expr);
+        }
+        // other directives, such as the DWARF directives read by get_debug_info, have no effect
+        _ => {}
+    }
+    if is_data_directive {
+        data.last_global_lines_len = Some(lines.len());
+    }
+    Ok(())
+}
+
+pub fn resolve_constants(data: &mut DataState) -> Result<(), Vec<AsmError>> {
+    // a constant can be defined in terms of the constants after it, so they are put into each
+    // other until none of them changes. that only comes to an end if no constant depends on itself
+    let mut errors: Vec<AsmError> = data
+        .constants
+        .keys()
+        .filter(|name| depends_on_itself(name, &data.constants))
+        .map(|name| {
+            let mut error = data.constant_errors[name].clone();
+            error.message = format!("{} is defined in terms of itself", name);
+            error
+        })
+        .collect();
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| (e.line, e.col));
+        return Err(errors);
+    }
+
+    loop {
+        let constants: HashMap<String, Expr> = data
+            .constants
+            .iter()
+            .map(|(name, expr)| (name.clone(), expr.substitute(&data.constants)))
+            .collect();
+        if constants == data.constants {
+            return Ok(());
+        }
+        data.constants = constants;
+    }
+}
+
+fn depends_on_itself(name: &str, constants: &HashMap<String, Expr>) -> bool {
+    // whether name can be reached by following the constants in its definition
+    let mut seen = HashSet::new();
+    let mut to_visit = constants[name].get_labels();
+    while let Some(label) = to_visit.pop() {
+        if label == name {
+            return true;
+        }
+        if seen.insert(label) {
+            if let Some(expr) = constants.get(label) {
+                to_visit.extend(expr.get_labels());
+            }
+        }
+    }
+    false
+}
+
+pub fn substitute_constants(lines: &mut [AsmLine], data: &mut DataState) {
+    // constants can be used before they are defined, so they are put in once every line is read
+    // and resolve_constants has put them into each other. the expressions that become numbers are
+    // put in the instructions and data
+    let constants = &data.constants;
+    for line in lines {
+        if let AsmLine::Jump(_, target) = line {
+            *target = target.substitute(constants);
+        }
+        for operand in line.get_operands_mut() {
+            *operand = match operand {
+                Operand::ImmLabel(expr) => match expr.substitute(constants) {
+                    Expr::Num(n) => Operand::Imm(n as u16),
+                    expr => Operand::ImmLabel(expr),
+                },
+                Operand::IndexedLabel(r, expr) => match expr.substitute(constants) {
+                    Expr::Num(n) => Operand::IndexedReg(*r, n as i16),
+                    expr => Operand::IndexedLabel(*r, expr),
+                },
+                Operand::AbsLabel(expr) => match expr.substitute(constants) {
+                    Expr::Num(n) => Operand::Abs(n as u16),
+                    expr => Operand::AbsLabel(expr),
+                },
+                // both read the memory at the address, however it is encoded
+                Operand::Symbolic(expr) => match expr.substitute(constants) {
+                    Expr::Num(n) => Operand::Abs(n as u16),
+                    expr => Operand::Symbolic(expr),
+                },
+                _ => continue,
+            };
+        }
+    }
+    for global in &mut data.globals {
+        global.label_words.retain_mut(|(offset, expr, _)| {
+            *expr = expr.substitute(constants);
+            match expr {
+                Expr::Num(n) => {
+                    global.initial_bytes[*offset..*offset + 2]
+                        .copy_from_slice(&(*n as u16).to_le_bytes());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+}
+
+fn read_args(cursor: &mut SourceCodeCursor) -> String {
+    // the rest of the line, up to a comment
+    let mut args = String::new();
+    let mut is_quoted = false;
+    while let Some(c) = cursor.peek() {
+        if c == '\n' || (c == ';' && !is_quoted) {
+            break;
+        }
+        if c == '"' {
+            is_quoted = !is_quoted;
+        }
+        args.push(c);
+        cursor.next();
+    }
+    args.trim_end().to_owned()
+}
+
+fn parse_string(s: &str) -> Option<Vec<u8>> {
+    // the characters of a string literal without its quotes, with C escapes
+    let mut bytes = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                c => c,
+            },
+            c => c,
+        };
+        if !c.is_ascii() {
+            return None;
+        }
+        bytes.push(c as u8);
+    }
+    Some(bytes)
+}
+
+fn parse_value(arg: &str, constants: &HashMap<String, Expr>) -> Result<Expr, String> {
+    // an expression, with the constants that are already defined put in
+    Ok(parse_expr_str(arg)?.substitute(constants))
+}
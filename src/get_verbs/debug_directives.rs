@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::debug_info::{DebugInfo, Function, VarLocation, Variable};
+
+// Reads the DWARF directives that cl430 writes with --symdebug:dwarf.
+// An entry is started by a .dwtag directive, usually labelled so that .dwattr directives can
+// add attributes to it. Only entries with children are ended, by a .dwendtag naming their label,
+// and every entry started in between is one of their children:
+//     $C$DW$1    .dwtag  DW_TAG_subprogram
+//                .dwattr $C$DW$1, DW_AT_TI_symbol_name("main")
+//     $C$DW$2    .dwtag  DW_TAG_variable, DW_AT_name("next_player")
+//                .dwattr $C$DW$2, DW_AT_location[DW_OP_breg1 2]
+//                .dwattr $C$DW$2, DW_AT_type(*$C$DW$T$10)
+//                .dwendtag $C$DW$1
+// Line numbers come from .dwpsn directives, which are read with parse_line_directive.
+
+// how many typedefs, consts and volatiles to look through to find the size of a type
+const MAX_TYPE_DEPTH: usize = 16;
+
+struct Entry {
+    tag: String,
+    key: String, // the label of the entry, or a made up name if it has none
+    parent: Option<usize>,
+}
+
+pub fn get_debug_info(source: &str) -> DebugInfo {
+    // the entries that have children are found first, as they are the only ones that are ended
+    let ended_labels: HashSet<&str> = source
+        .lines()
+        .filter_map(split_directive)
+        .filter(|(_, directive, _)| *directive == ".dwendtag")
+        .map(|(_, _, args)| args)
+        .collect();
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut attrs: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut open_entries: Vec<usize> = Vec::new();
+    for (label, directive, args) in source.lines().filter_map(split_directive) {
+        match directive {
+            ".dwtag" => {
+                // attributes can also be given after the tag, as in .dwattr
+                let parts = split_args(args);
+                let key = match label {
+                    Some(l) => l.to_owned(),
+                    None => format!("#{}", entries.len()),
+                };
+                let entry_attrs = attrs.entry(key.clone()).or_default();
+                for part in &parts[1..] {
+                    let (name, value) = parse_attr(part);
+                    entry_attrs.insert(name, value);
+                }
+                entries.push(Entry {
+                    tag: parts[0].to_owned(),
+                    key,
+                    parent: open_entries.last().copied(),
+                });
+                if label.is_some_and(|l| ended_labels.contains(l)) {
+                    open_entries.push(entries.len() - 1);
+                }
+            }
+            ".dwattr" => {
+                let parts = split_args(args);
+                if let Some((key, attr_parts)) = parts.split_first() {
+                    let entry_attrs = attrs.entry(key.to_string()).or_default();
+                    for part in attr_parts {
+                        let (name, value) = parse_attr(part);
+                        entry_attrs.insert(name, value);
+                    }
+                }
+            }
+            ".dwendtag" => {
+                while let Some(i) = open_entries.pop() {
+                    if entries[i].key == args {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let no_attrs = HashMap::new();
+    let get_attrs = |entry: &Entry| attrs.get(&entry.key).unwrap_or(&no_attrs);
+    let find_function = |mut i: usize| loop {
+        i = entries[i].parent?;
+        if entries[i].tag == "DW_TAG_subprogram" {
+            return Some(i);
+        }
+    };
+
+    let mut debug_info = DebugInfo::default();
+    let mut function_indices: HashMap<usize, usize> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let entry_attrs = get_attrs(entry);
+        match entry.tag.as_str() {
+            "DW_TAG_subprogram" => {
+                let name = entry_attrs
+                    .get("DW_AT_TI_symbol_name")
+                    .or(entry_attrs.get("DW_AT_name"));
+                if let Some(name) = name {
+                    function_indices.insert(i, debug_info.functions.len());
+                    debug_info.functions.push(Function {
+                        name: name.clone(),
+                        locals: Vec::new(),
+                    });
+                }
+            }
+            "DW_TAG_variable" | "DW_TAG_formal_parameter" => {
+                let name = entry_attrs.get("DW_AT_name");
+                let location = entry_attrs
+                    .get("DW_AT_location")
+                    .and_then(|l| parse_location(l));
+                let (name, location) = match (name, location) {
+                    (Some(n), Some(l)) => (n.clone(), l),
+                    _ => continue, // such as declarations of extern variables
+                };
+                let type_size = get_type_size(&attrs, entry_attrs);
+                let variable = Variable {
+                    name,
+                    location,
+                    is_byte: type_size == Some(1),
+                };
+                match find_function(i).and_then(|f| function_indices.get(&f)) {
+                    Some(f) => debug_info.functions[*f].locals.push(variable),
+                    None => debug_info.globals.push(variable),
+                }
+            }
+            _ => {}
+        }
+    }
+    debug_info
+}
+
+pub fn parse_line_directive(line: &str) -> Option<(String, usize)> {
+    // the file and line of a .dwpsn directive, such as
+    //     .dwpsn file "main.c",line 42,column 5,is_stmt,isa 0
+    let (_, directive, args) = split_directive(line)?;
+    if directive != ".dwpsn" {
+        return None;
+    }
+    let mut file = None;
+    let mut line_num = None;
+    for part in split_args(args) {
+        if let Some(f) = part.strip_prefix("file") {
+            file = Some(f.trim().trim_matches('"').to_owned());
+        } else if let Some(l) = part.strip_prefix("line") {
+            line_num = parse_num(l.trim()).map(|n| n as usize);
+        }
+    }
+    Some((file?, line_num?))
+}
+
+fn split_directive(line: &str) -> Option<(Option<&str>, &str, &str)> {
+    // the label (a label is any text that starts at the beginning of the line), the directive,
+    // and the rest of the line
+    let (label, rest) = match line.starts_with(|c: char| c.is_ascii_whitespace()) {
+        true => (None, line.trim_start()),
+        false => {
+            let (label, rest) = line.split_once(|c: char| c.is_ascii_whitespace())?;
+            (Some(label.trim_end_matches(':')), rest.trim_start())
+        }
+    };
+    if !rest.starts_with('.') {
+        return None;
+    }
+    let (directive, args) = match rest.split_once(|c: char| c.is_ascii_whitespace()) {
+        Some((d, a)) => (d, a.trim()),
+        None => (rest, ""),
+    };
+    Some((label, directive, args))
+}
+
+pub fn split_args(args: &str) -> Vec<&str> {
+    // splits at the commas that are not inside brackets, parentheses or quotes
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut is_quoted = false;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => is_quoted = !is_quoted,
+            '(' | '[' if !is_quoted => depth += 1,
+            ')' | ']' if !is_quoted => depth -= 1,
+            ',' if !is_quoted && depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+fn parse_attr(attr: &str) -> (String, String) {
+    // DW_AT_name("main") gives the unquoted name, DW_AT_location[DW_OP_addr x] gives
+    // the expression, and DW_AT_external gives an empty value
+    match attr.find(['(', '[']) {
+        Some(i) => {
+            let value = attr[i + 1..].trim_end_matches([')', ']']);
+            (attr[..i].to_owned(), value.trim_matches('"').to_owned())
+        }
+        None => (attr.to_owned(), String::new()),
+    }
+}
+
+fn parse_location(expr: &str) -> Option<VarLocation> {
+    // only the simple expressions that cl430 uses for variables are understood
+    let words: Vec<&str> = expr.split_whitespace().collect();
+    let location = match words.as_slice() {
+        ["DW_OP_addr", label] => Some(VarLocation::Label(label.to_string())),
+        ["DW_OP_bregx", reg, offset] => Some(VarLocation::RegOffset(
+            parse_num(reg)? as usize,
+            parse_num(offset)? as i16,
+        )),
+        ["DW_OP_regx", reg] => Some(VarLocation::Reg(parse_num(reg)? as usize)),
+        [op, offset] => {
+            let reg = op.strip_prefix("DW_OP_breg")?.parse::<usize>().ok()?;
+            Some(VarLocation::RegOffset(reg, parse_num(offset)? as i16))
+        }
+        [op] => Some(VarLocation::Reg(
+            op.strip_prefix("DW_OP_reg")?.parse::<usize>().ok()?,
+        )),
+        _ => None,
+    };
+    location.filter(|location| match location {
+        VarLocation::Reg(reg) | VarLocation::RegOffset(reg, _) => *reg < 16,
+        VarLocation::Label(_) => true,
+    })
+}
+
+fn get_type_size(
+    attrs: &HashMap<String, HashMap<String, String>>,
+    entry_attrs: &HashMap<String, String>,
+) -> Option<i64> {
+    // follows DW_AT_type to the first type that has a size
+    let mut curr_attrs = entry_attrs;
+    for _ in 0..MAX_TYPE_DEPTH {
+        if let Some(size) = curr_attrs.get("DW_AT_byte_size") {
+            return parse_num(size);
+        }
+        let type_label = curr_attrs.get("DW_AT_type")?.trim_start_matches('*');
+        curr_attrs = attrs.get(type_label)?;
+    }
+    None
+}
+
+fn parse_num(s: &str) -> Option<i64> {
+    // cl430 writes most numbers in hexadecimal, with a 0x prefix
+    match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    asm_error::AsmError,
+    expr::{BinaryOp, Expr},
+    get_verbs::{consume_whitespace, parse_operand::expect_char},
+    source_cursor::SourceCodeCursor,
+};
+
+// Reads expressions such as "table+2*SIZE", "(1<<4)|BIT0", "'a'" or "$-4". The operators have
+// the precedence that they have in C, from lowest to highest:
+//     |    &    << >>    + -    * /    unary - ~ +
+// Numbers can be decimal, hexadecimal with a 0x prefix, binary with a 0b prefix, or a character
+// in single quotes. Spaces are allowed around operators, but an expression ends at anything that
+// isn't an operator, such as a comma, the parenthesis of an indexed operand, or a comment.
+
+const BINARY_OPS: [&[(&str, BinaryOp)]; 5] = [
+    &[("|", BinaryOp::Or)],
+    &[("&", BinaryOp::And)],
+    &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[("*", BinaryOp::Mul), ("/", BinaryOp::Div)],
+];
+
+pub fn parse_expr(cursor: &mut SourceCodeCursor) -> Result<Expr, AsmError> {
+    parse_binary(cursor, 0)
+}
+
+pub fn parse_expr_str(s: &str) -> Result<Expr, String> {
+    // an expression that is the whole of s, such as an argument of a directive
+    let mut cursor = SourceCodeCursor::new(s.to_owned());
+    let expr = parse_expr(&mut cursor).map_err(|e| e.message)?;
+    consume_whitespace(&mut cursor);
+    match cursor.peek() {
+        None => Ok(expr),
+        Some(c) => Err(format!("unexpected {} in {}", c, s)),
+    }
+}
+
+fn parse_binary(cursor: &mut SourceCodeCursor, level: usize) -> Result<Expr, AsmError> {
+    // the operators of BINARY_OPS[level] and above, which are left associative
+    if level == BINARY_OPS.len() {
+        return parse_unary(cursor);
+    }
+    let mut expr = parse_binary(cursor, level + 1)?;
+    loop {
+        let col = cursor.get_col();
+        let op = match consume_op(cursor, BINARY_OPS[level]) {
+            Some(op) => op,
+            None => return Ok(expr),
+        };
+        let rhs = parse_binary(cursor, level + 1)?;
+        expr = fold(cursor, col, Expr::Binary(op, Box::new(expr), Box::new(rhs)))?;
+    }
+}
+
+fn parse_unary(cursor: &mut SourceCodeCursor) -> Result<Expr, AsmError> {
+    let col = cursor.get_col();
+    let expr = match cursor.peek() {
+        Some('-') | Some('~') | Some('+') => {
+            let op = cursor.next().unwrap();
+            consume_whitespace(cursor);
+            let e = parse_unary(cursor)?;
+            match op {
+                '-' => Expr::Neg(Box::new(e)),
+                '~' => Expr::Not(Box::new(e)),
+                _ => e,
+            }
+        }
+        Some('(') => {
+            cursor.next();
+            consume_whitespace(cursor);
+            let e = parse_expr(cursor)?;
+            consume_whitespace(cursor);
+            expect_char(cursor, ')')?;
+            e
+        }
+        Some('\'') => Expr::Num(parse_char(cursor)?),
+        Some(c) if c.is_ascii_digit() => Expr::Num(parse_num(cursor)?),
+        Some('$') if !cursor.peek_nth(2).is_some_and(is_label_char) => {
+            cursor.next();
+            Expr::Here
+        }
+        Some(c) if is_label_char(c) => {
+            let mut label = String::new();
+            while let Some(c) = cursor.peek().filter(|c| is_label_char(*c) || *c == '.') {
+                label.push(c);
+                cursor.next();
+            }
+            Expr::Label(label)
+        }
+        _ => {
+            return Err(cursor.make_error(col, "expected a number or a label".to_owned()));
+        }
+    };
+    fold(cursor, col, expr)
+}
+
+fn fold(cursor: &SourceCodeCursor, col: usize, expr: Expr) -> Result<Expr, AsmError> {
+    // works out the value of an operation on numbers, so that expressions that only have
+    // numbers in them become a number
+    let is_constant = match &expr {
+        Expr::Neg(e) | Expr::Not(e) => matches!(**e, Expr::Num(_)),
+        Expr::Binary(_, l, r) => matches!((&**l, &**r), (Expr::Num(_), Expr::Num(_))),
+        _ => false,
+    };
+    if !is_constant {
+        return Ok(expr);
+    }
+    match expr.eval(&|_| None, None) {
+        Ok(n) => Ok(Expr::Num(n)),
+        Err(message) => Err(cursor.make_error(col, message)),
+    }
+}
+
+fn consume_op(cursor: &mut SourceCodeCursor, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+    // the spaces before an operator are only consumed if there is an operator after them
+    let mut n = 1;
+    while matches!(cursor.peek_nth(n), Some(' ') | Some('\t')) {
+        n += 1;
+    }
+    for (s, op) in ops {
+        let is_match = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| cursor.peek_nth(n + i) == Some(c));
+        if is_match {
+            for _ in 0..n - 1 + s.len() {
+                cursor.next();
+            }
+            consume_whitespace(cursor);
+            return Some(*op);
+        }
+    }
+    None
+}
+
+fn parse_num(cursor: &mut SourceCodeCursor) -> Result<i64, AsmError> {
+    let col = cursor.get_col();
+    let mut s = String::new();
+    while let Some(c) = cursor.peek().filter(|c| c.is_ascii_alphanumeric()) {
+        s.push(c);
+        cursor.next();
+    }
+    let n = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b").or(s.strip_prefix("0B")) {
+        i64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    n.map_err(|_| cursor.make_error(col, format!("invalid number {}", s)))
+}
+
+fn parse_char(cursor: &mut SourceCodeCursor) -> Result<i64, AsmError> {
+    // a character in single quotes, such as 'a' or '\n'
+    let col = cursor.get_col();
+    let error = |cursor: &SourceCodeCursor| {
+        cursor.make_error(col, "expected a character such as 'a'".to_owned())
+    };
+    cursor.next();
+    let c = match cursor.peek() {
+        None | Some('\n') => return Err(error(cursor)),
+        Some('\\') => {
+            cursor.next();
+            match cursor.peek() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                None | Some('\n') => return Err(error(cursor)),
+                Some(c) => c,
+            }
+        }
+        Some(c) => c,
+    };
+    cursor.next();
+    if !c.is_ascii() || cursor.peek() != Some('\'') {
+        return Err(error(cursor));
+    }
+    cursor.next();
+    Ok(c as i64)
+}
+
+pub fn is_label_char(c: char) -> bool {
+    // labels from cl430 can have $ in them, such as $C$SL1
+    c.is_ascii_alphabetic() || c.is_ascii_digit() || c == '_' || c == '$'
+}
@@ -1,49 +1,107 @@
 use crate::{
+    asm_error::AsmError,
+    expr::Expr,
+    get_verbs::parse_expr::{is_label_char, parse_expr},
     operand::{Operand, Reg},
     source_cursor::SourceCodeCursor,
 };
 
-pub fn parse_operand(cursor: &mut SourceCodeCursor) -> Operand {
+pub fn parse_operand(cursor: &mut SourceCodeCursor) -> Result<Operand, AsmError> {
+    // offsets, addresses and immediates are expressions, which are kept in the operand when they
+    // depend on a label
+    let col = cursor.get_col();
     if let Some(r) = parse_reg(cursor) {
-        return Operand::Reg(r);
+        return Ok(Operand::Reg(r));
     }
-    if let Some(operand) = parse_imm(cursor) {
-        return operand;
+    if cursor.peek() == Some('#') {
+        cursor.next();
+        return match parse_expr(cursor)? {
+            Expr::Num(n) => Ok(Operand::Imm(to_word(cursor, col, n)?)),
+            expr => Ok(Operand::ImmLabel(expr)),
+        };
     }
     if cursor.peek() == Some('@') {
         cursor.next();
-        let reg = parse_reg(cursor).unwrap();
+        let reg = expect_reg(cursor)?;
         if cursor.peek() == Some('+') {
             cursor.next();
-            return Operand::IndirectAutoInc(reg);
+            return Ok(Operand::IndirectAutoInc(reg));
         } else {
-            return Operand::Indirect(reg);
+            return Ok(Operand::Indirect(reg));
         }
     }
 
     if cursor.peek() == Some('&') {
         cursor.next();
+        return match parse_expr(cursor)? {
+            Expr::Num(n) => Ok(Operand::Abs(to_word(cursor, col, n)?)),
+            expr => Ok(Operand::AbsLabel(expr)),
+        };
+    }
 
-        if cursor.begins_with("0x") {
-            cursor.next();
-            cursor.next();
-            let s = parse_label(cursor);
-            let imm_addr = u16::from_str_radix(&s, 16).unwrap();
-            return Operand::Abs(imm_addr);
-        }
+    let expr = parse_expr(cursor)?;
+    if cursor.peek() == Some('(') {
+        cursor.next();
+        let r = expect_reg(cursor)?;
+        expect_char(cursor, ')')?;
+        return match expr {
+            Expr::Num(n) => Ok(Operand::IndexedReg(r, to_word(cursor, col, n)? as i16)),
+            expr => Ok(Operand::IndexedLabel(r, expr)),
+        };
+    }
 
-        let s = parse_label(cursor);
-        assert_eq!(cursor.next().unwrap(), '+');
-        assert_eq!(cursor.next().unwrap(), '0');
-        return Operand::AbsLabel(s);
+    // symbolic mode, such as "MOV counter,r15"
+    match expr {
+        Expr::Label(label) if is_reg_name(&label) => Err(cursor.make_error(
+            col,
+            format!(
+                "can't use {}, the registers are r4 to r15, SP and SR",
+                label
+            ),
+        )),
+        // the memory at an address can be read in absolute mode, which is simpler
+        Expr::Num(n) => Ok(Operand::Abs(to_word(cursor, col, n)?)),
+        expr => Ok(Operand::Symbolic(expr)),
     }
+}
 
-    let offset: i16 = parse_signed_int(cursor).unwrap();
-    assert_eq!(cursor.next().unwrap(), '(');
-    let r = parse_reg(cursor).unwrap();
-    assert_eq!(cursor.next().unwrap(), ')');
+fn to_word(cursor: &SourceCodeCursor, col: usize, n: i64) -> Result<u16, AsmError> {
+    // negative numbers are written in two's complement
+    if n < i16::MIN as i64 || n > u16::MAX as i64 {
+        return Err(cursor.make_error(col, format!("{} doesn't fit in 16 bits", n)));
+    }
+    Ok(n as u16)
+}
 
-    return Operand::IndexedReg(r, offset);
+pub fn parse_dst_operand(cursor: &mut SourceCodeCursor) -> Result<Operand, AsmError> {
+    // the operands that a result can be written back to
+    let col = cursor.get_col();
+    let operand = parse_operand(cursor)?;
+    match operand {
+        Operand::Indirect(_)
+        | Operand::IndirectAutoInc(_)
+        | Operand::Imm(_)
+        | Operand::ImmLabel(_) => {
+            Err(cursor.make_error(col, format!("{} can't be a destination", operand)))
+        }
+        _ => Ok(operand),
+    }
+}
+
+pub fn expect_char(cursor: &mut SourceCodeCursor, c: char) -> Result<(), AsmError> {
+    // consumes c, or fails without moving the cursor
+    if cursor.peek() != Some(c) {
+        return Err(cursor.make_error(cursor.get_col(), format!("expected {}", c)));
+    }
+    cursor.next();
+    Ok(())
+}
+
+fn expect_reg(cursor: &mut SourceCodeCursor) -> Result<Reg, AsmError> {
+    match parse_reg(cursor) {
+        Some(reg) => Ok(reg),
+        None => Err(cursor.make_error(cursor.get_col(), "expected a register".to_owned())),
+    }
 }
 
 pub fn parse_reg(cursor: &mut SourceCodeCursor) -> Option<Reg> {
@@ -91,87 +149,30 @@ pub fn parse_reg(cursor: &mut SourceCodeCursor) -> Option<Reg> {
         }
     }
 
-    if is_three_char_reg {
-        cursor.next();
-        cursor.next();
-        cursor.next();
+    let len = if is_three_char_reg {
+        3
     } else if is_two_char_reg {
-        cursor.next();
-        cursor.next();
-    }
-
-    return res;
-}
-
-fn parse_imm(cursor: &mut SourceCodeCursor) -> Option<Operand> {
-    // tries to parse an imm such as "#13". moves the cursor only if
-    // successfully parsed.
-    let mut local_cursor = cursor.clone();
-
-    if local_cursor.next() != Some('#') {
+        2
+    } else {
         return None;
-    }
-
-    let mut imm_str = String::new();
-    while local_cursor.peek().is_some()
-        && (local_cursor.peek().unwrap().is_ascii_alphanumeric()
-            || local_cursor.peek().unwrap() == '_'
-            || local_cursor.peek().unwrap() == '-')
-    {
-        imm_str.push(local_cursor.next().unwrap());
-    }
-    let imm_u16 = u16::from_str_radix(&imm_str, 10);
-
-    match imm_u16 {
-        Ok(x) => {
-            *cursor = local_cursor;
-            return Some(Operand::Imm(x));
-        }
-        Err(_) => {}
     };
-    let imm_i16 = i16::from_str_radix(&imm_str, 10);
-    match imm_i16 {
-        Ok(x) => {
-            *cursor = local_cursor;
-            return Some(Operand::Imm(x as u16));
-        }
-        Err(_) => {
-            return Some(Operand::ImmLabel(imm_str));
-        }
-    }
-}
-
-fn parse_signed_int(cursor: &mut SourceCodeCursor) -> Option<i16> {
-    // tries to parse an imm such as "-4". moves the cursor only if
-    // successfully parsed.
-    let mut local_cursor = cursor.clone();
 
-    let mut signed_int_str = String::new();
-    while local_cursor.peek().is_some()
-        && (local_cursor.peek().unwrap().is_ascii_digit() || local_cursor.peek().unwrap() == '-')
-    {
-        signed_int_str.push(local_cursor.next().unwrap());
+    // a label that starts with a register name, such as SPEED or r4count, isn't a register
+    if cursor.peek_nth(len + 1).is_some_and(is_label_char) {
+        return None;
     }
-    let signed_int = i16::from_str_radix(&signed_int_str, 10);
-
-    match signed_int {
-        Ok(x) => {
-            *cursor = local_cursor;
-            return Some(x);
-        }
-        Err(_) => {
-            return None;
-        }
+    for _ in 0..len {
+        cursor.next();
     }
-}
 
-fn parse_label(cursor: &mut SourceCodeCursor) -> String {
-    let mut res = String::new();
-    while cursor.peek().is_some()
-        && (cursor.peek().unwrap().is_ascii_alphanumeric() || cursor.peek().unwrap() == '_')
-    {
-        res.push(cursor.next().unwrap());
-    }
+    return res;
+}
 
-    res
+fn is_reg_name(s: &str) -> bool {
+    // registers that parse_reg doesn't read, which would otherwise be taken for labels
+    let lower = s.to_ascii_lowercase();
+    matches!(lower.as_str(), "pc" | "sp" | "sr" | "cg")
+        || lower
+            .strip_prefix('r')
+            .is_some_and(|n| n.parse::<u8>().is_ok_and(|n| n < 16))
 }
@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
 
+use msp_emu::emulator::fault::CpuFault;
+
 pub async fn draw_monitor(x: f32, y: f32, w: f32, h: f32, buf: &[u8]) {
     let pixel_width = w / 160.0;
     let pixel_height = h / 120.0;
@@ -81,3 +83,37 @@ pub async fn get_curr_button_states() -> u8 {
 
     states
 }
+
+pub async fn draw_fault(x: f32, y: f32, fault: &CpuFault, regs: &[u16]) {
+    let line_height = 20.0;
+    draw_rectangle(x, y, 420.0, line_height * 7.0, MAROON);
+
+    draw_text(
+        &format!("CPU fault: {}", fault),
+        x + 10.0,
+        y + line_height,
+        20.0,
+        WHITE,
+    );
+    for row in 0..4 {
+        let mut line = String::new();
+        for col in 0..4 {
+            let reg = row * 4 + col;
+            line.push_str(&format!("R{:<2} {:04X}   ", reg, regs[reg]));
+        }
+        draw_text(
+            &line,
+            x + 10.0,
+            y + line_height * (row as f32 + 2.5),
+            20.0,
+            WHITE,
+        );
+    }
+    draw_text(
+        "press F9 to load the quick save",
+        x + 10.0,
+        y + line_height * 6.5,
+        20.0,
+        WHITE,
+    );
+}
@@ -0,0 +1,16 @@
+#![feature(bigint_helper_methods)]
+pub mod asm_error;
+pub mod asm_line;
+#[cfg(test)]
+mod asm_tests;
+pub mod byte_generator;
+pub mod ccode;
+pub mod debug_info;
+pub mod disassembler;
+pub mod emulator;
+pub mod expr;
+pub mod get_verbs;
+pub mod listing;
+pub mod operand;
+pub mod source_cursor;
+pub mod source_map;
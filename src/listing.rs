@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::{get_verbs::Global, source_map::SourceMap};
+
+// Writes the map and listing files that go alongside the assembled program, for going from
+// addresses (seen on a logic analyzer, or in a trace) back to code.
+// The map file has one line per function and global variable. Labels starting with $ are
+// generated by the compiler for jump targets inside functions, so they are left out, and the
+// size of a function runs up to the next function.
+// The listing file has one line per source line, with the address and encoded words of the
+// instruction on it, if any.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u16,
+    pub size: u16, // in bytes
+    pub kind: SymbolKind,
+}
+
+pub fn get_symbol_table(
+    globals: &[Global],
+    symbols: &HashMap<String, u16>,
+    program_len: usize,
+) -> Vec<Symbol> {
+    // symbols and program_len come from generate_bytes_and_symbols, given the same globals
+    let global_sizes: HashMap<&str, usize> = globals
+        .iter()
+        .map(|g| (g.label.as_str(), g.initial_bytes.len()))
+        .collect();
+    let mut function_addrs: Vec<u16> = symbols
+        .iter()
+        .filter(|(name, _)| !global_sizes.contains_key(name.as_str()) && !name.starts_with('$'))
+        .map(|(_, addr)| *addr)
+        .collect();
+    function_addrs.sort();
+
+    let mut table: Vec<Symbol> = symbols
+        .iter()
+        .filter(|(name, _)| !name.starts_with('$'))
+        .map(|(name, addr)| {
+            let (size, kind) = match global_sizes.get(name.as_str()) {
+                Some(size) => (*size, SymbolKind::Global),
+                None => {
+                    let end = match function_addrs.iter().find(|a| **a > *addr) {
+                        Some(next_addr) => *next_addr as usize,
+                        None => program_len,
+                    };
+                    (end - *addr as usize, SymbolKind::Function)
+                }
+            };
+            Symbol {
+                name: name.clone(),
+                addr: *addr,
+                size: size as u16,
+                kind,
+            }
+        })
+        .collect();
+    table.sort_by(|a, b| a.addr.cmp(&b.addr).then(a.name.cmp(&b.name)));
+    table
+}
+
+pub fn write_map(out: &mut dyn Write, symbol_table: &[Symbol]) -> io::Result<()> {
+    writeln!(out, "address    size  kind      symbol")?;
+    for symbol in symbol_table {
+        let kind = match symbol.kind {
+            SymbolKind::Function => "function",
+            SymbolKind::Global => "global",
+        };
+        writeln!(
+            out,
+            "{:04X}     {:>6}  {:<8}  {}",
+            symbol.addr, symbol.size, kind, symbol.name
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_listing(out: &mut dyn Write, source_map: &SourceMap, bytes: &[u8]) -> io::Result<()> {
+    // the length of an instruction is found from where the next one starts
+    let mut addrs: Vec<u16> = source_map
+        .get_instr_lines()
+        .iter()
+        .map(|l| l.addr)
+        .collect();
+    addrs.sort();
+    let mut words_by_line: HashMap<usize, (u16, Vec<String>)> = HashMap::new();
+    for instr_line in source_map.get_instr_lines() {
+        let addr = instr_line.addr as usize;
+        let end = match addrs.iter().find(|a| **a > instr_line.addr) {
+            Some(next_addr) => *next_addr as usize,
+            None => bytes.len(),
+        };
+        let words = bytes[addr..end]
+            .chunks(2)
+            .map(|w| format!("{:04X}", u16::from_le_bytes([w[0], w[1]])))
+            .collect();
+        words_by_line.insert(instr_line.line_num, (instr_line.addr, words));
+    }
+
+    for (i, text) in source_map.get_source_lines().iter().enumerate() {
+        let line_num = i + 1;
+        match words_by_line.get(&line_num) {
+            Some((addr, words)) => writeln!(
+                out,
+                "{:>5}  {:04X}  {:<14}  {}",
+                line_num,
+                addr,
+                words.join(" "),
+                text
+            )?,
+            None => writeln!(out, "{:>5}  {:4}  {:<14}  {}", line_num, "", "", text)?,
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,8 @@
-#![feature(bigint_helper_methods)]
-pub mod asm_line;
-pub mod byte_generator;
-pub mod ccode;
-pub mod emulator;
-pub mod get_verbs;
+pub mod debug_overlay;
 pub mod graphics;
-pub mod operand;
-pub mod source_cursor;
 
-use graphics::{draw_leds, draw_monitor, draw_switches, get_curr_button_states};
+use debug_overlay::DebugOverlay;
+use graphics::{draw_fault, draw_leds, draw_monitor, draw_switches, get_curr_button_states};
 use macroquad::prelude::*;
 use std::fs::File;
 use std::io::Read;
@@ -17,19 +11,43 @@ use std::process::exit;
 use std::process::Command;
 use std::str;
 
-use crate::byte_generator::generate_bytes;
-use crate::emulator::Emulator;
+use msp_emu::byte_generator::generate_bytes_symbols_and_addrs;
+use msp_emu::emulator::Emulator;
+use msp_emu::get_verbs;
+use msp_emu::listing::{get_symbol_table, write_listing, write_map};
+use msp_emu::source_map::SourceMap;
 
 const C_FILE_NAME: &str = "./main.c";
 const GENERATED_ASM_NAME: &str = "./main.asm";
 const OUTPUT_FILE_NAME: &str = "seq.code";
+const MAP_FILE_NAME: &str = "seq.map";
+const LISTING_FILE_NAME: &str = "seq.lst";
+const QUICKSAVE_FILE_NAME: &str = "quicksave.snap";
 
-#[macroquad::main("Assembler Emulator")]
+// clock of the FPGA board. can be overridden with --clock_hz=<frequency>
+const DEFAULT_CLOCK_FREQ_HZ: u64 = 25_000_000;
+// frames that take longer than this (e.g. while the window is being dragged) are not caught up on
+const MAX_FRAME_TIME: f32 = 0.1;
+
+// wide and tall enough for the board and the debug panels beside and below it
+const WINDOW_WIDTH: i32 = 1280;
+const WINDOW_HEIGHT: i32 = 750;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "Assembler Emulator".to_owned(),
+        window_width: WINDOW_WIDTH,
+        window_height: WINDOW_HEIGHT,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
 async fn main() {
     Command::new("/Applications/ti/ccs1220/ccs/tools/compiler/ti-cgt-msp430_21.6.1.LTS/bin/cl430")
         .args([
             "--asm_listing",
-            "--symdebug:none",
+            "--symdebug:dwarf",
             "--use_hw_mpy=none",
             "--opt_level=off",
         ])
@@ -41,7 +59,7 @@ async fn main() {
     )
     .args([
         "--skip_assembler",
-        "--symdebug:none",
+        "--symdebug:dwarf",
         "--use_hw_mpy=none",
         "--opt_level=off",
     ])
@@ -61,22 +79,86 @@ async fn main() {
         .read_to_string(&mut asm_contents)
         .expect(&format!("error reading file: {}", GENERATED_ASM_NAME));
 
-    let (globals, lines) = get_verbs::get_tokens(asm_contents);
+    let (globals, lines, line_nums) =
+        match get_verbs::try_get_tokens(GENERATED_ASM_NAME, asm_contents.clone()) {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for error in &errors {
+                    println!("{}\n", error);
+                }
+                println!("Assembly failed. Exiting.");
+                exit(1);
+            }
+        };
 
-    let bytes = generate_bytes(globals, lines);
+    let (bytes, symbols, addrs) =
+        match generate_bytes_symbols_and_addrs(globals.clone(), lines.clone(), &line_nums) {
+            Ok(program) => program,
+            Err(mut errors) => {
+                for error in &mut errors {
+                    error.set_source(GENERATED_ASM_NAME, &asm_contents);
+                    println!("{}\n", error);
+                }
+                println!("Assembly failed. Exiting.");
+                exit(1);
+            }
+        };
     write_bytes_to_file(&bytes);
     println!("Wrote {} bytes to file {}", bytes.len(), OUTPUT_FILE_NAME);
+    let mut f = File::create(MAP_FILE_NAME).expect("error creating map file.");
+    write_map(&mut f, &get_symbol_table(&globals, &symbols, bytes.len()))
+        .expect("error writing to map file");
+    let source_map = SourceMap::new(&asm_contents, &lines, &line_nums, &addrs);
+    let mut f = File::create(LISTING_FILE_NAME).expect("error creating listing file.");
+    write_listing(&mut f, &source_map, &bytes).expect("error writing to listing file");
+    println!("Wrote {} and {}", MAP_FILE_NAME, LISTING_FILE_NAME);
     let mut emulator = Emulator::new(&bytes);
+    let clock_freq_hz = get_clock_freq_hz();
 
     let mut curr_switch_states = 0u16;
+    let mut fault = None; // once the cpu faults, it stays stopped until a snapshot is loaded
+    let mut debug_overlay = DebugOverlay::new(symbols);
 
     loop {
-        emulator.run_some_instrs();
+        let frame_time = get_frame_time().min(MAX_FRAME_TIME) as f64;
+        if fault.is_none() && !debug_overlay.is_paused() {
+            if let Err(f) = emulator.run_cycles((clock_freq_hz as f64 * frame_time) as u64) {
+                println!("CPU fault: {}\n{:?}", f, emulator);
+                fault = Some(f);
+            }
+        }
+        if fault.is_none() {
+            if let Err(f) = debug_overlay.handle_input(&mut emulator) {
+                println!("CPU fault: {}\n{:?}", f, emulator);
+                fault = Some(f);
+            }
+        }
         clear_background(LIGHTGRAY);
 
         let gfx_buf = emulator.get_gfx_buffer();
 
         draw_monitor(10.0, 10.0, 640.0, 480.0, gfx_buf).await;
+        debug_overlay.draw(&emulator);
+        if let Some(f) = &fault {
+            draw_fault(20.0, 20.0, f, emulator.get_regs()).await;
+        }
+
+        if is_key_pressed(KeyCode::F5) {
+            match emulator.save_snapshot_to_file(QUICKSAVE_FILE_NAME) {
+                Ok(()) => println!("Saved snapshot to {}", QUICKSAVE_FILE_NAME),
+                Err(e) => println!("Could not save snapshot: {}", e),
+            }
+        }
+        if is_key_pressed(KeyCode::F9) {
+            match emulator.load_snapshot_from_file(QUICKSAVE_FILE_NAME) {
+                Ok(()) => {
+                    println!("Loaded snapshot from {}", QUICKSAVE_FILE_NAME);
+                    curr_switch_states = emulator.get_switch_states();
+                    fault = None;
+                }
+                Err(e) => println!("Could not load snapshot: {}", e),
+            }
+        }
 
         draw_leds(10.0, 500.0, emulator.get_led_output()).await;
         draw_switches(10.0, 520.0, &mut curr_switch_states).await;
@@ -87,6 +169,21 @@ async fn main() {
     }
 }
 
+fn get_clock_freq_hz() -> u64 {
+    for arg in std::env::args() {
+        if let Some(freq) = arg.strip_prefix("--clock_hz=") {
+            return match freq.parse() {
+                Ok(freq) => freq,
+                Err(_) => {
+                    println!("invalid clock frequency: {}", freq);
+                    exit(1);
+                }
+            };
+        }
+    }
+    DEFAULT_CLOCK_FREQ_HZ
+}
+
 fn write_bytes_to_file(bytes: &Vec<u8>) {
     let mut f = File::create(OUTPUT_FILE_NAME).expect("error creating output file.");
     for byte in bytes {
@@ -1,14 +1,19 @@
+use std::fmt;
+
+use crate::expr::Expr;
+
 #[derive(Debug, Clone)]
 pub enum Operand {
-    // NOTE: "symbolic mode" is not supported
     Reg(Reg),
     IndexedReg(Reg, i16),
+    IndexedLabel(Reg, Expr), // offset that depends on a label, such as table+2(r15)
     Abs(u16),
-    AbsLabel(String), // label, used for global variables
+    AbsLabel(Expr), // address that depends on a label, used for global variables
     Indirect(Reg),
     IndirectAutoInc(Reg),
     Imm(u16),
-    ImmLabel(String), // label, used for calling functions
+    ImmLabel(Expr), // value that depends on a label, used for calling functions
+    Symbolic(Expr), // address of a label, addressed by its offset from PC
 }
 
 impl Operand {
@@ -23,8 +28,8 @@ impl Operand {
             Operand::Imm(8) => 0x3,
             Operand::Imm(u16::MAX) => 0x3,
 
-            Operand::IndexedReg(_, _) => 0x1,
-            Operand::Abs(_) | Operand::AbsLabel(_) => 0x1,
+            Operand::IndexedReg(_, _) | Operand::IndexedLabel(_, _) => 0x1,
+            Operand::Abs(_) | Operand::AbsLabel(_) | Operand::Symbolic(_) => 0x1,
             Operand::Indirect(_) => 0x2,
             Operand::IndirectAutoInc(_) => 0x3,
             Operand::Imm(_) | Operand::ImmLabel(_) => 0x3,
@@ -35,8 +40,8 @@ impl Operand {
     pub fn to_ad_bit(&self) -> u16 {
         let bits: u16 = match self {
             Operand::Reg(_) => 0x0,
-            Operand::IndexedReg(_, _) => 0x1,
-            Operand::Abs(_) | Operand::AbsLabel(_) => 0x01,
+            Operand::IndexedReg(_, _) | Operand::IndexedLabel(_, _) => 0x1,
+            Operand::Abs(_) | Operand::AbsLabel(_) | Operand::Symbolic(_) => 0x01,
 
             Operand::Indirect(_)
             | Operand::IndirectAutoInc(_)
@@ -48,7 +53,7 @@ impl Operand {
         return bits << 7;
     }
 
-    pub fn get_imm_word(&self) -> (Option<u16>, Option<String>) {
+    pub fn get_imm_word(&self) -> (Option<u16>, Option<Expr>) {
         match self {
             Operand::Imm(0)
             | Operand::Imm(1)
@@ -60,8 +65,11 @@ impl Operand {
             Operand::Reg(_) | Operand::Indirect(_) | Operand::IndirectAutoInc(_) => {}
             Operand::IndexedReg(_, offset) => return (Some(*offset as u16), None),
             Operand::Abs(imm) | Operand::Imm(imm) => return (Some(*imm), None),
-            Operand::AbsLabel(label) | Operand::ImmLabel(label) => {
-                return (Some(0), Some(label.clone()));
+            Operand::IndexedLabel(_, expr)
+            | Operand::AbsLabel(expr)
+            | Operand::ImmLabel(expr)
+            | Operand::Symbolic(expr) => {
+                return (Some(0), Some(expr.clone()));
             }
         };
         return (None, None);
@@ -78,10 +86,30 @@ impl Operand {
 
             Operand::Reg(r)
             | Operand::IndexedReg(r, _)
+            | Operand::IndexedLabel(r, _)
             | Operand::Indirect(r)
             | Operand::IndirectAutoInc(r) => r.to_bits(),
             Operand::Abs(_) | Operand::AbsLabel(_) => 0x2,
-            Operand::Imm(_) | Operand::ImmLabel(_) => 0x0,
+            Operand::Imm(_) | Operand::ImmLabel(_) | Operand::Symbolic(_) => 0x0,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // immediates and offsets are written in decimal, since that is what the assembler reads
+        match self {
+            Operand::Reg(r) => write!(f, "{}", r),
+            Operand::IndexedReg(r, offset) => write!(f, "{}({})", offset, r),
+            Operand::IndexedLabel(r, expr) => write!(f, "{}({})", expr, r),
+            Operand::Abs(addr) => write!(f, "&0x{:04X}", addr),
+            Operand::AbsLabel(expr) => write!(f, "&{}", expr),
+            Operand::Indirect(r) => write!(f, "@{}", r),
+            Operand::IndirectAutoInc(r) => write!(f, "@{}+", r),
+            Operand::Imm(u16::MAX) => write!(f, "#-1"),
+            Operand::Imm(imm) => write!(f, "#{}", imm),
+            Operand::ImmLabel(expr) => write!(f, "#{}", expr),
+            Operand::Symbolic(expr) => write!(f, "{}", expr),
         }
     }
 }
@@ -130,4 +158,36 @@ impl Reg {
             Reg::R15 => 0xF,
         }
     }
+
+    pub fn from_bits(bits: u16) -> Reg {
+        match bits & 0xF {
+            0x0 => Reg::PC,
+            0x1 => Reg::SP,
+            0x2 => Reg::SR,
+            0x3 => Reg::CG,
+            0x4 => Reg::R4,
+            0x5 => Reg::R5,
+            0x6 => Reg::R6,
+            0x7 => Reg::R7,
+            0x8 => Reg::R8,
+            0x9 => Reg::R9,
+            0xA => Reg::R10,
+            0xB => Reg::R11,
+            0xC => Reg::R12,
+            0xD => Reg::R13,
+            0xE => Reg::R14,
+            _ => Reg::R15,
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reg::PC => write!(f, "PC"),
+            Reg::SP => write!(f, "SP"),
+            Reg::SR => write!(f, "SR"),
+            r => write!(f, "r{}", r.to_bits()),
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::asm_error::AsmError;
+
 #[derive(Debug, Clone)]
 pub struct SourceCodeCursor {
     contents: Vec<char>,
@@ -16,6 +18,35 @@ impl SourceCodeCursor {
         }
     }
 
+    pub fn get_line(&self) -> usize {
+        // the line of the next character, counting from 1
+        self.curr_line
+    }
+
+    pub fn get_col(&self) -> usize {
+        // the column of the next character, counting from 1
+        self.curr_col + 1
+    }
+
+    pub fn make_error(&self, col: usize, message: String) -> AsmError {
+        // an error at col of the current line. get_tokens fills in the file name
+        let line_start = self.contents[..self.index]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |i| i + 1);
+        let line_end = self.contents[self.index..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.contents.len(), |i| self.index + i);
+        AsmError {
+            file_name: String::new(),
+            line: self.curr_line,
+            col,
+            snippet: self.contents[line_start..line_end].iter().collect(),
+            message,
+        }
+    }
+
     pub fn peek(&self) -> Option<char> {
         self.contents.get(self.index).copied()
     }
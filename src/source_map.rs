@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use crate::asm_line::AsmLine;
+use crate::ccode::CC;
+use crate::get_verbs::debug_directives::parse_line_directive;
+
+// Maps the instructions of an assembled program back to the lines of its assembly source, and
+// from there to the lines of the C source when the compiler marked them. cl430 marks the code
+// generated for each C statement with a comment if given --c_src_interlist, as in
+//     ;  42 | cursor_location = 0;
+// or with a line directive if given --symdebug:dwarf, as in
+//     .dwpsn file "main.c",line 42,column 5,is_stmt,isa 0
+// Lines from other files (such as inline functions from headers) are not mapped.
+
+#[derive(Debug, Clone)]
+pub struct InstrLine {
+    pub addr: u16,
+    pub line_num: usize, // counting from 1
+    pub is_branch: bool, // whether it is a conditional jump
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    source_lines: Vec<String>,
+    instr_lines: Vec<InstrLine>,
+    addr_lines: HashMap<u16, usize>, // the source line of the instruction at each address
+    c_lines: Vec<Option<usize>>,     // the C line of every source line
+    c_file_name: Option<String>,     // the file named by the first line directive
+}
+
+impl SourceMap {
+    pub fn new(source: &str, lines: &[AsmLine], line_nums: &[usize], addrs: &[u16]) -> Self {
+        // takes the output of get_tokens_with_line_nums and generate_bytes_symbols_and_addrs
+        let instr_lines = lines
+            .iter()
+            .zip(line_nums.iter().zip(addrs))
+            .filter(|(line, _)| !matches!(line, AsmLine::Label(_)))
+            .map(|(line, (line_num, addr))| InstrLine {
+                addr: *addr,
+                line_num: *line_num,
+                is_branch: matches!(line, AsmLine::Jump(cc, _) if !matches!(cc, CC::Unconditional)),
+            })
+            .collect::<Vec<InstrLine>>();
+        let addr_lines = instr_lines.iter().map(|l| (l.addr, l.line_num)).collect();
+
+        let source_lines: Vec<String> = source.lines().map(String::from).collect();
+        let mut c_lines = Vec::new();
+        let mut c_file_name: Option<String> = None;
+        let mut curr_c_line = None;
+        for line in &source_lines {
+            if let Some(c_line) = parse_interlist_comment(line) {
+                curr_c_line = Some(c_line);
+            } else if let Some((file_name, c_line)) = parse_line_directive(line) {
+                let c_file_name = c_file_name.get_or_insert(file_name.clone());
+                curr_c_line = (file_name == *c_file_name).then_some(c_line);
+            }
+            c_lines.push(curr_c_line);
+        }
+
+        SourceMap {
+            source_lines,
+            instr_lines,
+            addr_lines,
+            c_lines,
+            c_file_name,
+        }
+    }
+
+    pub fn get_source_lines(&self) -> &[String] {
+        &self.source_lines
+    }
+
+    pub fn get_instr_lines(&self) -> &[InstrLine] {
+        &self.instr_lines
+    }
+
+    pub fn get_c_line(&self, line_num: usize) -> Option<usize> {
+        self.c_lines
+            .get(line_num.checked_sub(1)?)
+            .copied()
+            .flatten()
+    }
+
+    pub fn has_c_lines(&self) -> bool {
+        self.c_lines.iter().any(|c_line| c_line.is_some())
+    }
+
+    pub fn get_c_file_name(&self) -> Option<&str> {
+        // only known if the C lines came from line directives
+        self.c_file_name.as_deref()
+    }
+
+    pub fn get_c_line_at(&self, addr: u16) -> Option<usize> {
+        // the C line of the instruction at addr
+        self.get_c_line(*self.addr_lines.get(&addr)?)
+    }
+
+    pub fn format_c_location(&self, addr: u16) -> Option<String> {
+        // such as main.c:42, or line 42 if the file is not known
+        let c_line = self.get_c_line_at(addr)?;
+        match &self.c_file_name {
+            Some(file_name) => Some(format!("{}:{}", file_name, c_line)),
+            None => Some(format!("line {}", c_line)),
+        }
+    }
+
+    pub fn find_c_line_addr(&self, c_line: usize) -> Option<u16> {
+        // the address of the first instruction generated for a C line
+        self.instr_lines
+            .iter()
+            .find(|l| self.get_c_line(l.line_num) == Some(c_line))
+            .map(|l| l.addr)
+    }
+}
+
+fn parse_interlist_comment(line: &str) -> Option<usize> {
+    let comment = line.trim_start().strip_prefix(';')?;
+    let (c_line, _) = comment.split_once('|')?;
+    c_line.trim().parse().ok()
+}